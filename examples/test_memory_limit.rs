@@ -1,3 +1,6 @@
+// 古いサンプルなので書き方は変えず、スタイルの指摘だけ許可する
+#![allow(clippy::empty_line_after_doc_comments, clippy::field_reassign_with_default, clippy::manual_is_multiple_of)]
+
 /// Memory limit test program
 
 use std::sync::atomic::AtomicBool;
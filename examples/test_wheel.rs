@@ -1,3 +1,6 @@
+// 古いサンプルなので書き方は変えず、スタイルの指摘だけ許可する
+#![allow(clippy::empty_line_after_doc_comments, clippy::field_reassign_with_default)]
+
 /// ホイール構造のテストプログラム（CLI専用）
 
 use std::sync::atomic::AtomicBool;
@@ -23,6 +26,7 @@ fn test_wheel(wheel_type: WheelType, name: &str) {
         WheelType::Odd => "test_odd",
         WheelType::Mod6 => "test_mod6",
         WheelType::Mod30 => "test_mod30",
+        WheelType::Mod210 => "test_mod210",
     };
     
     // 一時ディレクトリを作成
@@ -111,6 +115,7 @@ fn main() {
     test_wheel(WheelType::Odd, "Odd (奇数のみ) - メモリ1/2");
     test_wheel(WheelType::Mod6, "Mod6 (6k±1) - メモリ1/3");
     test_wheel(WheelType::Mod30, "Mod30 (30周期) - メモリ8/30");
+    test_wheel(WheelType::Mod210, "Mod210 (210周期) - メモリ48/210");
     
     println!("\n========================================");
     println!("全テスト完了");
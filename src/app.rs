@@ -63,6 +63,18 @@ impl App for MyApp {
                     WorkerMessage::MemUsage(mem) => {
                         self.mem_usage = mem;
                     }
+                    WorkerMessage::ProcessMemUsage(mem) => {
+                        self.process_mem_usage = mem;
+                    }
+                    WorkerMessage::Paused(paused) => {
+                        self.is_paused = paused;
+                        if paused {
+                            self.eta = "Paused".to_string();
+                            self.log.push_str("Paused.\n");
+                        } else {
+                            self.log.push_str("Resumed.\n");
+                        }
+                    }
                     WorkerMessage::Done => {
                         self.is_running = false;
                         self.explore_running = false;
@@ -5,6 +5,7 @@
 //! - `MyApp::new` による初期化
 
 use std::collections::HashMap;
+use std::path::Path;
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 
@@ -12,7 +13,8 @@ use eframe::CreationContext;
 use sysinfo::System;
 
 use crate::app_style::setup_style;
-use crate::config::{load_or_create_config, Config, OutputFormat, WheelType};
+use crate::config::{Config, LayeredConfig, OutputFormat, SieveEngineKind, WheelType};
+use crate::engine_types::PauseFlag;
 use crate::ui_components::ZoomPanState;
 
 /// アプリケーションのタブ（Generator / Explore / Gap / Density / Spiral）
@@ -38,6 +40,8 @@ pub enum SpiralGridShape {
 
 pub struct MyApp {
     pub config: Config,
+    /// 起動時に読み込んだ設定の層（保存先と各値の出どころ）
+    pub settings: LayeredConfig,
     pub is_running: bool,
     pub log: String,
     pub receiver: Option<std::sync::mpsc::Receiver<crate::worker_message::WorkerMessage>>,
@@ -45,8 +49,12 @@ pub struct MyApp {
     pub prime_min_input: String,
     pub prime_max_input: String,
     pub split_count_input: String,
+    pub split_width_input: String,
+    pub index_stride_input: String,
     pub segment_size_input: String,
     pub writer_buffer_size_input: String,
+    pub pipeline_depth_input: String,
+    pub threads_input: String,
 
     /// Generator / π(x) 用の進捗（0.0〜1.0）
     pub progress: f32,
@@ -59,7 +67,13 @@ pub struct MyApp {
 
     pub eta: String,
     pub mem_usage: u64,
+    /// このプロセスの RSS（バイト）
+    pub process_mem_usage: u64,
     pub stop_flag: Arc<AtomicBool>,
+    /// 実行中のワーカーを一時停止させるフラグ（Pause / Resume ボタンで切り替える）
+    pub pause_flag: Arc<PauseFlag>,
+    /// ワーカーが一時停止中であることを通知してきたか（`WorkerMessage::Paused`）
+    pub is_paused: bool,
 
     pub total_mem: u64,
     pub current_processed: u64,
@@ -70,8 +84,13 @@ pub struct MyApp {
     pub last_prime_only: bool,
 
     pub selected_wheel_type: WheelType,
+    pub selected_engine: SieveEngineKind,
     pub memory_usage_percent_input: String,
+    pub memory_ceiling_mb_input: String,
     pub use_timestamp_prefix: bool,
+    pub resume: bool,
+    pub delete_partials_on_stop: bool,
+    pub auto_segment_size: bool,
 
     pub show_advanced_options: bool,
 
@@ -161,19 +180,30 @@ pub enum ExploreGraphMode {
 }
 
 impl MyApp {
-    pub fn new(cc: &CreationContext<'_>) -> Self {
-        let config = load_or_create_config().unwrap_or_default();
+    /// `config_path` は `--config` で指定された設定ファイル（なければ `settings.toml`）。
+    pub fn new(cc: &CreationContext<'_>, config_path: Option<&Path>) -> Self {
+        let mut log = String::new();
+        let settings = LayeredConfig::load_or_create(config_path).unwrap_or_else(|e| {
+            log.push_str(&format!("Failed to load settings: {e}\n"));
+            LayeredConfig::defaults()
+        });
+        let config = settings.config.clone();
 
         let mut sys = System::new_all();
         sys.refresh_all();
-        let total_mem = sys.total_memory(); // KB
+        let total_mem = sys.total_memory(); // バイト
 
         let selected_format = config.output_format;
         let output_dir_input = config.output_dir.clone();
         let last_prime_only = config.last_prime_only;
         let selected_wheel_type = config.wheel_type;
+        let selected_engine = config.engine;
         let memory_usage_percent_input = config.memory_usage_percent.to_string();
+        let memory_ceiling_mb_input = config.memory_ceiling_mb.to_string();
         let use_timestamp_prefix = config.use_timestamp_prefix;
+        let resume = config.resume;
+        let delete_partials_on_stop = config.delete_partials_on_stop;
+        let auto_segment_size = config.auto_segment_size;
 
         // Apple 風のミニマルなダークモード UI
         setup_style(&cc.egui_ctx);
@@ -182,12 +212,17 @@ impl MyApp {
             prime_min_input: config.prime_min.to_string(),
             prime_max_input: config.prime_max.to_string(),
             split_count_input: config.split_count.to_string(),
+            split_width_input: config.split_width.to_string(),
+            index_stride_input: config.index_stride.to_string(),
             segment_size_input: config.segment_size.to_string(),
             writer_buffer_size_input: config.writer_buffer_size.to_string(),
+            pipeline_depth_input: config.pipeline_depth.to_string(),
+            threads_input: config.threads.to_string(),
 
             config,
+            settings,
             is_running: false,
-            log: String::new(),
+            log,
             receiver: None,
 
             progress: 0.0,
@@ -196,7 +231,10 @@ impl MyApp {
             density_progress: 0.0,
             eta: "N/A".to_string(),
             mem_usage: 0,
+            process_mem_usage: 0,
             stop_flag: Arc::new(AtomicBool::new(false)),
+            pause_flag: Arc::new(PauseFlag::new()),
+            is_paused: false,
 
             total_mem,
             current_processed: 0,
@@ -207,8 +245,13 @@ impl MyApp {
             last_prime_only,
 
             selected_wheel_type,
+            selected_engine,
             memory_usage_percent_input,
+            memory_ceiling_mb_input,
             use_timestamp_prefix,
+            resume,
+            delete_partials_on_stop,
+            auto_segment_size,
 
             show_advanced_options: false,
 
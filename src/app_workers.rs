@@ -8,15 +8,9 @@
 use std::sync::atomic::Ordering;
 use std::sync::mpsc;
 
-use chrono::Local;
-
-use crate::config::save_config;
-use crate::cpu_engine::generate_primes_cpu;
-use crate::engine_types::{PrimeResult, Progress};
-use crate::output::{FilePrimeWriter, LastPrimeWriter, OutputMetadata};
-use crate::prime_pi_engine::{compute_prime_pi, PRIMECOUNT_MODE, PRIMECOUNT_VERSION};
-use crate::verify::{verify_primes_file, LogCallback};
-use crate::worker_message::{format_eta, WorkerMessage};
+use crate::engine_types::PrimeResult;
+use crate::prime_pi_engine::compute_prime_pi;
+use crate::worker_message::WorkerMessage;
 
 use crate::app_state::MyApp;
 
@@ -72,12 +66,15 @@ impl MyApp {
         self.explore_processed = 0;
         self.explore_total = 0;
         self.stop_flag.store(false, Ordering::SeqCst);
+        self.pause_flag.resume();
+        self.is_paused = false;
         self.log.clear();
 
         let (sender, receiver) = mpsc::channel();
         self.receiver = Some(receiver);
 
         let stop_flag = self.stop_flag.clone();
+        let pause_flag = self.pause_flag.clone();
         let speed = self.explore_speed;
 
         crate::explore_engine::start_explore_animation(
@@ -85,6 +82,7 @@ impl MyApp {
             explore_max,
             speed,
             stop_flag,
+            pause_flag,
             sender,
         );
     }
@@ -144,12 +142,15 @@ impl MyApp {
         self.gap_max_gap_prev_prime = 0;
         self.gap_max_gap_prime = 0;
         self.stop_flag.store(false, Ordering::SeqCst);
+        self.pause_flag.resume();
+        self.is_paused = false;
         self.log.clear();
 
         let (sender, receiver) = mpsc::channel();
         self.receiver = Some(receiver);
 
         let stop_flag = self.stop_flag.clone();
+        let pause_flag = self.pause_flag.clone();
         let speed = self.gap_speed;
 
         crate::explore_engine::start_gap_animation(
@@ -157,6 +158,7 @@ impl MyApp {
             gap_max,
             speed,
             stop_flag,
+            pause_flag,
             sender,
         );
     }
@@ -220,12 +222,15 @@ impl MyApp {
         self.density_total = 0;
         self.density_total_primes = 0;
         self.stop_flag.store(false, Ordering::SeqCst);
+        self.pause_flag.resume();
+        self.is_paused = false;
         self.log.clear();
 
         let (sender, receiver) = mpsc::channel();
         self.receiver = Some(receiver);
 
         let stop_flag = self.stop_flag.clone();
+        let pause_flag = self.pause_flag.clone();
         let speed = self.density_speed;
 
         crate::explore_engine::start_density_animation(
@@ -234,6 +239,7 @@ impl MyApp {
             interval_size,
             speed,
             stop_flag,
+            pause_flag,
             sender,
         );
     }
@@ -293,12 +299,15 @@ impl MyApp {
         self.spiral_pan_x = 0.0;
         self.spiral_pan_y = 0.0;
         self.stop_flag.store(false, Ordering::SeqCst);
+        self.pause_flag.resume();
+        self.is_paused = false;
         self.log.clear();
 
         let (sender, receiver) = mpsc::channel();
         self.receiver = Some(receiver);
 
         let stop_flag = self.stop_flag.clone();
+        let pause_flag = self.pause_flag.clone();
         let speed = self.spiral_speed;
 
         crate::explore_engine::start_spiral_generation(
@@ -306,6 +315,7 @@ impl MyApp {
             size,
             speed,
             stop_flag,
+            pause_flag,
             sender,
         );
     }
@@ -347,7 +357,7 @@ impl MyApp {
         self.config.prime_min = prime_min;
         self.config.prime_max = prime_max;
 
-        if let Err(e) = save_config(&self.config) {
+        if let Err(e) = self.settings.save(&self.config) {
             self.log
                 .push_str(&format!("Failed to save settings: {e}\n"));
         }
@@ -356,6 +366,8 @@ impl MyApp {
         self.progress = 0.0;
         self.eta = "Calculating π(x)...".to_string();
         self.stop_flag.store(false, Ordering::SeqCst);
+        self.pause_flag.resume();
+        self.is_paused = false;
         self.current_processed = 0;
         self.total_range = 0;
 
@@ -444,6 +456,22 @@ impl MyApp {
             }
         };
 
+        let split_width = match self.split_width_input.trim().parse::<u64>() {
+            Ok(v) => v,
+            Err(_) => {
+                errors.push("split_width is not a valid u64 integer.");
+                0
+            }
+        };
+
+        let index_stride = match self.index_stride_input.trim().parse::<u64>() {
+            Ok(v) => v,
+            Err(_) => {
+                errors.push("index_stride is not a valid u64 integer.");
+                0
+            }
+        };
+
         let segment_size = match self.segment_size_input.trim().parse::<u64>() {
             Ok(v) => v,
             Err(_) => {
@@ -461,6 +489,22 @@ impl MyApp {
                 }
             };
 
+        let pipeline_depth = match self.pipeline_depth_input.trim().parse::<usize>() {
+            Ok(v) => v,
+            Err(_) => {
+                errors.push("pipeline_depth is not a valid usize integer.");
+                0
+            }
+        };
+
+        let threads = match self.threads_input.trim().parse::<usize>() {
+            Ok(v) => v,
+            Err(_) => {
+                errors.push("threads is not a valid usize integer.");
+                0
+            }
+        };
+
         let memory_usage_percent = match self.memory_usage_percent_input.trim().parse::<f64>() {
             Ok(v) => {
                 if !(10.0..=90.0).contains(&v) {
@@ -476,6 +520,14 @@ impl MyApp {
             }
         };
 
+        let memory_ceiling_mb = match self.memory_ceiling_mb_input.trim().parse::<u64>() {
+            Ok(v) => v,
+            Err(_) => {
+                errors.push("memory_ceiling_mb is not a valid u64 integer.");
+                0
+            }
+        };
+
         if prime_min >= prime_max {
             errors.push("prime_min must be less than prime_max.");
         }
@@ -492,15 +544,24 @@ impl MyApp {
         self.config.prime_max = prime_max;
         self.config.segment_size = segment_size;
         self.config.writer_buffer_size = writer_buffer_size;
+        self.config.pipeline_depth = pipeline_depth;
+        self.config.threads = threads;
         self.config.output_format = self.selected_format;
         self.config.output_dir = self.output_dir_input.clone();
         self.config.split_count = split_count;
+        self.config.split_width = split_width;
+        self.config.index_stride = index_stride;
         self.config.wheel_type = self.selected_wheel_type;
+        self.config.engine = self.selected_engine;
         self.config.memory_usage_percent = memory_usage_percent;
+        self.config.memory_ceiling_mb = memory_ceiling_mb;
         self.config.last_prime_only = self.last_prime_only;
         self.config.use_timestamp_prefix = self.use_timestamp_prefix;
+        self.config.resume = self.resume;
+        self.config.delete_partials_on_stop = self.delete_partials_on_stop;
+        self.config.auto_segment_size = self.auto_segment_size;
 
-        if let Err(e) = save_config(&self.config) {
+        if let Err(e) = self.settings.save(&self.config) {
             self.log
                 .push_str(&format!("Failed to save settings: {e}\n"));
         }
@@ -509,359 +570,29 @@ impl MyApp {
         self.progress = 0.0;
         self.eta = "Calculating...".to_string();
         self.stop_flag.store(false, Ordering::SeqCst);
+        self.pause_flag.resume();
+        self.is_paused = false;
         self.current_processed = 0;
         self.total_range = 0;
 
         let cfg = self.config.clone();
+        let config_sources = self.settings.sources_for(&cfg);
         let (sender, receiver) = mpsc::channel();
         self.receiver = Some(receiver);
         let stop_flag = self.stop_flag.clone();
+        let pause_flag = self.pause_flag.clone();
 
         std::thread::spawn(move || {
             let monitor_handle = crate::worker_jobs::start_resource_monitor(sender.clone());
 
-            let run = || -> PrimeResult<()> {
-                if cfg.last_prime_only {
-                    // 最後の素数だけモード: ファイル書き出し無し（CPU 専用）
-                    let mut writer = LastPrimeWriter::new();
-
-                    let mut last_progress = 0u64;
-                    let mut last_total = 0u64;
-                    let mut eta_history: Vec<u64> = Vec::new();
-
-                    let progress_cb = |p: Progress| {
-                        last_progress = p.processed;
-                        last_total = p.total;
-
-                        let eta_str = if let Some(eta) = p.eta_secs {
-                            // 簡易スムージング（直近5回の移動平均）
-                            eta_history.push(eta);
-                            if eta_history.len() > 5 {
-                                eta_history.remove(0);
-                            }
-                            let avg_eta =
-                                eta_history.iter().sum::<u64>() / eta_history.len() as u64;
-                            format_eta(Some(avg_eta))
-                        } else {
-                            format_eta(None)
-                        };
-
-                        sender.send(WorkerMessage::Eta(eta_str)).ok();
-                        sender
-                            .send(WorkerMessage::Progress {
-                                current: p.processed,
-                                total: p.total,
-                            })
-                            .ok();
-                    };
-
-                    if stop_flag.load(Ordering::SeqCst) {
-                        return Ok(());
-                    }
-
-                    sender
-                        .send(WorkerMessage::Log(
-                            "Using CPU engine (Rayon segmented sieve) - Last Prime Only Mode"
-                                .to_string(),
-                        ))
-                        .ok();
-                    generate_primes_cpu(&cfg, &stop_flag, &mut writer, progress_cb)?;
-
-                    if last_total > 0 {
-                        sender
-                            .send(WorkerMessage::Progress {
-                                current: last_total,
-                                total: last_total,
-                            })
-                            .ok();
-                        sender
-                            .send(WorkerMessage::Eta(format_eta(Some(0))))
-                            .ok();
-                    }
-
-                    // 最後の素数を表示
-                    if let Some(last) = writer.get_last_prime() {
-                        sender
-                            .send(WorkerMessage::Log(format!("Last prime found: {last}")))
-                            .ok();
-                    } else {
-                        sender
-                            .send(WorkerMessage::Log(
-                                "No primes found in range.".to_string(),
-                            ))
-                            .ok();
-                    }
-
-                    // 検出した素数の総数と prime_pi によるカウントをログ出力・検証
-                    let total_primes = writer.total_primes_written();
-                    sender
-                        .send(WorkerMessage::Log(format!(
-                            "total primes found : {total_primes}"
-                        )))
-                        .ok();
-
-                    // primecount (prime_pi) による区間 [prime_min, prime_max] の素数個数
-                    match (|| -> PrimeResult<u64> {
-                        let pi_max = compute_prime_pi(cfg.prime_max)?;
-                        let pi_before_min = if cfg.prime_min > 0 {
-                            compute_prime_pi(cfg.prime_min - 1)?
-                        } else {
-                            0
-                        };
-                        Ok(pi_max.saturating_sub(pi_before_min))
-                    })() {
-                        Ok(pi_count) => {
-                            sender
-                                .send(WorkerMessage::Log(format!(
-                                    "#primes π(x) = {pi_count}"
-                                )))
-                                .ok();
-                            // π(x) 一致チェック
-                            if total_primes == pi_count {
-                                sender
-                                    .send(WorkerMessage::Log(
-                                        "Verification: OK - count matches π(x)".to_string()
-                                    ))
-                                    .ok();
-                            } else {
-                                sender
-                                    .send(WorkerMessage::Log(format!(
-                                        "Verification: MISMATCH - sieve={}, π(x)={}",
-                                        total_primes, pi_count
-                                    )))
-                                    .ok();
-                            }
-                        }
-                        Err(e) => {
-                            sender
-                                .send(WorkerMessage::Log(format!(
-                                    "Error while computing π(x): {e}"
-                                )))
-                                .ok();
-                        }
-                    }
-                } else {
-                    // 通常モード: ファイルに書き出す（CPU 専用）
-                    let sieve_start = std::time::Instant::now();
-
-                    // ファイル名プレフィックス（オプション）
-                    let timestamp_prefix = if cfg.use_timestamp_prefix {
-                        Some(Local::now().format("%Y%m%d_%H%M%S_").to_string())
-                    } else {
-                        None
-                    };
-
-                    let mut writer = FilePrimeWriter::new(
-                        &cfg.output_dir,
-                        cfg.output_format,
-                        cfg.split_count,
-                        cfg.writer_buffer_size,
-                        timestamp_prefix.clone(),
-                    )?;
-
-                    let mut last_progress = 0u64;
-                    let mut last_total = 0u64;
-                    let mut eta_history: Vec<u64> = Vec::new();
-
-                    let progress_cb = |p: Progress| {
-                        last_progress = p.processed;
-                        last_total = p.total;
-
-                        let eta_str = if let Some(eta) = p.eta_secs {
-                            // 簡易スムージング（直近5回の移動平均）
-                            eta_history.push(eta);
-                            if eta_history.len() > 5 {
-                                eta_history.remove(0);
-                            }
-                            let avg_eta =
-                                eta_history.iter().sum::<u64>() / eta_history.len() as u64;
-                            format_eta(Some(avg_eta))
-                        } else {
-                            format_eta(None)
-                        };
-
-                        sender.send(WorkerMessage::Eta(eta_str)).ok();
-                        sender
-                            .send(WorkerMessage::Progress {
-                                current: p.processed,
-                                total: p.total,
-                            })
-                            .ok();
-                    };
-
-                    if stop_flag.load(Ordering::SeqCst) {
-                        return Ok(());
-                    }
-
-                    sender
-                        .send(WorkerMessage::Log(
-                            "Using CPU engine (Rayon segmented sieve)".to_string(),
-                        ))
-                        .ok();
-                    generate_primes_cpu(&cfg, &stop_flag, &mut writer, progress_cb)?;
-
-                    if last_total > 0 {
-                        sender
-                            .send(WorkerMessage::Progress {
-                                current: last_total,
-                                total: last_total,
-                            })
-                            .ok();
-                        sender
-                            .send(WorkerMessage::Eta(format_eta(Some(0))))
-                            .ok();
-                    }
-
-                    // ファイルに書き出した素数の総数と prime_pi によるカウントをログ出力・検証
-                    let total_primes = writer.total_primes_written();
-                    sender
-                        .send(WorkerMessage::Log(format!(
-                            "total primes found : {total_primes}"
-                        )))
-                        .ok();
-
-                    // primecount (prime_pi) による区間 [prime_min, prime_max] の素数個数
-                    let mut pi_x_verified = false;
-                    match (|| -> PrimeResult<u64> {
-                        let pi_max = compute_prime_pi(cfg.prime_max)?;
-                        let pi_before_min = if cfg.prime_min > 0 {
-                            compute_prime_pi(cfg.prime_min - 1)?
-                        } else {
-                            0
-                        };
-                        Ok(pi_max.saturating_sub(pi_before_min))
-                    })() {
-                        Ok(pi_count) => {
-                            sender
-                                .send(WorkerMessage::Log(format!(
-                                    "#primes π(x) = {pi_count}"
-                                )))
-                                .ok();
-                            // π(x) 一致チェック
-                            if total_primes == pi_count {
-                                pi_x_verified = true;
-                                sender
-                                    .send(WorkerMessage::Log(
-                                        "Verification: OK - count matches π(x)".to_string()
-                                    ))
-                                    .ok();
-                            } else {
-                                sender
-                                    .send(WorkerMessage::Log(format!(
-                                        "Verification: MISMATCH - sieve={}, π(x)={}",
-                                        total_primes, pi_count
-                                    )))
-                                    .ok();
-                            }
-                        }
-                        Err(e) => {
-                            sender
-                                .send(WorkerMessage::Log(format!(
-                                    "Error while computing π(x): {e}"
-                                )))
-                                .ok();
-                        }
-                    }
-
-                    // メタデータファイルを出力
-                    let elapsed_ms = sieve_start.elapsed().as_millis() as u64;
-
-                    let output_files: Vec<String> = writer
-                        .output_file_paths()
-                        .iter()
-                        .map(|p| p.to_string_lossy().to_string())
-                        .collect();
-
-                    let metadata = OutputMetadata::new(
-                        (cfg.prime_min, cfg.prime_max),
-                        total_primes,
-                        pi_x_verified,
-                        elapsed_ms,
-                        output_files,
-                        Some(PRIMECOUNT_VERSION.to_string()),
-                        Some(PRIMECOUNT_MODE.to_string()),
-                    );
-                    match metadata.write_to_file(
-                        &cfg.output_dir,
-                        &cfg,
-                        timestamp_prefix.as_deref(),
-                    ) {
-                        Ok(meta_path) => {
-                            sender
-                                .send(WorkerMessage::Log(format!(
-                                    "Metadata written to: {}",
-                                    meta_path.display()
-                                )))
-                                .ok();
-                        }
-                        Err(e) => {
-                            sender
-                                .send(WorkerMessage::Log(format!(
-                                    "Failed to write metadata: {e}"
-                                )))
-                                .ok();
-                        }
-                    }
-
-                    // 自動ファイル検証
-                    match cfg.output_format {
-                        crate::config::OutputFormat::Binary => {
-                            if let Some(first_path) = writer.output_file_paths().first() {
-                                let path_str = first_path.to_string_lossy().to_string();
-
-                                sender
-                                    .send(WorkerMessage::Log(format!(
-                                        "Auto-verifying (binary): {path_str}"
-                                    )))
-                                    .ok();
-
-                                let sender_clone = sender.clone();
-                                let log_cb: LogCallback = Box::new(move |msg: String| {
-                                    sender_clone.send(WorkerMessage::Log(msg)).ok();
-                                });
-
-                                match verify_primes_file(&path_str, 100, Some(log_cb)) {
-                                    Ok(report) => {
-                                        sender
-                                            .send(WorkerMessage::Log(format!(
-                                                "File verification OK: lines={}, min={}, max={}, tail_checked={}",
-                                                report.line_count, report.min, report.max, report.checked_tail
-                                            )))
-                                            .ok();
-                                    }
-                                    Err(e) => {
-                                        sender
-                                            .send(WorkerMessage::Log(format!(
-                                                "File verification FAILED: {e}"
-                                            )))
-                                            .ok();
-                                    }
-                                }
-                            } else {
-                                sender
-                                    .send(WorkerMessage::Log(
-                                        "Skipping file verification: no binary output file found"
-                                            .to_string(),
-                                    ))
-                                    .ok();
-                            }
-                        }
-                        _ => {
-                            sender
-                                .send(WorkerMessage::Log(
-                                    "Skipping file verification (only supported for Binary format)"
-                                        .to_string(),
-                                ))
-                                .ok();
-                        }
-                    }
-                }
-
-                Ok(())
-            };
-
             let wall_start = std::time::Instant::now();
-            let result = run();
+            let result = crate::worker_jobs::run_generate_job_pausable(
+                &cfg,
+                Some(&config_sources),
+                &stop_flag,
+                &pause_flag,
+                &sender,
+            );
             let elapsed = wall_start.elapsed();
             let elapsed_ms = elapsed.as_secs_f64() * 1000.0;
 
@@ -0,0 +1,171 @@
+//! 素数生成の途中再開（チェックポイント）。
+//!
+//! - セグメントグループを処理するたびに、次に篩うべき位置と Writer の確定済み状態を
+//!   出力フォルダの `{prefix}primes.checkpoint.json` に保存します（`prefix` は出力ファイルと同じ
+//!   タイムスタンプ接頭辞）。同じフォルダに複数の実行が書き出しても互いに上書きしません。
+//! - `Config::resume` が有効な場合、設定が一致するチェックポイントから生成を再開します。
+//! - 正常に完了した時点でチェックポイントは削除されます。
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::output::WriterState;
+
+/// チェックポイントファイル名（出力フォルダ直下に、タイムスタンプ接頭辞を付けて作成）
+pub const CHECKPOINT_FILE_NAME: &str = "primes.checkpoint.json";
+
+/// チェックポイント形式のバージョン
+pub const CHECKPOINT_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Checkpoint {
+    pub version: u32,
+    /// 出力内容に影響する設定のハッシュ（`config_hash`）
+    pub config_hash: u64,
+    /// 次に処理するセグメントの開始位置
+    pub next_seg_start: u64,
+    /// 出力ファイル名のタイムスタンプ接頭辞（再開後も同じファイルに追記するため）
+    pub timestamp_prefix: Option<String>,
+    pub writer: WriterState,
+    pub saved_at: String,
+}
+
+impl Checkpoint {
+    pub fn new(
+        cfg: &Config,
+        next_seg_start: u64,
+        timestamp_prefix: Option<String>,
+        writer: WriterState,
+    ) -> Self {
+        Self {
+            version: CHECKPOINT_VERSION,
+            config_hash: config_hash(cfg),
+            next_seg_start,
+            timestamp_prefix,
+            writer,
+            saved_at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        }
+    }
+
+    /// 出力フォルダとタイムスタンプ接頭辞に対応するチェックポイントファイルのパス
+    pub fn path_for(output_dir: &str, timestamp_prefix: Option<&str>) -> PathBuf {
+        Path::new(output_dir).join(format!(
+            "{}{CHECKPOINT_FILE_NAME}",
+            timestamp_prefix.unwrap_or("")
+        ))
+    }
+
+    /// このチェックポイントの保存先
+    pub fn path(&self, output_dir: &str) -> PathBuf {
+        Self::path_for(output_dir, self.timestamp_prefix.as_deref())
+    }
+
+    /// 出力フォルダ内のチェックポイントをすべて読み込む（保存日時の古い順）。
+    ///
+    /// - フォルダが無ければ空を返します。
+    /// - 読めない・壊れたファイルは警告を出して読み飛ばします。ただし `config_hash` が
+    ///   一致するチェックポイントが読めない場合は、黙って最初からやり直さないようエラーにします。
+    pub fn load_all(output_dir: &str, config_hash: u64) -> io::Result<Vec<Self>> {
+        let entries = match fs::read_dir(output_dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        let mut checkpoints = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            let is_checkpoint = path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().ends_with(CHECKPOINT_FILE_NAME));
+            if !is_checkpoint || !path.is_file() {
+                continue;
+            }
+            match Self::load(&path) {
+                Ok(checkpoint) => checkpoints.push(checkpoint),
+                Err(e) if stored_config_hash(&path) == Some(config_hash) => return Err(e),
+                Err(e) => log::warn!("Skipping unreadable checkpoint: {e}"),
+            }
+        }
+        // saved_at は "%Y-%m-%d %H:%M:%S" 形式なので文字列順が時刻順になる
+        checkpoints.sort_by(|a, b| a.saved_at.cmp(&b.saved_at));
+        Ok(checkpoints)
+    }
+
+    /// チェックポイントファイルを読み込む。
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        let checkpoint: Self = serde_json::from_str(&text).map_err(|e| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("{}: {e}", path.display()))
+        })?;
+        if checkpoint.version != CHECKPOINT_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "{}: unsupported checkpoint version {} (expected {CHECKPOINT_VERSION})",
+                    path.display(),
+                    checkpoint.version
+                ),
+            ));
+        }
+        Ok(checkpoint)
+    }
+
+    /// チェックポイントを保存する。
+    ///
+    /// - 一時ファイルに書いてからリネームするため、保存中に中断しても前回の内容は壊れません。
+    pub fn save(&self, output_dir: &str) -> io::Result<()> {
+        fs::create_dir_all(output_dir)?;
+        let path = self.path(output_dir);
+        let tmp_path = path.with_extension("json.tmp");
+        let text = serde_json::to_string_pretty(self)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(&tmp_path, text)?;
+        fs::rename(&tmp_path, &path)
+    }
+
+    /// チェックポイントを削除する（存在しなければ何もしない）。
+    pub fn remove(output_dir: &str, timestamp_prefix: Option<&str>) -> io::Result<()> {
+        match fs::remove_file(Self::path_for(output_dir, timestamp_prefix)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// 読み込めなかったチェックポイントファイルから `config_hash` だけを取り出す（取れなければ `None`）。
+fn stored_config_hash(path: &Path) -> Option<u64> {
+    let text = fs::read_to_string(path).ok()?;
+    let value: serde_json::Value = serde_json::from_str(&text).ok()?;
+    value.get("config_hash")?.as_u64()
+}
+
+/// 出力内容に影響する設定値のハッシュ（FNV-1a）。
+///
+/// - 範囲・出力形式・分割数・分割幅・最後の素数だけモード・ホイールが同じ場合に限り再開を許可します。
+///   （ホイールごとに最初に書き出す小さい素数が異なるため `wheel_type` も含めます）
+/// - `segment_size` やメモリ設定は出力内容を変えないため含めません。
+pub fn config_hash(cfg: &Config) -> u64 {
+    let key = format!(
+        "{}|{}|{:?}|{}|{}|{}|{:?}",
+        cfg.prime_min,
+        cfg.prime_max,
+        cfg.output_format,
+        cfg.split_count,
+        cfg.split_width,
+        cfg.last_prime_only,
+        cfg.wheel_type
+    );
+
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in key.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
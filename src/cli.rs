@@ -0,0 +1,550 @@
+//! GUI を起動せずに実行する CLI サブコマンド群。
+//!
+//! - `generate`: Generator タブと同じ素数生成ジョブ（`worker_jobs::run_generate_job`）をヘッドレスで実行
+//! - `verify`: 既存の素数ファイル（テキスト / バイナリ）またはディレクトリ内の出力ファイルを検証
+//!
+//! 進捗・ETA・ログは標準エラーへ、最終結果（素数の個数など）は標準出力へ書き出します。
+//! `--json` を付けると、すべての `WorkerMessage` を 1 行 1 オブジェクトの JSON として標準出力へ流し、
+//! 最後に結果を `{"type": "Result", "data": {...}}` の 1 行で出力します（ダッシュボード・ラッパー向け）。
+//! 終了コードはスクリプトや cron から判定しやすいように `EXIT_*` 定数の値に統一しています。
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::{mpsc, Arc};
+
+use serde::Serialize;
+
+use crate::config::{ConfigSource, LayeredConfig};
+use crate::verify::{verify_primes_file, LogCallback, VerifyReport};
+use crate::worker_jobs::{run_generate_job, start_resource_monitor, GenerateOutcome};
+use crate::worker_message::WorkerMessage;
+
+/// 正常終了。
+pub const EXIT_OK: i32 = 0;
+/// 引数エラーや I/O エラーなど、処理自体が失敗した場合。
+pub const EXIT_ERROR: i32 = 1;
+/// 処理は完了したが、π(x) 照合またはファイル検証に失敗した場合。
+pub const EXIT_VERIFY_FAILED: i32 = 2;
+
+const GENERATE_USAGE: &str = "\
+Usage: sosu-seisei-main2 generate [options]
+
+Settings are layered: defaults < config file < SOSU_* environment variables < flags.
+Environment variables use the settings.toml key in upper case, e.g. SOSU_PRIME_MAX.
+
+Options:
+  --config <path>         settings file to load instead of ./settings.toml
+  --min <n>               prime_min (inclusive)
+  --max <n>               prime_max (inclusive)
+  --wheel <type>          odd | mod6 | mod30 | mod210
+  --engine <engine>       segmented | reference | atkin
+  --format <format>       text | csv | json | binary | deltavarint | wheelbitmap
+  --output-dir <dir>      output directory
+  --split-count <n>       primes per file (0 = no split)
+  --split-width <w>       one file per value range [k*w, (k+1)*w) (0 = off, overrides --split-count)
+  --index-stride <k>      write primes.index recording every k-th prime (binary / deltavarint, 0 = off)
+  --segment-size <n>      segment size upper bound
+  --auto-segment-size     pick a segment size that fits the CPU cache (ignores --segment-size)
+  --buffer-size <n>       writer buffer size in bytes
+  --pipeline-depth <n>    sieved groups queued for a dedicated writer (0 = off)
+  --threads <n>           worker threads for sieving (0 = all cores)
+  --memory-percent <p>    memory usage percent (10.0 - 90.0)
+  --memory-ceiling-mb <n> hard limit on the process RSS in MB (0 = none)
+  --last-prime-only       skip file output, report only the last prime
+  --no-last-prime-only    write primes to files (overrides settings.toml)
+  --no-timestamp          do not prefix output file names with a timestamp
+  --resume                continue from the matching <output-dir>/*primes.checkpoint.json if present
+  --delete-partials-on-stop
+                          on stop, delete *.partial outputs and the checkpoint instead of keeping them
+  --json                  stream every worker message as JSON lines on stdout";
+
+const VERIFY_USAGE: &str = "\
+Usage: sosu-seisei-main2 verify <file|dir> [--tail N]
+
+Verifies text (one prime per line), binary (little-endian u64), delta-varint (.dvar) or
+wheel bitmap (.w30) prime files. When a directory is given, every primes*.txt / .bin /
+.dvar / .w30 file in it is verified
+in file-index order and the files are checked to continue each other.
+
+Options:
+  --tail <n>              number of trailing values checked with Miller-Rabin (default: 100)
+  --json                  stream log / progress messages and the result as JSON lines on stdout";
+
+/// `verify` で末尾サンプルとして Miller-Rabin チェックする件数のデフォルト（自動検証と同じ）。
+const DEFAULT_VERIFY_TAIL: usize = 100;
+
+/// CLI のメッセージ出力形式。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MessageFormat {
+    /// ログ・進捗は標準エラー、結果は `key = value` 形式で標準出力
+    Human,
+    /// すべてを JSON lines で標準出力（`--json`）
+    JsonLines,
+}
+
+/// `--json` を取り除いた引数と、出力形式を返す。
+fn take_message_format(args: &[String]) -> (MessageFormat, Vec<String>) {
+    let format = if args.iter().any(|a| a == "--json") {
+        MessageFormat::JsonLines
+    } else {
+        MessageFormat::Human
+    };
+    let rest = args.iter().filter(|a| *a != "--json").cloned().collect();
+    (format, rest)
+}
+
+/// 値を 1 行の JSON として標準出力へ書き出し、すぐに flush する（パイプ先へ逐次届けるため）。
+fn print_json_line<T: Serialize>(value: &T) {
+    let Ok(line) = serde_json::to_string(value) else {
+        return;
+    };
+    let mut stdout = std::io::stdout().lock();
+    let _ = writeln!(stdout, "{line}");
+    let _ = stdout.flush();
+}
+
+/// `--json` モードの最終行 `{"type": "Result", "data": ...}`。
+fn print_json_result<T: Serialize>(data: &T) {
+    #[derive(Serialize)]
+    struct ResultLine<'a, T> {
+        #[serde(rename = "type")]
+        kind: &'static str,
+        data: &'a T,
+    }
+    print_json_line(&ResultLine { kind: "Result", data });
+}
+
+/// 先頭引数がサブコマンドであれば実行し、その終了コードを返す。
+///
+/// - サブコマンドでない場合は `None` を返し、呼び出し側は GUI を起動する。
+pub fn try_run_subcommand(args: &[String]) -> Option<i32> {
+    let (command, rest) = args.split_first()?;
+    match command.as_str() {
+        "generate" => Some(run_generate_cli(rest)),
+        "verify" => Some(run_verify_cli(rest)),
+        _ => None,
+    }
+}
+
+/// `generate` サブコマンド本体。
+fn run_generate_cli(args: &[String]) -> i32 {
+    if args.iter().any(|a| a == "--help" || a == "-h") {
+        eprintln!("{GENERATE_USAGE}");
+        return EXIT_OK;
+    }
+
+    let (format, args) = take_message_format(args);
+    let layered = match load_generate_config(&args) {
+        Ok(layered) => layered,
+        Err(e) => {
+            eprintln!("{e}");
+            eprintln!("{GENERATE_USAGE}");
+            return EXIT_ERROR;
+        }
+    };
+
+    match run_generate_headless(layered, format) {
+        Ok(outcome) => {
+            if format == MessageFormat::JsonLines {
+                print_json_result(&outcome);
+            } else {
+                println!("total_primes = {}", outcome.total_primes);
+                if let Some(last) = outcome.last_prime {
+                    println!("last_prime = {last}");
+                }
+                if let Some(ok) = outcome.pi_x_verified {
+                    println!("pi_x_verified = {ok}");
+                }
+                if let Some(ok) = outcome.file_verified {
+                    println!("file_verified = {ok}");
+                }
+            }
+
+            if outcome.pi_x_verified == Some(false) || outcome.file_verified == Some(false) {
+                EXIT_VERIFY_FAILED
+            } else {
+                EXIT_OK
+            }
+        }
+        Err(e) => {
+            eprintln!("An error occurred: {e}");
+            EXIT_ERROR
+        }
+    }
+}
+
+/// 値を取る `generate` のフラグと、対応する `Config` のキー名。
+const GENERATE_VALUE_FLAGS: &[(&str, &str)] = &[
+    ("--min", "prime_min"),
+    ("--max", "prime_max"),
+    ("--wheel", "wheel_type"),
+    ("--engine", "engine"),
+    ("--format", "output_format"),
+    ("--output-dir", "output_dir"),
+    ("--split-count", "split_count"),
+    ("--split-width", "split_width"),
+    ("--index-stride", "index_stride"),
+    ("--segment-size", "segment_size"),
+    ("--buffer-size", "writer_buffer_size"),
+    ("--pipeline-depth", "pipeline_depth"),
+    ("--threads", "threads"),
+    ("--memory-percent", "memory_usage_percent"),
+    ("--memory-ceiling-mb", "memory_ceiling_mb"),
+];
+
+/// 値を取らない `generate` のフラグと、設定されるキー名・値。
+const GENERATE_SWITCH_FLAGS: &[(&str, &str, &str)] = &[
+    ("--last-prime-only", "last_prime_only", "true"),
+    ("--no-last-prime-only", "last_prime_only", "false"),
+    ("--no-timestamp", "use_timestamp_prefix", "false"),
+    ("--resume", "resume", "true"),
+    ("--delete-partials-on-stop", "delete_partials_on_stop", "true"),
+    ("--auto-segment-size", "auto_segment_size", "true"),
+];
+
+/// `generate` の設定を「デフォルト → 設定ファイル → 環境変数 → CLI フラグ」の順に重ねて構築する。
+///
+/// GUI の `start_worker` と同じ検証（範囲・メモリ使用率）を行う。
+fn load_generate_config(args: &[String]) -> Result<LayeredConfig, String> {
+    // `--config` はほかのフラグより先に解決する（ファイル層が CLI 層の下になるため）
+    let mut config_path: Option<&str> = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--config" {
+            let value = iter
+                .next()
+                .ok_or_else(|| "Missing value for --config".to_string())?;
+            config_path = Some(value);
+        }
+    }
+
+    let mut layered = LayeredConfig::load(config_path.map(Path::new))
+        .map_err(|e| format!("Failed to load settings: {e}"))?;
+
+    let mut iter = args.iter();
+    while let Some(flag) = iter.next() {
+        if flag == "--config" {
+            iter.next();
+            continue;
+        }
+        if let Some(&(_, key, value)) = GENERATE_SWITCH_FLAGS.iter().find(|(f, _, _)| f == flag) {
+            layered.set(key, value, ConfigSource::Cli(flag.clone()))?;
+            continue;
+        }
+        let Some(&(_, key)) = GENERATE_VALUE_FLAGS.iter().find(|(f, _)| f == flag) else {
+            return Err(format!("Unknown option: {flag}"));
+        };
+        let value = iter
+            .next()
+            .ok_or_else(|| format!("Missing value for {flag}"))?;
+        layered
+            .set(key, value, ConfigSource::Cli(flag.clone()))
+            .map_err(|e| format!("{flag}: {e}"))?;
+    }
+
+    let cfg = &layered.config;
+    if cfg.prime_min >= cfg.prime_max {
+        return Err("prime_min must be less than prime_max.".to_string());
+    }
+    if !(10.0..=90.0).contains(&cfg.memory_usage_percent) {
+        return Err("memory_usage_percent must be between 10.0 and 90.0.".to_string());
+    }
+    Ok(layered)
+}
+
+fn parse_number<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, String>
+where
+    T::Err: std::fmt::Display,
+{
+    value
+        .trim()
+        .parse::<T>()
+        .map_err(|e| format!("Invalid value for {flag}: {value} ({e})"))
+}
+
+/// 素数生成ジョブをワーカースレッドで実行し、`WorkerMessage` を `format` に従って出力する。
+///
+/// GUI の `MyApp::start_worker` と同じく、ジョブ終了後に経過時間をログし、
+/// 最後に `Done` を送ってメッセージループを終了させる。
+/// JSON lines モードでは GUI と同様にメモリモニタも起動し、`MemUsage` も流す。
+fn run_generate_headless(
+    layered: LayeredConfig,
+    format: MessageFormat,
+) -> crate::engine_types::PrimeResult<GenerateOutcome> {
+    let stop_flag = Arc::new(AtomicBool::new(false));
+    let (sender, receiver) = mpsc::channel();
+
+    if format == MessageFormat::JsonLines {
+        // receiver が破棄されると送信に失敗して自動的に終了する
+        start_resource_monitor(sender.clone());
+    }
+
+    let handle = std::thread::spawn(move || {
+        let wall_start = std::time::Instant::now();
+        let result = run_generate_job(
+            &layered.config,
+            Some(&layered.sources),
+            &stop_flag,
+            &sender,
+        );
+        let elapsed_ms = wall_start.elapsed().as_secs_f64() * 1000.0;
+
+        sender
+            .send(WorkerMessage::Log(format!(
+                "Total elapsed time: {:.3} ms",
+                elapsed_ms
+            )))
+            .ok();
+        let _ = sender.send(WorkerMessage::Done);
+        result
+    });
+
+    match format {
+        MessageFormat::Human => print_messages_to_stderr(&receiver),
+        MessageFormat::JsonLines => print_messages_as_json_lines(&receiver),
+    }
+
+    handle
+        .join()
+        .map_err(|_| "generate worker thread panicked".to_string())?
+}
+
+/// `Done` / `Stopped` を受け取るか送信側がすべて破棄されるまで、
+/// ワーカーからのメッセージを人間向けの形式で標準エラーに出力する。
+fn print_messages_to_stderr(receiver: &mpsc::Receiver<WorkerMessage>) {
+    let mut eta = String::from("Calculating...");
+    while let Ok(message) = receiver.recv() {
+        match message {
+            WorkerMessage::Log(msg) => eprintln!("{}", msg.trim_end()),
+            WorkerMessage::Eta(eta_str) => eta = eta_str,
+            WorkerMessage::Progress { current, total } => {
+                let percent = if total > 0 {
+                    current as f64 / total as f64 * 100.0
+                } else {
+                    0.0
+                };
+                eprintln!("Progress: {percent:.1}% ({current}/{total}), ETA: {eta}");
+            }
+            WorkerMessage::Paused(true) => eprintln!("Paused."),
+            WorkerMessage::Paused(false) => eprintln!("Resumed."),
+            WorkerMessage::Done => break,
+            WorkerMessage::Stopped => {
+                eprintln!("Process stopped.");
+                break;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// `Done` / `Stopped` を受け取るか送信側がすべて破棄されるまで、
+/// ワーカーからのメッセージをそのまま JSON lines として標準出力に出力する。
+fn print_messages_as_json_lines(receiver: &mpsc::Receiver<WorkerMessage>) {
+    while let Ok(message) = receiver.recv() {
+        print_json_line(&message);
+        if matches!(message, WorkerMessage::Done | WorkerMessage::Stopped) {
+            break;
+        }
+    }
+}
+
+/// `verify` の最終結果（`--json` の `Result` 行）。
+#[derive(Serialize)]
+struct VerifySummary {
+    files: usize,
+    values: u64,
+    failed: usize,
+}
+
+/// `verify` サブコマンド本体。
+///
+/// - 各ファイルの `VerifyReport` を標準出力へ、検証中のログを標準エラーへ出力する。
+/// - 1 つでも検証に失敗したファイルがあれば `EXIT_VERIFY_FAILED` を返す。
+fn run_verify_cli(args: &[String]) -> i32 {
+    if args.iter().any(|a| a == "--help" || a == "-h") {
+        eprintln!("{VERIFY_USAGE}");
+        return EXIT_OK;
+    }
+
+    let (format, args) = take_message_format(args);
+    let json = format == MessageFormat::JsonLines;
+    // 人間向けの出力先（`to_stdout`）と JSON lines の Log を切り替える
+    let emit = move |to_stdout: bool, msg: String| {
+        if json {
+            print_json_line(&WorkerMessage::Log(msg));
+        } else if to_stdout {
+            println!("{msg}");
+        } else {
+            eprintln!("{msg}");
+        }
+    };
+
+    let mut target: Option<&String> = None;
+    let mut tail = DEFAULT_VERIFY_TAIL;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--tail" => {
+                let parsed = iter
+                    .next()
+                    .ok_or_else(|| "Missing value for --tail".to_string())
+                    .and_then(|v| parse_number::<usize>(arg, v));
+                match parsed {
+                    Ok(v) => tail = v,
+                    Err(e) => {
+                        eprintln!("{e}");
+                        eprintln!("{VERIFY_USAGE}");
+                        return EXIT_ERROR;
+                    }
+                }
+            }
+            _ if target.is_none() && !arg.starts_with("--") => target = Some(arg),
+            _ => {
+                eprintln!("Unexpected argument: {arg}");
+                eprintln!("{VERIFY_USAGE}");
+                return EXIT_ERROR;
+            }
+        }
+    }
+
+    let Some(target) = target else {
+        eprintln!("{VERIFY_USAGE}");
+        return EXIT_ERROR;
+    };
+
+    let path = Path::new(target);
+    let files = if path.is_dir() {
+        match collect_prime_files(path) {
+            Ok(files) if !files.is_empty() => files,
+            Ok(_) => {
+                eprintln!("No primes*.txt / .bin / .dvar / .w30 files found in {}", path.display());
+                return EXIT_ERROR;
+            }
+            Err(e) => {
+                eprintln!("Failed to read directory {}: {e}", path.display());
+                return EXIT_ERROR;
+            }
+        }
+    } else if path.is_file() {
+        vec![path.to_path_buf()]
+    } else {
+        eprintln!("No such file or directory: {}", path.display());
+        return EXIT_ERROR;
+    };
+
+    let mut failed = 0usize;
+    let mut total_values = 0u64;
+    // 分割出力の連続性チェック用: (ファイルのグループ名, 直前ファイルの最大値)
+    let mut previous: Option<(String, u64)> = None;
+
+    for (i, file) in files.iter().enumerate() {
+        emit(false, format!("Verifying: {}", file.display()));
+        let log_cb: LogCallback = Box::new(move |msg: String| emit(false, msg));
+        match verify_primes_file(file, tail, Some(log_cb)) {
+            Ok(report) => {
+                emit(true, format_verify_report(file, &report));
+                total_values += report.line_count;
+
+                let group = split_file_index(file).0;
+                if let Some((prev_group, prev_max)) = &previous {
+                    if *prev_group == group && report.min <= *prev_max {
+                        emit(
+                            false,
+                            format!(
+                                "Verification FAILED: {} does not continue the previous file (min={} <= previous max={prev_max})",
+                                file.display(),
+                                report.min
+                            ),
+                        );
+                        failed += 1;
+                    }
+                }
+                previous = Some((group, report.max));
+            }
+            Err(e) => {
+                emit(true, format!("{}: FAILED: {e}", file.display()));
+                failed += 1;
+                previous = None;
+            }
+        }
+        if json {
+            print_json_line(&WorkerMessage::Progress {
+                current: i as u64 + 1,
+                total: files.len() as u64,
+            });
+        }
+    }
+
+    if json {
+        print_json_line(&WorkerMessage::Done);
+        print_json_result(&VerifySummary {
+            files: files.len(),
+            values: total_values,
+            failed,
+        });
+    } else {
+        println!(
+            "files = {}, values = {total_values}, failed = {failed}",
+            files.len()
+        );
+    }
+
+    if failed > 0 {
+        EXIT_VERIFY_FAILED
+    } else {
+        EXIT_OK
+    }
+}
+
+fn format_verify_report(file: &Path, report: &VerifyReport) -> String {
+    format!(
+        "{}: OK: lines={}, min={}, max={}, tail_checked={}",
+        file.display(),
+        report.line_count,
+        report.min,
+        report.max,
+        report.checked_tail
+    )
+}
+
+/// ディレクトリ内の素数出力ファイル（`primes*.txt` / `.bin` / `.dvar` / `.w30`）を列挙する。
+///
+/// - `primes.meta.txt` などのレポートは除外する。
+/// - 分割出力（`primes_1.bin`, `primes_2.bin`, ... / `primes_0-1000.bin`, `primes_1000-2000.bin`, ...）は
+///   ファイル番号（範囲の開始値）の数値順に並べる。
+fn collect_prime_files(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if !path.is_file() {
+            continue;
+        }
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        if !name.contains("primes") || name.contains(".meta.") || !matches!(ext, "txt" | "bin" | "dvar" | "w30") {
+            continue;
+        }
+        files.push(path);
+    }
+    files.sort_by_key(|p| split_file_index(p));
+    Ok(files)
+}
+
+/// `prefix_primes_12.bin` → (`prefix_primes.bin`, 12) のように、
+/// ファイル名をグループ名と分割番号に分ける（番号なしは 0）。
+///
+/// - 値の範囲による分割（`primes_1000-2000.bin`）では範囲の開始値を番号とする。
+fn split_file_index(path: &Path) -> (String, u64) {
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    if let Some((base, idx)) = stem.rsplit_once('_') {
+        let idx = idx.split_once('-').map_or(idx, |(start, _)| start);
+        if let Ok(idx) = idx.parse::<u64>() {
+            return (format!("{base}.{ext}"), idx);
+        }
+    }
+    (format!("{stem}.{ext}"), 0)
+}
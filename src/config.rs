@@ -1,7 +1,9 @@
 use serde::{Deserialize, Serialize};
+use std::fmt;
 use std::fs::File;
-use std::io::{BufWriter, Read, Write};
-use std::path::Path;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum OutputFormat {
@@ -9,6 +11,10 @@ pub enum OutputFormat {
     CSV,
     JSON,
     Binary,
+    /// 差分 / 2 を LEB128 varint で格納する圧縮バイナリ（`delta_varint` モジュール参照）
+    DeltaVarint,
+    /// mod 30 ホイールの篩ビットマップ（`wheel_bitmap` モジュール参照）
+    WheelBitmap,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
@@ -19,6 +25,66 @@ pub enum WheelType {
     Mod6,
     /// mod 30 ホイール (2, 3, 5を除外) - メモリ使用量 8/30
     Mod30,
+    /// mod 210 ホイール (2, 3, 5, 7を除外) - メモリ使用量 48/210
+    Mod210,
+}
+
+/// 素数生成に使うエンジン（`sieve_engine` モジュール参照）
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SieveEngineKind {
+    /// Rayon 並列のホイール分割篩（`wheel_type` を使う）
+    #[default]
+    Segmented,
+    /// 単一スレッドの素朴な分割エラトステネスの篩（検算用）
+    Reference,
+    /// 単一スレッドの分割アトキンの篩
+    Atkin,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    /// 出力形式名（大文字小文字を区別しない）を変換する。CLI / 環境変数から利用。
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "text" | "txt" => Ok(OutputFormat::Text),
+            "csv" => Ok(OutputFormat::CSV),
+            "json" => Ok(OutputFormat::JSON),
+            "binary" | "bin" => Ok(OutputFormat::Binary),
+            "deltavarint" | "delta-varint" | "dvar" => Ok(OutputFormat::DeltaVarint),
+            "wheelbitmap" | "wheel-bitmap" | "bitmap" | "w30" => Ok(OutputFormat::WheelBitmap),
+            _ => Err(format!("Unknown output format: {value}")),
+        }
+    }
+}
+
+impl FromStr for SieveEngineKind {
+    type Err = String;
+
+    /// エンジン名（大文字小文字を区別しない）を変換する。CLI / 環境変数から利用。
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "segmented" | "cpu" => Ok(SieveEngineKind::Segmented),
+            "reference" | "eratosthenes" => Ok(SieveEngineKind::Reference),
+            "atkin" => Ok(SieveEngineKind::Atkin),
+            _ => Err(format!("Unknown sieve engine: {value}")),
+        }
+    }
+}
+
+impl FromStr for WheelType {
+    type Err = String;
+
+    /// ホイール名（大文字小文字を区別しない）を変換する。CLI / 環境変数から利用。
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "odd" => Ok(WheelType::Odd),
+            "mod6" => Ok(WheelType::Mod6),
+            "mod30" => Ok(WheelType::Mod30),
+            "mod210" => Ok(WheelType::Mod210),
+            _ => Err(format!("Unknown wheel type: {value}")),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -33,14 +99,41 @@ pub struct Config {
     pub output_dir: String,
     #[serde(default)]
     pub split_count: u64,
+    /// 値の範囲による分割幅 W。0 以外なら `[k·W, (k+1)·W)` ごとに 1 ファイルへ書き出し、`split_count` は無視する
+    #[serde(default)]
+    pub split_width: u64,
+    /// 疎なインデックス（`primes.index`）の記録間隔 K。0 以外なら K 個ごとの素数の位置を記録する（Binary / DeltaVarint のみ）
+    #[serde(default)]
+    pub index_stride: u64,
     #[serde(default)]
     pub last_prime_only: bool,
     #[serde(default = "default_wheel_type")]
     pub wheel_type: WheelType,
     #[serde(default = "default_memory_usage_percent")]
     pub memory_usage_percent: f64,
+    /// プロセスの RSS のハードリミット（MB）。近づくと篩のグループを小さくし、超えると書き出しを待つ。0 なら上限なし
+    #[serde(default)]
+    pub memory_ceiling_mb: u64,
     #[serde(default = "default_use_timestamp_prefix")]
     pub use_timestamp_prefix: bool,
+    /// 出力フォルダにチェックポイントがあれば、そこから生成を再開する
+    #[serde(default)]
+    pub resume: bool,
+    /// 停止したときに書きかけの `*.partial` ファイルとチェックポイントを削除する（false なら再開用に残す）
+    #[serde(default)]
+    pub delete_partials_on_stop: bool,
+    /// 篩と書き出しを並行させるときのキューの深さ（グループ数）。0 なら従来どおり交互に行う
+    #[serde(default)]
+    pub pipeline_depth: usize,
+    /// 篩に使うワーカースレッド数。0 なら全コアを使う
+    #[serde(default)]
+    pub threads: usize,
+    /// true なら `segment_size` の代わりに、CPU キャッシュ（L1/L2）に収まるセグメントサイズを自動で選ぶ
+    #[serde(default)]
+    pub auto_segment_size: bool,
+    /// 素数生成に使うエンジン
+    #[serde(default)]
+    pub engine: SieveEngineKind,
 }
 
 fn default_wheel_type() -> WheelType {
@@ -70,36 +163,280 @@ impl Default for Config {
             output_format: OutputFormat::Binary,
             output_dir: ".".to_string(),
             split_count: 0,
+            split_width: 0,
+            index_stride: 0,
             last_prime_only: true,
             wheel_type: WheelType::Mod30,
             memory_usage_percent: 50.0,
+            memory_ceiling_mb: 0,
             use_timestamp_prefix: default_use_timestamp_prefix(),
+            resume: false,
+            delete_partials_on_stop: false,
+            pipeline_depth: 0,
+            threads: 0,
+            auto_segment_size: false,
+            engine: SieveEngineKind::Segmented,
         }
     }
 }
 
-const SETTINGS_FILE: &str = "settings.toml";
+/// `Config` の全フィールド名（`settings.toml` のキー名と同じ）。
+///
+/// 環境変数名は `SOSU_` + 大文字のキー名（例: `SOSU_PRIME_MAX`）になります。
+pub const CONFIG_KEYS: &[&str] = &[
+    "prime_min",
+    "prime_max",
+    "prime_pi_x",
+    "segment_size",
+    "writer_buffer_size",
+    "output_format",
+    "output_dir",
+    "split_count",
+    "split_width",
+    "index_stride",
+    "last_prime_only",
+    "wheel_type",
+    "memory_usage_percent",
+    "memory_ceiling_mb",
+    "use_timestamp_prefix",
+    "resume",
+    "delete_partials_on_stop",
+    "pipeline_depth",
+    "threads",
+    "auto_segment_size",
+    "engine",
+];
+
+/// 実行ごとに指定するフラグ。設定ファイルからは読み込まず、保存もしない
+/// （GUI で一度チェックしただけで、以後の CLI 実行が黙って再開・削除しないようにするため）。
+pub const RUN_ONLY_KEYS: &[&str] = &["resume", "delete_partials_on_stop"];
+
+/// 環境変数による上書きのプレフィックス。
+pub const ENV_PREFIX: &str = "SOSU_";
+
+impl Config {
+    /// キー名と文字列値で 1 つのフィールドを上書きする（環境変数 / CLI 用）。
+    pub fn set_field(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "prime_min" => self.prime_min = parse_value(key, value)?,
+            "prime_max" => self.prime_max = parse_value(key, value)?,
+            "prime_pi_x" => self.prime_pi_x = parse_value(key, value)?,
+            "segment_size" => self.segment_size = parse_value(key, value)?,
+            "writer_buffer_size" => self.writer_buffer_size = parse_value(key, value)?,
+            "output_format" => self.output_format = value.parse()?,
+            "output_dir" => self.output_dir = value.to_string(),
+            "split_count" => self.split_count = parse_value(key, value)?,
+            "split_width" => self.split_width = parse_value(key, value)?,
+            "index_stride" => self.index_stride = parse_value(key, value)?,
+            "last_prime_only" => self.last_prime_only = parse_bool(key, value)?,
+            "wheel_type" => self.wheel_type = value.parse()?,
+            "memory_usage_percent" => self.memory_usage_percent = parse_value(key, value)?,
+            "memory_ceiling_mb" => self.memory_ceiling_mb = parse_value(key, value)?,
+            "use_timestamp_prefix" => self.use_timestamp_prefix = parse_bool(key, value)?,
+            "resume" => self.resume = parse_bool(key, value)?,
+            "delete_partials_on_stop" => self.delete_partials_on_stop = parse_bool(key, value)?,
+            "pipeline_depth" => self.pipeline_depth = parse_value(key, value)?,
+            "threads" => self.threads = parse_value(key, value)?,
+            "auto_segment_size" => self.auto_segment_size = parse_bool(key, value)?,
+            "engine" => self.engine = value.parse()?,
+            _ => return Err(format!("Unknown setting: {key}")),
+        }
+        Ok(())
+    }
+}
+
+fn parse_value<T: FromStr>(key: &str, value: &str) -> Result<T, String>
+where
+    T::Err: fmt::Display,
+{
+    value
+        .trim()
+        .parse::<T>()
+        .map_err(|e| format!("Invalid value for {key}: {value} ({e})"))
+}
+
+fn parse_bool(key: &str, value: &str) -> Result<bool, String> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "true" | "1" | "yes" | "on" => Ok(true),
+        "false" | "0" | "no" | "off" => Ok(false),
+        _ => Err(format!("Invalid value for {key}: {value} (expected true/false)")),
+    }
+}
+
+/// 実効設定値の出どころ。
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum ConfigSource {
+    /// `Config::default()`（または serde のデフォルト値）
+    Default,
+    /// 設定ファイル（パス）
+    File(String),
+    /// 環境変数（変数名）
+    Env(String),
+    /// CLI フラグ（フラグ名）
+    Cli(String),
+    /// GUI の入力欄（読み込んだ値から変更されたもの）
+    Gui,
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigSource::Default => write!(f, "default"),
+            ConfigSource::File(path) => write!(f, "file ({path})"),
+            ConfigSource::Env(name) => write!(f, "env ({name})"),
+            ConfigSource::Cli(flag) => write!(f, "cli ({flag})"),
+            ConfigSource::Gui => write!(f, "gui"),
+        }
+    }
+}
+
+/// フィールド名ごとの出どころ一覧（`CONFIG_KEYS` の順）。
+pub type ConfigSources = Vec<(String, ConfigSource)>;
+
+/// デフォルト → 設定ファイル → `SOSU_*` 環境変数 → CLI フラグ の順に重ねた設定。
+///
+/// - 後の層ほど優先されます。
+/// - `sources` には各フィールドの値がどの層から来たかを記録し、メタレポートに出力します。
+/// - CLI と GUI の両方がこの型で設定を読み込みます（GUI は `save` で設定ファイルへ書き戻す）。
+#[derive(Debug, Clone)]
+pub struct LayeredConfig {
+    pub config: Config,
+    pub sources: ConfigSources,
+    /// 読み書きする設定ファイル（`--config` で指定したもの、なければ `settings.toml`）
+    pub path: PathBuf,
+    /// 環境変数を重ねる前の値（デフォルト + 設定ファイル）
+    base: Config,
+}
+
+impl LayeredConfig {
+    /// デフォルト値だけの設定（設定ファイルは `settings.toml`）。
+    pub fn defaults() -> Self {
+        Self {
+            config: Config::default(),
+            sources: CONFIG_KEYS
+                .iter()
+                .map(|&key| (key.to_string(), ConfigSource::Default))
+                .collect(),
+            path: PathBuf::from(SETTINGS_FILE),
+            base: Config::default(),
+        }
+    }
+
+    /// デフォルト値・設定ファイル・環境変数を重ねて読み込む。
+    ///
+    /// - `path` が `Some` の場合はそのファイルを必ず読み込む（存在しなければエラー）。
+    /// - `None` の場合は `settings.toml` が存在すれば読み込む（作成はしない）。
+    /// - `RUN_ONLY_KEYS` のキーは設定ファイルにあっても無視する。
+    pub fn load(path: Option<&Path>) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let mut layered = Self::defaults();
+        if let Some(p) = path {
+            layered.path = p.to_path_buf();
+        }
+
+        if path.is_some() || layered.path.exists() {
+            let file_path = layered.path.clone();
+            let contents = std::fs::read_to_string(&file_path)
+                .map_err(|e| format!("Failed to read config file {}: {e}", file_path.display()))?;
+            // 部分的な設定ファイルも受け付けるため、デフォルト値の上にファイルのキーを重ねる
+            let mut table: toml::Table = toml::from_str(&contents)?;
+            table.retain(|key, _| !RUN_ONLY_KEYS.contains(&key));
+            let mut merged = toml::Table::try_from(&layered.config)?;
+            merged.extend(table.clone());
+            layered.config = merged.try_into()?;
+            layered.base = layered.config.clone();
+            let source = ConfigSource::File(file_path.display().to_string());
+            for (key, src) in layered.sources.iter_mut() {
+                if table.contains_key(key.as_str()) {
+                    *src = source.clone();
+                }
+            }
+        }
+
+        for &key in CONFIG_KEYS {
+            let var = format!("{ENV_PREFIX}{}", key.to_ascii_uppercase());
+            if let Ok(value) = std::env::var(&var) {
+                layered
+                    .set(key, &value, ConfigSource::Env(var.clone()))
+                    .map_err(|e| format!("{var}: {e}"))?;
+            }
+        }
+
+        Ok(layered)
+    }
+
+    /// GUI 用に読み込む。`path` が `None` で `settings.toml` が無ければデフォルト値で作成する。
+    pub fn load_or_create(
+        path: Option<&Path>,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let layered = Self::load(path)?;
+        if !layered.path.exists() {
+            write_settings(&layered.path, toml::Table::try_from(&layered.base)?)?;
+        }
+        Ok(layered)
+    }
+
+    /// 1 つのフィールドを上書きし、その出どころを記録する。
+    pub fn set(&mut self, key: &str, value: &str, source: ConfigSource) -> Result<(), String> {
+        self.config.set_field(key, value)?;
+        if let Some((_, src)) = self.sources.iter_mut().find(|(k, _)| *k == key) {
+            *src = source;
+        }
+        Ok(())
+    }
 
-pub fn load_or_create_config() -> Result<Config, Box<dyn std::error::Error + Send + Sync>> {
-    if Path::new(SETTINGS_FILE).exists() {
-        let mut file = File::open(SETTINGS_FILE)?;
-        let mut contents = String::new();
-        file.read_to_string(&mut contents)?;
-        let cfg = toml::from_str(&contents)?;
-        Ok(cfg)
-    } else {
-        let cfg = Config::default();
-        save_config(&cfg)?;
-        Ok(cfg)
+    /// `current`（GUI で編集した設定）の各値の出どころを返す。
+    ///
+    /// - 読み込んだ値から変わったフィールドは `ConfigSource::Gui`、それ以外は読み込み時の出どころ。
+    pub fn sources_for(&self, current: &Config) -> ConfigSources {
+        let (Ok(loaded), Ok(edited)) = (
+            toml::Table::try_from(&self.config),
+            toml::Table::try_from(current),
+        ) else {
+            return self.sources.clone();
+        };
+        self.sources
+            .iter()
+            .map(|(key, source)| {
+                let source = if loaded.get(key) == edited.get(key) {
+                    source.clone()
+                } else {
+                    ConfigSource::Gui
+                };
+                (key.clone(), source)
+            })
+            .collect()
+    }
+
+    /// `current`（GUI で編集した設定）を設定ファイルへ保存する。
+    ///
+    /// - 環境変数で上書きされ、GUI で変更されていない値はファイルへ書き戻さない（ファイル側の値を残す）。
+    /// - `RUN_ONLY_KEYS` のキーは保存しない。
+    pub fn save(&self, current: &Config) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut table = toml::Table::try_from(current)?;
+        let loaded = toml::Table::try_from(&self.config)?;
+        let base = toml::Table::try_from(&self.base)?;
+        for (key, source) in &self.sources {
+            if matches!(source, ConfigSource::Env(_)) && table.get(key) == loaded.get(key) {
+                if let Some(value) = base.get(key) {
+                    table.insert(key.clone(), value.clone());
+                }
+            }
+        }
+        write_settings(&self.path, table)
     }
 }
 
-pub fn save_config(cfg: &Config) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let toml_str = toml::to_string_pretty(cfg)?;
-    let file = File::create(SETTINGS_FILE)?;
+const SETTINGS_FILE: &str = "settings.toml";
+
+/// 設定を TOML で書き出す（`RUN_ONLY_KEYS` のキーは除く）。
+fn write_settings(
+    path: &Path,
+    mut table: toml::Table,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    table.retain(|key, _| !RUN_ONLY_KEYS.contains(&key));
+    let toml_str = toml::to_string_pretty(&table)?;
+    let file = File::create(path)?;
     let mut writer = BufWriter::new(file);
     writer.write_all(toml_str.as_bytes())?;
     Ok(())
 }
-
-
@@ -1,13 +1,15 @@
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::time::Instant;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
 
 use bitvec::prelude::*;
 use rayon::prelude::*;
 
 use crate::config::{Config, WheelType};
-use crate::engine_types::{compute_eta, Progress, PrimeResult};
+use crate::engine_types::{compute_eta, PauseFlag, Progress, PrimeResult};
 use crate::output::PrimeWriter;
-use crate::sieve_math::{integer_sqrt, simple_sieve};
+use crate::sieve_math::{integer_sqrt, sieving_primes, simple_sieve};
 
 /// CPU ベースの分割エラトステネスの篩で素数を生成する。
 ///
@@ -24,7 +26,57 @@ pub fn generate_primes_cpu(
     cfg: &Config,
     stop_flag: &AtomicBool,
     writer: &mut dyn PrimeWriter,
+    progress_cb: impl FnMut(Progress),
+) -> PrimeResult<()> {
+    generate_primes_cpu_resumable(cfg, stop_flag, writer, progress_cb, None, |_, _| Ok(()))
+}
+
+/// チェックポイント／途中再開に対応した `generate_primes_cpu`。
+///
+/// - `resume_from` が `Some(seg_start)` の場合、そのセグメント開始位置から篩を再開します。
+///   ホイールで除外される小さい素数（2, 3, 5 など）は書き出し済みとみなして出力しません。
+/// - `on_group_done(next_seg_start, writer)` は各セグメントグループの出力と進捗報告が
+///   完了するたびに呼ばれます。`next_seg_start` は次に処理するセグメントの開始位置で、
+///   呼び出し側はここで `PrimeWriter::sync_state` を使ってチェックポイントを保存できます。
+/// - 停止時（`stop_flag`）はグループ途中で終了するため、最後のチェックポイント以降に
+///   書き出された素数は再開時に切り捨てられる前提です。
+/// - `cfg.pipeline_depth > 0` の場合は篩を別スレッドで先行させ、篩い終えたグループを
+///   深さ `pipeline_depth` の有界キュー経由で受け取りながら、呼び出しスレッドが書き出しに専念します。
+///   出力順・`progress_cb`・`on_group_done` の呼び出しはどちらのモードでも同じです。
+/// - 篩は実行ごとに作る専用のスレッドプール（`cfg.threads` 本、0 なら全コア）で行い、
+///   グローバルな rayon プールは使いません。
+pub fn generate_primes_cpu_resumable(
+    cfg: &Config,
+    stop_flag: &AtomicBool,
+    writer: &mut dyn PrimeWriter,
+    progress_cb: impl FnMut(Progress),
+    resume_from: Option<u64>,
+    on_group_done: impl FnMut(u64, &mut dyn PrimeWriter) -> PrimeResult<()>,
+) -> PrimeResult<()> {
+    generate_primes_cpu_pausable(
+        cfg,
+        stop_flag,
+        &PauseFlag::new(),
+        writer,
+        progress_cb,
+        resume_from,
+        on_group_done,
+    )
+}
+
+/// 一時停止に対応した `generate_primes_cpu_resumable`。
+///
+/// - `pause_flag` が立っている間は、セグメントグループの篩と書き出しの合間で
+///   状態を保ったままブロックし、再開されるとその続きから処理します。
+/// - 一時停止していた時間は ETA の推定から除外します。
+pub fn generate_primes_cpu_pausable(
+    cfg: &Config,
+    stop_flag: &AtomicBool,
+    pause_flag: &PauseFlag,
+    writer: &mut dyn PrimeWriter,
     mut progress_cb: impl FnMut(Progress),
+    resume_from: Option<u64>,
+    mut on_group_done: impl FnMut(u64, &mut dyn PrimeWriter) -> PrimeResult<()>,
 ) -> PrimeResult<()> {
     let prime_min = cfg.prime_min;
     let prime_max = cfg.prime_max;
@@ -33,7 +85,10 @@ pub fn generate_primes_cpu(
     }
 
     let start_time = Instant::now();
-    let total_range = prime_max - prime_min + 1;
+    // 実行前の一時停止時間は数えない
+    let paused_at_start = pause_flag.paused_secs();
+    // [0, u64::MAX] の個数 2^64 は表せないので飽和させる（進捗表示にしか使わない）
+    let total_range = (prime_max - prime_min).saturating_add(1);
     let wheel_type = cfg.wheel_type;
 
     if stop_flag.load(Ordering::SeqCst) {
@@ -42,37 +97,68 @@ pub fn generate_primes_cpu(
 
     // メモリ制限に基づいてセグメントサイズを調整
     use crate::memory;
-    let num_threads = rayon::current_num_threads();
-    let optimal_segment_size = memory::calculate_optimal_segment_size(
-        cfg.memory_usage_percent,
-        num_threads,
-        wheel_type,
-    );
-    let segment_size = if cfg.segment_size > 0 {
-        cfg.segment_size.min(optimal_segment_size)
+    let pool = build_thread_pool(cfg.threads)?;
+    let num_threads = pool.current_num_threads();
+    log::info!("Worker threads: {}", num_threads);
+    // Writer が個数と最後の素数しか使わない場合（Last Prime Only）や、
+    // mod 30 のビットマップを書き出す場合は素数列を作らない
+    let output = if writer.counts_only() {
+        SegmentOutput::Counts
+    } else if writer.wants_wheel_bits() {
+        SegmentOutput::WheelBits
     } else {
-        optimal_segment_size
+        SegmentOutput::Primes
+    };
+    let memory_model =
+        memory::MemoryModel::from_config(cfg, num_threads, output == SegmentOutput::Counts);
+    let optimal_segment_size =
+        memory::calculate_optimal_segment_size(cfg.memory_usage_percent, &memory_model);
+    // 自動モードでは CPU キャッシュに収まるサイズを選ぶ（メモリから求めたサイズが上限）
+    let cache_choice = cfg.auto_segment_size.then(|| {
+        let cache = memory::detect_cpu_cache();
+        (cache, memory::calculate_cache_segment_size(&cache, wheel_type))
+    });
+    let segment_size = match cache_choice {
+        Some((_, Some(cache_segment_size))) => cache_segment_size.min(optimal_segment_size),
+        Some((_, None)) => optimal_segment_size,
+        None if cfg.segment_size > 0 => cfg.segment_size.min(optimal_segment_size),
+        None => optimal_segment_size,
     };
 
     // メモリ情報をログ出力
-    let mem_info = memory::get_memory_info(segment_size, num_threads, wheel_type);
+    let mem_info = memory::get_memory_info(segment_size, &memory_model);
     log::info!("ホイールタイプ: {:?}, セグメントサイズ: {}", wheel_type, segment_size);
     log::info!("{}", mem_info.format());
+    match cache_choice {
+        Some((cache, Some(cache_segment_size))) => log::info!(
+            "セグメントサイズ自動選択: キャッシュ {} → {} (メモリ上限 {})",
+            cache.format(),
+            cache_segment_size,
+            optimal_segment_size
+        ),
+        Some((_, None)) => log::info!(
+            "セグメントサイズ自動選択: CPU キャッシュを検出できないため、メモリ上限 {} を使用",
+            optimal_segment_size
+        ),
+        None => {}
+    }
 
-    // small primes up to sqrt(max)
-    let root = integer_sqrt(prime_max) + 1;
-    let small_primes = simple_sieve(root)?;
+    // small primes up to sqrt(max)（範囲に倍数を持つものだけ）
+    let small_primes = sieving_primes(prime_min, prime_max)?;
 
     // ホイールタイプに応じた小さい素数の特別処理
     let wheel_excluded_primes: Vec<u64> = match wheel_type {
         WheelType::Odd => vec![2],
         WheelType::Mod6 => vec![2, 3],
         WheelType::Mod30 => vec![2, 3, 5],
+        WheelType::Mod210 => vec![2, 3, 5, 7],
     };
 
-    for &p in &wheel_excluded_primes {
-        if prime_min <= p && p <= prime_max {
-            writer.write_prime(p)?;
+    if resume_from.is_none() {
+        for &p in &wheel_excluded_primes {
+            if prime_min <= p && p <= prime_max {
+                writer.write_prime(p)?;
+            }
         }
     }
 
@@ -89,25 +175,49 @@ pub fn generate_primes_cpu(
             total: total_range,
             eta_secs: Some(0),
         });
+        writer.mark_sieved(prime_max)?;
         writer.finish()?;
         return Ok(());
     }
 
-    // 篩い開始位置を決定
-    let mut seg_start = sieve_start.max(prime_min);
+    // 篩い開始位置を決定（再開時はチェックポイントの位置から）
+    let seg_start = match resume_from {
+        Some(next) => next.max(sieve_start).max(prime_min),
+        None => sieve_start.max(prime_min),
+    };
     if seg_start > prime_max {
+        progress_cb(Progress {
+            processed: total_range,
+            total: total_range,
+            eta_secs: Some(0),
+        });
+        writer.mark_sieved(prime_max)?;
         writer.finish()?;
         return Ok(());
     }
+    // seg_start より前（ホイールで除外された素数まで）は書き出し済み
+    if seg_start > prime_min {
+        writer.mark_sieved(seg_start - 1)?;
+    }
 
     // 全セグメント数を概算（ベクタには保持しない）
-    let remaining = prime_max - seg_start + 1;
-    let total_segments = (remaining.div_ceil(segment_size)) as usize;
+    let total_segments = ((prime_max - seg_start) / segment_size + 1) as usize;
 
     // グループサイズはスレッド数に合わせる（過剰な同時結果保持を防ぐ）
     let group_size = num_threads.max(1);
 
     let total_groups = total_segments.div_ceil(group_size);
+
+    // プロセスの RSS に対するハードリミット（設定されていればグループの大きさを調整する）
+    let memory_ceiling = memory::MemoryCeiling::from_mb(cfg.memory_ceiling_mb);
+    if let Some(ceiling) = &memory_ceiling {
+        log::info!(
+            "Memory ceiling: {:.1}MB (process RSS)",
+            ceiling.limit() as f64 / (1024.0 * 1024.0)
+        );
+    }
+    // 篩い終えてまだ書き出されていないグループ数
+    let groups_in_flight = AtomicUsize::new(0);
     log::info!(
         "Processing ~{} segments in groups of {} (total {} groups)",
         total_segments,
@@ -115,136 +225,672 @@ pub fn generate_primes_cpu(
         total_groups
     );
 
+    // 大きな上限ではセグメント幅を超える素数をバケット篩で扱う
+    // （ホイールの素数 2, 3, 5, 7 は倍数が候補にならないので、セグメント幅が小さくてもバケットに入れない。
+    //   7 より大きい素数はどのホイールでも候補なので、この条件は昇順の素数列で前半だけが真になる）
+    let (sieving_primes, buckets) = if use_bucket_sieve(prime_max, segment_size) {
+        let split = small_primes
+            .partition_point(|&p| p <= segment_size || !is_wheel_candidate(p, wheel_type));
+        let buckets = BucketSieve::new(
+            &small_primes[split..],
+            seg_start,
+            segment_size,
+            prime_max,
+            wheel_type,
+        );
+        log::info!(
+            "Bucket sieve enabled: {} primes > segment size in {} buckets",
+            small_primes.len() - split,
+            buckets.bucket_count()
+        );
+        (&small_primes[..split], Some(buckets))
+    } else {
+        (&small_primes[..], None)
+    };
+
     // 進捗カウンタ（seg_start より前の範囲は既に処理済みとみなす）
     let mut processed = seg_start.saturating_sub(prime_min);
+    // ETA はこの実行で処理した分だけから推定する（再開前の処理済み範囲を除く）
+    let initial_processed = processed;
 
-    #[derive(Clone)]
-    struct SegmentResult {
-        low: u64,
-        high: u64,
-        primes: Vec<u64>,
+    match output {
+        SegmentOutput::Counts => {
+            log::info!("Writer needs counts only: using popcount sieve path")
+        }
+        SegmentOutput::WheelBits => {
+            log::info!("Writer takes wheel bits: writing sieve bits as mod 30 bytes")
+        }
+        SegmentOutput::Primes => {}
     }
 
-    let mut group_index = 0usize;
+    let mut sieve = GroupSieve {
+        next_seg_start: Some(seg_start),
+        prime_max,
+        segment_size,
+        group_size,
+        max_group_size: group_size,
+        total_groups,
+        group_index: 0,
+        sieving_primes,
+        buckets,
+        output,
+        wheel_type,
+        stop_flag,
+        pause_flag,
+        pool: &pool,
+        memory_ceiling,
+        groups_in_flight: &groups_in_flight,
+    };
+
+    // 篩い終えたグループを順に書き出す。停止フラグで中断した場合は false を返す
+    let mut write_group = |group: SieveGroup| -> PrimeResult<bool> {
+        // 即座に出力（グループごとにメモリを解放）
+        for res in group.results {
+            pause_flag.wait_while_paused(stop_flag);
+            if stop_flag.load(Ordering::SeqCst) {
+                return Ok(false);
+            }
 
-    // セグメントを逐次生成しつつ、グループ単位で並列処理
-    while seg_start <= prime_max {
-        if stop_flag.load(Ordering::SeqCst) {
-            writer.finish()?;
-            return Ok(());
+            match output {
+                SegmentOutput::Primes => {
+                    for p in res.primes {
+                        writer.write_prime(p)?;
+                    }
+                }
+                SegmentOutput::Counts => {
+                    writer.write_count(res.counted.count, res.counted.last_prime)?;
+                }
+                SegmentOutput::WheelBits => {
+                    writer.write_wheel_bits(
+                        res.low / 30,
+                        &res.wheel_bits,
+                        res.counted.count,
+                        res.counted.first_prime,
+                        res.counted.last_prime,
+                    )?;
+                }
+            }
+            writer.mark_sieved(res.high)?;
+
+            processed = processed.saturating_add(res.high - res.low + 1);
         }
 
-        group_index += 1;
+        // グループ処理完了後に進捗を更新（リアルタイム）
+        let elapsed = start_time.elapsed().as_secs_f64();
+        let eta_secs = compute_eta(
+            processed.min(total_range) - initial_processed,
+            total_range - initial_processed,
+            elapsed,
+            pause_flag.paused_secs() - paused_at_start,
+        );
 
-        // このグループで処理するセグメント境界を生成（メモリに保持するのはこのグループ分だけ）
-        let mut group_bounds: Vec<(u64, u64)> = Vec::with_capacity(group_size);
-        for _ in 0..group_size {
-            if seg_start > prime_max {
+        progress_cb(Progress {
+            processed: processed.min(total_range),
+            total: total_range,
+            eta_secs,
+        });
+
+        log::info!(
+            "Group {}/{} completed. Overall progress: {:.1}%",
+            group.index,
+            total_groups,
+            (processed.min(total_range) as f64 / total_range as f64) * 100.0
+        );
+
+        // 最後まで篩い終えた場合は prime_max の次（u64::MAX では表せないので飽和）を渡す
+        on_group_done(
+            group.next_seg_start.unwrap_or(prime_max.saturating_add(1)),
+            writer,
+        )?;
+        Ok(true)
+    };
+
+    if cfg.pipeline_depth == 0 {
+        // グループごとに「並列に篩う → 呼び出しスレッドで書き出す」を交互に行う
+        while let Some(group) = sieve.next_group() {
+            if !write_group(group)? {
                 break;
             }
+        }
+    } else {
+        // 篩はスレッドで先行させ、書き出しは呼び出しスレッドが専任で行う。
+        // キューには最大 pipeline_depth グループが溜まり、それ以上は篩側が待つ。
+        log::info!(
+            "Pipelined output enabled: up to {} sieved groups queued for the writer",
+            cfg.pipeline_depth
+        );
+        std::thread::scope(|scope| -> PrimeResult<()> {
+            let (sender, receiver) = mpsc::sync_channel::<SieveGroup>(cfg.pipeline_depth);
+            scope.spawn(move || {
+                while let Some(group) = sieve.next_group() {
+                    // 書き出し側が終了（停止・エラー）したら篩も止める
+                    if sender.send(group).is_err() {
+                        break;
+                    }
+                }
+            });
+            for group in receiver {
+                if !write_group(group)? {
+                    break;
+                }
+            }
+            Ok(())
+        })?;
+    }
+
+    if stop_flag.load(Ordering::SeqCst) {
+        writer.finish()?;
+        return Ok(());
+    }
+    log::info!("All processing completed");
+
+    writer.finish()?;
+    Ok(())
+}
+
+/// 篩に使うスレッドプールを作る（`threads` が 0 なら rayon の既定どおり全コアを使う）。
+pub fn build_thread_pool(threads: usize) -> PrimeResult<rayon::ThreadPool> {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .thread_name(|i| format!("sieve-worker-{i}"))
+        .build()?;
+    Ok(pool)
+}
+
+/// メモリ上限を超えている間に、書き出し待ちのグループが捌けたかを見直す間隔
+const MEMORY_CEILING_POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// RSS がメモリ上限のこの割合を超えたらグループを半分にする
+const MEMORY_CEILING_SHRINK_RATIO: f64 = 0.85;
+/// RSS がメモリ上限のこの割合を下回ったらグループを元の大きさへ戻していく
+const MEMORY_CEILING_GROW_RATIO: f64 = 0.7;
+
+/// Writer へセグメントの篩の結果を渡す形
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SegmentOutput {
+    /// 素数列（`write_prime`）
+    Primes,
+    /// 個数と最後の素数（`write_count`）
+    Counts,
+    /// mod 30 のバイト列と個数（`write_wheel_bits`）
+    WheelBits,
+}
+
+/// 1 セグメント分の篩の結果（`SegmentOutput` に応じて、`primes` / `counted` / `wheel_bits` と `counted` のいずれかを使う）
+struct SegmentResult {
+    low: u64,
+    high: u64,
+    primes: Vec<u64>,
+    counted: SegmentCount,
+    /// ブロック `low / 30` からの mod 30 のバイト列
+    wheel_bits: Vec<u8>,
+}
+
+/// 篩い終えた 1 グループ（`results` はセグメント開始値の昇順）
+struct SieveGroup<'a> {
+    /// 1 から始まるグループ番号（ログ用）
+    index: usize,
+    results: Vec<SegmentResult>,
+    /// 次のグループの開始位置（最後まで篩い終えたら None）
+    next_seg_start: Option<u64>,
+    /// 書き出し（または破棄）されるまで書き出し待ちのグループとして数える
+    _in_flight: InFlightGroup<'a>,
+}
+
+/// 書き出し待ちのグループ数を、グループがドロップされたときに減らすガード
+struct InFlightGroup<'a>(&'a AtomicUsize);
+
+impl<'a> InFlightGroup<'a> {
+    fn new(counter: &'a AtomicUsize) -> Self {
+        counter.fetch_add(1, Ordering::SeqCst);
+        Self(counter)
+    }
+}
+
+impl Drop for InFlightGroup<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// セグメントを `group_size` 個ずつ並列に篩い、グループ単位で結果を返す（書き出しはしない）。
+///
+/// - バケット篩はセグメント順に取り出す必要があるため、`next_group` は 1 スレッドから順に呼びます。
+/// - 停止フラグが立っているか、`prime_max` まで篩い終えたら `None` を返します。
+/// - 一時停止中は次のグループを篩い始める前に待ちます。
+/// - メモリ上限（`memory_ceiling`）があれば、プロセスの RSS に応じて `group_size` を
+///   `max_group_size` 以下で増減させ、上限を超えたら書き出し待ちのグループが捌けるまで待ちます。
+struct GroupSieve<'a> {
+    next_seg_start: Option<u64>,
+    prime_max: u64,
+    segment_size: u64,
+    group_size: usize,
+    max_group_size: usize,
+    total_groups: usize,
+    group_index: usize,
+    sieving_primes: &'a [u64],
+    buckets: Option<BucketSieve>,
+    output: SegmentOutput,
+    wheel_type: WheelType,
+    stop_flag: &'a AtomicBool,
+    pause_flag: &'a PauseFlag,
+    pool: &'a rayon::ThreadPool,
+    memory_ceiling: Option<crate::memory::MemoryCeiling>,
+    groups_in_flight: &'a AtomicUsize,
+}
+
+impl<'a> GroupSieve<'a> {
+    fn next_group(&mut self) -> Option<SieveGroup<'a>> {
+        let mut seg_start = self.next_seg_start?;
+        self.pause_flag.wait_while_paused(self.stop_flag);
+        self.apply_memory_ceiling();
+        if self.stop_flag.load(Ordering::SeqCst) {
+            return None;
+        }
+
+        self.group_index += 1;
+
+        // このグループで処理するセグメント境界を生成（メモリに保持するのはこのグループ分だけ）
+        let mut group_bounds: Vec<(u64, u64)> = Vec::with_capacity(self.group_size);
+        for _ in 0..self.group_size {
             let seg_end = seg_start
-                .saturating_add(segment_size - 1)
-                .min(prime_max);
+                .saturating_add(self.segment_size - 1)
+                .min(self.prime_max);
             group_bounds.push((seg_start, seg_end));
-            seg_start = seg_end.saturating_add(1);
+            self.next_seg_start = seg_end.checked_add(1).filter(|&n| n <= self.prime_max);
+            match self.next_seg_start {
+                Some(next) => seg_start = next,
+                None => break,
+            }
         }
 
-        if group_bounds.is_empty() {
-            break;
-        }
+        // バケット篩: 各セグメントで消す大きな素数の倍数を、セグメント順に取り出しておく
+        let bucket_hits: Vec<Vec<u64>> = match self.buckets.as_mut() {
+            Some(buckets) => group_bounds
+                .iter()
+                .map(|&(_, high)| buckets.take_segment_hits(high))
+                .collect(),
+            None => vec![Vec::new(); group_bounds.len()],
+        };
 
         log::info!(
             "Processing group {}/{} ({} segments)...",
-            group_index,
-            total_groups,
+            self.group_index,
+            self.total_groups,
             group_bounds.len()
         );
 
         // グループ内を並列処理
-        let mut results: Vec<SegmentResult> = group_bounds
-            .par_iter()
-            .map(|&(low, high)| {
-                if stop_flag.load(Ordering::SeqCst) {
-                    SegmentResult {
+        let (sieving_primes, stop_flag, wheel_type) =
+            (self.sieving_primes, self.stop_flag, self.wheel_type);
+        let output = self.output;
+        let mut results: Vec<SegmentResult> = self.pool.install(|| {
+            group_bounds
+                .par_iter()
+                .zip(bucket_hits.par_iter())
+                .map(|(&(low, high), hits)| {
+                    let mut res = SegmentResult {
                         low,
                         high,
                         primes: Vec::new(),
+                        counted: SegmentCount::default(),
+                        wheel_bits: Vec::new(),
+                    };
+                    if stop_flag.load(Ordering::SeqCst) {
+                        return res;
                     }
-                } else {
-                    let primes =
-                        sieve_segment_collect(low, high, &small_primes, stop_flag, wheel_type);
-                    SegmentResult { low, high, primes }
-                }
-            })
-            .collect();
+                    match output {
+                        SegmentOutput::Primes => {
+                            res.primes = sieve_segment_collect(
+                                low,
+                                high,
+                                sieving_primes,
+                                hits,
+                                stop_flag,
+                                wheel_type,
+                            );
+                        }
+                        SegmentOutput::Counts => {
+                            res.counted = sieve_segment_count(
+                                low,
+                                high,
+                                sieving_primes,
+                                hits,
+                                stop_flag,
+                                wheel_type,
+                            );
+                        }
+                        SegmentOutput::WheelBits => {
+                            (res.wheel_bits, res.counted) = sieve_segment_wheel_bits(
+                                low,
+                                high,
+                                sieving_primes,
+                                hits,
+                                stop_flag,
+                                wheel_type,
+                            );
+                        }
+                    }
+                    res
+                })
+                .collect()
+        });
 
         // セグメント開始値でソートして順序を保証
         results.sort_by_key(|r| r.low);
 
-        // 即座に出力（グループごとにメモリを解放）
-        for res in results {
-            if stop_flag.load(Ordering::SeqCst) {
-                writer.finish()?;
-                return Ok(());
+        Some(SieveGroup {
+            index: self.group_index,
+            results,
+            next_seg_start: self.next_seg_start,
+            _in_flight: InFlightGroup::new(self.groups_in_flight),
+        })
+    }
+
+    /// プロセスの RSS に応じて次のグループの大きさを決める（メモリ上限がなければ何もしない）。
+    ///
+    /// 上限を超えている間は 1 セグメントずつにし、書き出し待ちのグループがあればそれが
+    /// 書き出されるまで待ちます。書き出し待ちがなければ、待っても RSS は減らないのでそのまま進みます。
+    fn apply_memory_ceiling(&mut self) {
+        let Some(ceiling) = self.memory_ceiling.as_mut() else {
+            return;
+        };
+        let mut waited = false;
+        while let Some((rss, ratio)) = ceiling.usage() {
+            if ratio < 1.0 {
+                if ratio >= MEMORY_CEILING_SHRINK_RATIO && self.group_size > 1 {
+                    self.group_size = (self.group_size / 2).max(1);
+                    log::info!(
+                        "Process RSS {:.1}MB is near the memory ceiling: group size reduced to {}",
+                        rss as f64 / (1024.0 * 1024.0),
+                        self.group_size
+                    );
+                } else if ratio < MEMORY_CEILING_GROW_RATIO && self.group_size < self.max_group_size {
+                    self.group_size = (self.group_size * 2).min(self.max_group_size);
+                }
+                return;
             }
 
-            for p in res.primes {
-                writer.write_prime(p)?;
+            self.group_size = 1;
+            if self.groups_in_flight.load(Ordering::SeqCst) == 0
+                || self.stop_flag.load(Ordering::SeqCst)
+            {
+                return;
+            }
+            if !waited {
+                log::warn!(
+                    "Process RSS {:.1}MB exceeds the memory ceiling: waiting for queued groups to be written",
+                    rss as f64 / (1024.0 * 1024.0)
+                );
+                waited = true;
             }
+            std::thread::sleep(MEMORY_CEILING_POLL_INTERVAL);
+        }
+    }
+}
 
-            processed = processed.saturating_add(res.high - res.low + 1);
+pub(crate) fn sieve_segment_collect(
+    low_inclusive: u64,
+    high_inclusive: u64,
+    small_primes: &[u64],
+    bucket_hits: &[u64],
+    stop_flag: &AtomicBool,
+    wheel_type: WheelType,
+) -> Vec<u64> {
+    let Some((low, is_prime)) =
+        sieve_segment_bits(
+            low_inclusive,
+            high_inclusive,
+            small_primes,
+            bucket_hits,
+            stop_flag,
+            wheel_type,
+        )
+    else {
+        return Vec::new();
+    };
+
+    // 素数を収集
+    is_prime
+        .iter_ones()
+        .map(|i| index_to_n(i, low, wheel_type))
+        .collect()
+}
+
+/// セグメント内の素数の個数と最小・最大の素数（カウント専用経路・ビットマップ経路）。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct SegmentCount {
+    pub count: u64,
+    pub first_prime: Option<u64>,
+    pub last_prime: Option<u64>,
+}
+
+impl SegmentCount {
+    /// `sieve_segment_bits` の結果から数える。
+    fn from_bits(low: u64, is_prime: &BitVec, wheel_type: WheelType) -> Self {
+        Self {
+            count: is_prime.count_ones() as u64,
+            first_prime: is_prime.first_one().map(|i| index_to_n(i, low, wheel_type)),
+            last_prime: is_prime.last_one().map(|i| index_to_n(i, low, wheel_type)),
         }
+    }
+}
 
-        // グループ処理完了後に進捗を更新（リアルタイム）
-        let elapsed = start_time.elapsed().as_secs_f64();
-        let eta_secs = compute_eta(processed.min(total_range), total_range, elapsed);
+/// `sieve_segment_collect` と同じ篩を行い、素数列を作らずに個数と最大の素数だけを返す。
+///
+/// - 個数はビット配列の popcount、最小・最大の素数は最初と最後に立っているビットから求めます。
+/// - 停止フラグで中断された場合は空の結果を返します。
+pub(crate) fn sieve_segment_count(
+    low_inclusive: u64,
+    high_inclusive: u64,
+    small_primes: &[u64],
+    bucket_hits: &[u64],
+    stop_flag: &AtomicBool,
+    wheel_type: WheelType,
+) -> SegmentCount {
+    let Some((low, is_prime)) =
+        sieve_segment_bits(
+            low_inclusive,
+            high_inclusive,
+            small_primes,
+            bucket_hits,
+            stop_flag,
+            wheel_type,
+        )
+    else {
+        return SegmentCount::default();
+    };
 
-        progress_cb(Progress {
-            processed: processed.min(total_range),
-            total: total_range,
-            eta_secs,
-        });
+    SegmentCount::from_bits(low, &is_prime, wheel_type)
+}
 
-        log::info!(
-            "Group {}/{} completed. Overall progress: {:.1}%",
-            group_index,
-            total_groups,
-            (processed.min(total_range) as f64 / total_range as f64) * 100.0
-        );
+/// `sieve_segment_count` と同じ篩を行い、ビット配列を mod 30 のバイト列（`wheel_bitmap` の本体と同じ配置）に変換して返す。
+///
+/// - バイト列はブロック `low_inclusive / 30` から `high_inclusive / 30` までを表し、区間外のビットは 0 です。
+/// - Mod30 のビット配列は候補の並びが mod 30 のバイトと同じなので、8 ビットずつ区間の開始位置までずらして詰めます。
+///   他のホイールでは立っているビット（素数）ごとに値を求めて置き直します（2, 3, 5 はバイト列に現れません）。
+/// - 停止フラグで中断された場合は空の結果を返します。
+pub(crate) fn sieve_segment_wheel_bits(
+    low_inclusive: u64,
+    high_inclusive: u64,
+    small_primes: &[u64],
+    bucket_hits: &[u64],
+    stop_flag: &AtomicBool,
+    wheel_type: WheelType,
+) -> (Vec<u8>, SegmentCount) {
+    let first_block = low_inclusive / 30;
+    let mut bytes = vec![0u8; (high_inclusive / 30 - first_block + 1) as usize];
+    let Some((low, is_prime)) =
+        sieve_segment_bits(
+            low_inclusive,
+            high_inclusive,
+            small_primes,
+            bucket_hits,
+            stop_flag,
+            wheel_type,
+        )
+    else {
+        return (bytes, SegmentCount::default());
+    };
+
+    if wheel_type == WheelType::Mod30 {
+        // インデックス 0（low）の、バイト列の先頭からのビット位置
+        let offset = ((low / 30 - first_block) * 8) as usize
+            + MOD30_TO_INDEX[(low % 30) as usize] as usize;
+        for (k, chunk) in is_prime.chunks(8).enumerate() {
+            let bits = u16::from(chunk.load_le::<u8>()) << (offset % 8);
+            let at = offset / 8 + k;
+            bytes[at] |= bits as u8;
+            if bits > 0xff {
+                bytes[at + 1] |= (bits >> 8) as u8;
+            }
+        }
+    } else {
+        for i in is_prime.iter_ones() {
+            let n = index_to_n(i, low, wheel_type);
+            let bit = MOD30_TO_INDEX[(n % 30) as usize];
+            if bit != 255 {
+                bytes[(n / 30 - first_block) as usize] |= 1 << bit;
+            }
+        }
     }
 
-    log::info!("All processing completed");
+    (bytes, SegmentCount::from_bits(low, &is_prime, wheel_type))
+}
 
-    writer.finish()?;
-    Ok(())
+/// `count_primes_sieve` が 1 回に篩う幅（mod 30 ホイールで約 1 MB のビット配列）
+const COUNT_SEGMENT_SIZE: u64 = 30 << 20;
+
+/// 区間 `[a, b]` の素数の個数を分割篩で数える（素数列は作らない）。
+///
+/// primecount を使う `compute_prime_pi` とは独立した実装なので、
+/// `π(b) - π(a - 1)` の検算に使えます。`a > b` の場合は 0 を返します。
+pub fn count_primes_sieve(a: u64, b: u64) -> PrimeResult<u64> {
+    if a > b {
+        return Ok(0);
+    }
+
+    let wheel_type = WheelType::Mod30;
+    let wheel_count = [2u64, 3, 5].iter().filter(|&&p| a <= p && p <= b).count() as u64;
+    let sieve_low = a.max(7);
+    if sieve_low > b {
+        return Ok(wheel_count);
+    }
+
+    let small_primes = sieving_primes(sieve_low, b)?;
+    let segments = (b - sieve_low) / COUNT_SEGMENT_SIZE + 1;
+    let stop_flag = AtomicBool::new(false);
+
+    let sieved: u64 = (0..segments)
+        .into_par_iter()
+        .map(|i| {
+            let low = sieve_low + i * COUNT_SEGMENT_SIZE;
+            let high = low.saturating_add(COUNT_SEGMENT_SIZE - 1).min(b);
+            sieve_segment_count(low, high, &small_primes, &[], &stop_flag, wheel_type).count
+        })
+        .sum();
+
+    Ok(wheel_count + sieved)
 }
 
-fn sieve_segment_collect(
+/// セグメント `[low_inclusive, high_inclusive]` を篩い、ホイール候補ごとの素数フラグを返す。
+///
+/// - 戻り値の `u64` はホイール候補に合わせて調整した開始値で、インデックス 0 に対応します。
+/// - `bucket_hits` はバケット篩が求めた、このセグメント内の大きな素数の倍数です。
+/// - ビット配列の長さは区間内の候補数ちょうどなので、立っているビットはすべて区間内の素数です。
+/// - 候補が存在しない区間や、停止フラグで中断された場合は `None`。
+fn sieve_segment_bits(
     low_inclusive: u64,
     high_inclusive: u64,
     small_primes: &[u64],
+    bucket_hits: &[u64],
     stop_flag: &AtomicBool,
     wheel_type: WheelType,
-) -> Vec<u64> {
+) -> Option<(u64, BitVec)> {
+    sieve_segment_bits_with(
+        low_inclusive,
+        high_inclusive,
+        small_primes,
+        bucket_hits,
+        stop_flag,
+        wheel_type,
+        MarkingStrategy::WheelStride,
+    )
+}
+
+/// 倍数消去の実装を指定する `sieve_segment_bits`。
+fn sieve_segment_bits_with(
+    low_inclusive: u64,
+    high_inclusive: u64,
+    small_primes: &[u64],
+    bucket_hits: &[u64],
+    stop_flag: &AtomicBool,
+    wheel_type: WheelType,
+    marking: MarkingStrategy,
+) -> Option<(u64, BitVec)> {
     if low_inclusive > high_inclusive {
-        return Vec::new();
+        return None;
     }
 
-    // ホイールに応じた最小値の調整
-    let low = adjust_low(low_inclusive, wheel_type);
+    // ホイールに応じた最小値の調整（u64::MAX 付近で次の候補がなければ区間は空）
+    let low = adjust_low(low_inclusive, wheel_type)?;
     if low > high_inclusive {
-        return Vec::new();
+        return None;
     }
     let high = high_inclusive;
 
-    // ビット配列のサイズを計算
+    // ビット配列のサイズを計算（[low, high] の候補数ちょうど）
     let len = calculate_bitvec_size(low, high, wheel_type);
-    let mut is_prime = bitvec![1; len];
+    // 通常は 7〜19 の倍数を消し済みのパターンから初期化する（参照実装はすべて篩う）
+    let mut is_prime = match marking {
+        MarkingStrategy::WheelStride => presieved_bits(low, high, len, wheel_type),
+        MarkingStrategy::Reference => bitvec![1; len],
+    };
 
-    // 篩処理
+    let completed = match marking {
+        MarkingStrategy::WheelStride => cross_off_wheel_stride(
+            &mut is_prime,
+            low,
+            high,
+            small_primes,
+            stop_flag,
+            wheel_type,
+        ),
+        MarkingStrategy::Reference => {
+            cross_off_reference(&mut is_prime, low, high, small_primes, stop_flag, wheel_type)
+        }
+    };
+    if !completed {
+        return None;
+    }
+
+    // バケット篩から渡された大きな素数の倍数を消す
+    for &n in bucket_hits {
+        if let Some(idx) = n_to_index(n, low, wheel_type) {
+            if idx < len {
+                is_prime.set(idx, false);
+            }
+        }
+    }
+
+    Some((low, is_prime))
+}
+
+/// 倍数消去の参照実装。
+///
+/// 倍数ごとに `n_to_index` でインデックスを求め、候補でない倍数は `n += p` で読み飛ばします。
+/// 単純で検証しやすい代わりに、倍数 1 つごとに除算・剰余が必要です。停止した場合は `false`。
+fn cross_off_reference(
+    is_prime: &mut BitVec,
+    low: u64,
+    high: u64,
+    small_primes: &[u64],
+    stop_flag: &AtomicBool,
+    wheel_type: WheelType,
+) -> bool {
+    let len = is_prime.len();
     for &p in small_primes {
         if stop_flag.load(Ordering::SeqCst) {
-            return Vec::new();
+            return false;
         }
         
         // ホイールで既に除外されている素数はスキップ
@@ -264,20 +910,27 @@ fn sieve_segment_collect(
                     continue;
                 }
             }
+            WheelType::Mod210 => {
+                if p == 2 || p == 3 || p == 5 || p == 7 {
+                    continue;
+                }
+            }
         }
         
-        if p * p > high {
+        let Some(square) = p.checked_mul(p).filter(|&sq| sq <= high) else {
             break;
-        }
+        };
 
-        // 開始位置を計算
-        let mut start = if low % p == 0 {
-            low
+        // 開始位置を計算（u64::MAX を超える倍数は区間外なので次の素数へ）
+        let Some(mut start) = (if low.is_multiple_of(p) {
+            Some(low)
         } else {
-            low + (p - (low % p))
+            low.checked_add(p - (low % p))
+        }) else {
+            continue;
         };
-        if start < p * p {
-            start = p * p;
+        if start < square {
+            start = square;
         }
 
         // ホイールの候補に合わせて調整
@@ -285,14 +938,17 @@ fn sieve_segment_collect(
             if n_to_index(start, low, wheel_type).is_some() {
                 break;
             }
-            start += p;
+            match start.checked_add(p) {
+                Some(next) => start = next,
+                None => break,
+            }
         }
 
         // マーク処理
         let mut n = start;
         while n <= high {
             if stop_flag.load(Ordering::SeqCst) {
-                return Vec::new();
+                return false;
             }
             
             if let Some(idx) = n_to_index(n, low, wheel_type) {
@@ -302,25 +958,453 @@ fn sieve_segment_collect(
             }
             
             // 次の候補を探す
-            n += p;
+            let Some(next) = n.checked_add(p) else {
+                break;
+            };
+            n = next;
             while n <= high && n_to_index(n, low, wheel_type).is_none() {
-                n += p;
+                let Some(next) = n.checked_add(p) else {
+                    break;
+                };
+                n = next;
             }
         }
     }
 
-    // 素数を収集
-    let mut primes = Vec::new();
-    for (i, bit) in is_prime.iter().by_vals().enumerate() {
-        if bit {
-            let n = index_to_n(i, low, wheel_type);
-            if n <= high {
-                primes.push(n);
+    true
+}
+
+/// 倍数消去のホイール刻み実装（通常はこちらを使う）。
+///
+/// 素数 `p` の倍数のうちホイール候補になるものだけを、事前に求めたビットインデックスの
+/// 増分（mod 30 なら 8 種類）を順に足して直接消していきます。停止した場合は `false`。
+/// 事前篩パターン（`presieved_bits`）で消し済みの素数は飛ばします。
+fn cross_off_wheel_stride(
+    is_prime: &mut BitVec,
+    low: u64,
+    high: u64,
+    small_primes: &[u64],
+    stop_flag: &AtomicBool,
+    wheel_type: WheelType,
+) -> bool {
+    let len = is_prime.len();
+    let table = WheelStrideTable::get(wheel_type);
+    let mut strides = [0usize; MAX_WHEEL_RESIDUES];
+    let strides = &mut strides[..table.residue_count()];
+
+    for &p in small_primes {
+        if stop_flag.load(Ordering::SeqCst) {
+            return false;
+        }
+        // ホイールで既に除外されている素数はスキップ
+        if p <= table.modulus && !is_wheel_candidate(p, wheel_type) {
+            continue;
+        }
+        // 事前篩パターンに含まれる素数はスキップ
+        if PRESIEVE_PRIMES.contains(&p) {
+            continue;
+        }
+        let Some(square) = p.checked_mul(p).filter(|&sq| sq <= high) else {
+            break;
+        };
+
+        // low 以上かつ p^2 以上で、ホイール候補になる最初の倍数 p * k
+        let from = low.max(square);
+        let mut k = from.div_ceil(p);
+        while !is_wheel_candidate(k, wheel_type) {
+            k += 1;
+        }
+        let Some(mut idx) = k
+            .checked_mul(p)
+            .filter(|&n| n <= high)
+            .and_then(|n| n_to_index(n, low, wheel_type))
+        else {
+            continue;
+        };
+
+        table.fill_strides(p, strides);
+        let mut j = table.position(k);
+        while idx < len {
+            is_prime.set(idx, false);
+            idx += strides[j];
+            j += 1;
+            if j == strides.len() {
+                j = 0;
+            }
+        }
+    }
+    true
+}
+
+/// 事前篩の対象にする小さい素数（ホイールで除外される素数は除いて使う）
+const PRESIEVE_PRIMES: [u64; 5] = [7, 11, 13, 17, 19];
+
+/// 7〜19 の倍数を消し済みのビットパターン（ホイール候補のインデックス空間）。
+///
+/// 倍数のパターンは「ホイールの周期 × 7·11·13·17·19」で繰り返すので、
+/// 1 周期分を作っておき、各セグメントは位相を合わせてコピーするだけで初期化できます
+/// （mod 30 では 8 × 323,323 ビット ≒ 316 KiB）。
+fn presieve_pattern(wheel_type: WheelType) -> &'static BitVec {
+    static ODD: OnceLock<BitVec> = OnceLock::new();
+    static MOD6: OnceLock<BitVec> = OnceLock::new();
+    static MOD30: OnceLock<BitVec> = OnceLock::new();
+    static MOD210: OnceLock<BitVec> = OnceLock::new();
+    let cell = match wheel_type {
+        WheelType::Odd => &ODD,
+        WheelType::Mod6 => &MOD6,
+        WheelType::Mod30 => &MOD30,
+        WheelType::Mod210 => &MOD210,
+    };
+    cell.get_or_init(|| {
+        let table = WheelStrideTable::get(wheel_type);
+        let primes: Vec<u64> = PRESIEVE_PRIMES
+            .into_iter()
+            .filter(|&p| is_wheel_candidate(p, wheel_type))
+            .collect();
+        let count = table.residue_count() as u64;
+        let period = count * primes.iter().product::<u64>();
+        (0..period)
+            .map(|i| {
+                let n = (i / count) * table.modulus + table.residues[(i % count) as usize];
+                primes.iter().all(|&p| !n.is_multiple_of(p))
+            })
+            .collect()
+    })
+}
+
+/// セグメント（先頭の候補 `low`、長さ `len`）のビット配列を事前篩パターンから作る。
+fn presieved_bits(low: u64, high: u64, len: usize, wheel_type: WheelType) -> BitVec {
+    let pattern = presieve_pattern(wheel_type);
+    let period = pattern.len() as u64;
+    let mut pos = (WheelStrideTable::get(wheel_type).global_index(low) % period) as usize;
+
+    let mut bits = BitVec::with_capacity(len);
+    while bits.len() < len {
+        let take = (pattern.len() - pos).min(len - bits.len());
+        bits.extend_from_bitslice(&pattern[pos..pos + take]);
+        pos = 0;
+    }
+
+    // パターンでは 7〜19 自身も消えているので、区間内にあれば素数として戻す
+    for p in PRESIEVE_PRIMES {
+        if low <= p && p <= high {
+            if let Some(idx) = n_to_index(p, low, wheel_type) {
+                bits.set(idx, true);
+            }
+        }
+    }
+    bits
+}
+
+/// セグメント篩で合成数のビットを消す（倍数消去）の実装。
+///
+/// 生成処理では常に `WheelStride` を使います。`Reference` は元の実装を残したもので、
+/// テストで両者の結果を突き合わせるために `sieve_range_with` から選べます。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarkingStrategy {
+    /// 事前篩パターンで初期化し、素数ごとのホイール刻みでビットインデックスを直接進める
+    WheelStride,
+    /// 倍数ごとに `n_to_index` で候補判定する参照実装
+    Reference,
+}
+
+/// `[low, high]` を 1 セグメントとして篩い、区間内の素数（ホイール素数を含む）を返す。
+///
+/// 倍数消去の実装を選べるテスト・比較用の入口です。
+pub fn sieve_range_with(
+    low: u64,
+    high: u64,
+    wheel_type: WheelType,
+    marking: MarkingStrategy,
+) -> PrimeResult<Vec<u64>> {
+    if low > high {
+        return Ok(Vec::new());
+    }
+    let small_primes = simple_sieve(integer_sqrt(high) + 1)?;
+    let stop_flag = AtomicBool::new(false);
+
+    let mut primes: Vec<u64> = small_primes
+        .iter()
+        .copied()
+        .take_while(|&p| p <= WheelStrideTable::get(wheel_type).modulus)
+        .filter(|&p| !is_wheel_candidate(p, wheel_type) && low <= p && p <= high)
+        .collect();
+    // 1 はホイール候補だが素数ではないので篩の対象から外す
+    if let Some((base, is_prime)) = sieve_segment_bits_with(
+        low.max(2),
+        high,
+        &small_primes,
+        &[],
+        &stop_flag,
+        wheel_type,
+        marking,
+    ) {
+        primes.extend(is_prime.iter_ones().map(|i| index_to_n(i, base, wheel_type)));
+    }
+    Ok(primes)
+}
+
+/// ホイール 1 周あたりの候補数の最大値（mod 210 の 48）
+const MAX_WHEEL_RESIDUES: usize = 48;
+
+/// ホイール刻みの倍数消去に使う表。
+///
+/// 素数 `p = q * M + r`（M はホイールの周期）の候補倍数 `p * k` から次の候補倍数
+/// `p * (k + g_j)` へのビットインデックスの増分は `q * C * g_j + base[r][j]` と書けます
+/// （C は 1 周あたりの候補数、`g_j` は k の剰余が j 番目の候補のときの次の候補までの差）。
+/// `base` は `r` と `j` だけで決まるので、ホイールごとに 1 回だけ求めておきます。
+struct WheelStrideTable {
+    modulus: u64,
+    /// 周期内の候補（昇順）
+    residues: Vec<u64>,
+    /// 剰余 → 候補番号（候補でなければ usize::MAX）
+    position: Vec<usize>,
+    /// 候補 j から次の候補までの差（最後は次の周期の先頭まで）
+    gaps: Vec<u64>,
+    /// `base[rpos * C + j]`
+    base: Vec<u64>,
+}
+
+impl WheelStrideTable {
+    fn new(wheel_type: WheelType) -> Self {
+        let modulus = match wheel_type {
+            WheelType::Odd => 2,
+            WheelType::Mod6 => 6,
+            WheelType::Mod30 => 30,
+            WheelType::Mod210 => 210,
+        };
+        let residues: Vec<u64> = (1..modulus)
+            .filter(|&r| is_wheel_candidate(r, wheel_type))
+            .collect();
+        let count = residues.len();
+        let mut position = vec![usize::MAX; modulus as usize];
+        for (j, &r) in residues.iter().enumerate() {
+            position[r as usize] = j;
+        }
+        let gaps: Vec<u64> = (0..count)
+            .map(|j| {
+                let next = residues.get(j + 1).copied().unwrap_or(residues[0] + modulus);
+                next - residues[j]
+            })
+            .collect();
+
+        // 大域インデックス（周期数 * C + 周期内の番号）
+        let global_index =
+            |n: u64| (n / modulus) * count as u64 + position[(n % modulus) as usize] as u64;
+        let mut base = Vec::with_capacity(count * count);
+        for &r in &residues {
+            for j in 0..count {
+                let k = residues[j];
+                base.push(global_index(r * (k + gaps[j])) - global_index(r * k));
             }
         }
+
+        Self {
+            modulus,
+            residues,
+            position,
+            gaps,
+            base,
+        }
+    }
+
+    /// ホイールごとに 1 回だけ作った表を返す。
+    fn get(wheel_type: WheelType) -> &'static Self {
+        static ODD: OnceLock<WheelStrideTable> = OnceLock::new();
+        static MOD6: OnceLock<WheelStrideTable> = OnceLock::new();
+        static MOD30: OnceLock<WheelStrideTable> = OnceLock::new();
+        static MOD210: OnceLock<WheelStrideTable> = OnceLock::new();
+        let cell = match wheel_type {
+            WheelType::Odd => &ODD,
+            WheelType::Mod6 => &MOD6,
+            WheelType::Mod30 => &MOD30,
+            WheelType::Mod210 => &MOD210,
+        };
+        cell.get_or_init(|| Self::new(wheel_type))
+    }
+
+    fn residue_count(&self) -> usize {
+        self.residues.len()
+    }
+
+    /// 候補 `n` の大域インデックス（0 からの候補の通し番号）
+    fn global_index(&self, n: u64) -> u64 {
+        (n / self.modulus) * self.residue_count() as u64 + self.position(n) as u64
+    }
+
+    /// 候補 `k` の周期内の番号
+    fn position(&self, k: u64) -> usize {
+        self.position[(k % self.modulus) as usize]
+    }
+
+    /// 素数 `p` について、候補番号 j の倍数から次の候補倍数へのインデックス増分を `strides[j]` に書く。
+    fn fill_strides(&self, p: u64, strides: &mut [usize]) {
+        let count = self.residue_count();
+        let q = p / self.modulus;
+        let row = self.position(p) * count;
+        for (j, stride) in strides.iter_mut().enumerate() {
+            *stride = (q * count as u64 * self.gaps[j] + self.base[row + j]) as usize;
+        }
     }
+}
+
+/// `prime_max` とセグメント幅からバケット篩を使うかどうかを決める。
+///
+/// - `segment_size` は 1 セグメントが受け持つ整数の個数です（ビット配列のバイト数ではありません）。
+/// - 篩に使う √prime_max 以下の素数にセグメント幅を超えるものがあれば使います。
+///   そうした素数は 1 セグメントに 0〜1 回しか現れないため、セグメントごとに開始位置を
+///   剰余で求め直すより、次の倍数をバケットで持ち越すほうが安く済みます。
+pub fn use_bucket_sieve(prime_max: u64, segment_size: u64) -> bool {
+    integer_sqrt(prime_max) > segment_size
+}
+
+/// バケット篩の 1 エントリ（素数と、その次に消すべき倍数）
+#[derive(Debug, Clone, Copy)]
+struct BucketEntry {
+    prime: u64,
+    next: u64,
+}
 
-    primes
+/// セグメント幅より大きい篩素数を扱うバケット篩。
+///
+/// - 扱うのはホイール候補の素数だけです（ホイールの素数の倍数は候補に現れず、`candidate_multiple` が見つけられないため）。
+/// - 各素数は「次に消す倍数が入るセグメント」のバケットに置かれ、
+///   そのセグメントを処理するときに倍数を 1 つ取り出して次のバケットへ移ります。
+/// - 素数はセグメント幅より大きいので、1 セグメントあたり高々 1 回しか現れません。
+/// - バケットはリングバッファで、倍数が進む最大距離（ホイールの最大ギャップ × 素数）を収められる個数を持ちます。
+/// - 最初の倍数 `p^2` がリングより先にある素数は `pending` に置き、`p^2` を含むセグメントで加えます。
+struct BucketSieve {
+    buckets: Vec<Vec<BucketEntry>>,
+    /// まだバケットに入れていない素数（昇順。最初に消す倍数は `p^2`）
+    pending: Vec<u64>,
+    /// `pending` のうち次に加える素数の位置
+    pending_next: usize,
+    /// バケット 0 に対応するセグメントの開始値
+    base: u64,
+    segment_size: u64,
+    prime_max: u64,
+    wheel_type: WheelType,
+    /// 次に取り出すセグメントの番号
+    next_segment: u64,
+}
+
+impl BucketSieve {
+    fn new(
+        large_primes: &[u64],
+        seg_start: u64,
+        segment_size: u64,
+        prime_max: u64,
+        wheel_type: WheelType,
+    ) -> Self {
+        let max_step = large_primes.last().copied().unwrap_or(0) * wheel_max_gap(wheel_type);
+        let bucket_count = (max_step / segment_size + 2) as usize;
+        let mut sieve = Self {
+            buckets: vec![Vec::new(); bucket_count],
+            pending: Vec::new(),
+            pending_next: 0,
+            base: seg_start,
+            segment_size,
+            prime_max,
+            wheel_type,
+            next_segment: 0,
+        };
+
+        for &p in large_primes {
+            // seg_start 以上かつ p^2 以上の最初の倍数
+            let Some(square) = p.checked_mul(p).filter(|&sq| sq <= prime_max) else {
+                break;
+            };
+            if square >= seg_start {
+                // p^2 はリングの範囲より先にありうるので、そのセグメントに達してから加える
+                sieve.pending.push(p);
+                continue;
+            }
+            let first = seg_start.div_ceil(p).checked_mul(p);
+            if let Some(next) = first.and_then(|n| sieve.candidate_multiple(p, n)) {
+                sieve.push(BucketEntry { prime: p, next });
+            }
+        }
+        sieve
+    }
+
+    fn bucket_count(&self) -> usize {
+        self.buckets.len()
+    }
+
+    /// `n` 以上で最初にホイール候補になる `p` の倍数（`prime_max` を超える場合は None）
+    fn candidate_multiple(&self, p: u64, mut n: u64) -> Option<u64> {
+        while n <= self.prime_max {
+            if is_wheel_candidate(n, self.wheel_type) {
+                return Some(n);
+            }
+            n = n.checked_add(p)?;
+        }
+        None
+    }
+
+    fn push(&mut self, entry: BucketEntry) {
+        let segment = (entry.next - self.base) / self.segment_size;
+        let slot = (segment % self.buckets.len() as u64) as usize;
+        self.buckets[slot].push(entry);
+    }
+
+    /// 次のセグメント（終端 `seg_high`）で消す倍数を取り出し、各素数を次の倍数へ進める。
+    ///
+    /// セグメントは開始値から順に、1 つずつ呼び出す必要があります。
+    fn take_segment_hits(&mut self, seg_high: u64) -> Vec<u64> {
+        // p^2 がこのセグメントに入った素数を加える（ホイールの素数は入れないので p は候補で、p^2 も候補）
+        while let Some(&p) = self.pending.get(self.pending_next) {
+            if p * p > seg_high {
+                break;
+            }
+            self.push(BucketEntry {
+                prime: p,
+                next: p * p,
+            });
+            self.pending_next += 1;
+        }
+
+        let slot = (self.next_segment % self.buckets.len() as u64) as usize;
+        self.next_segment += 1;
+
+        let entries = std::mem::take(&mut self.buckets[slot]);
+        let mut hits = Vec::with_capacity(entries.len());
+        for entry in entries {
+            debug_assert!(entry.next <= seg_high);
+            hits.push(entry.next);
+            let advanced = entry
+                .next
+                .checked_add(entry.prime)
+                .and_then(|n| self.candidate_multiple(entry.prime, n));
+            if let Some(next) = advanced {
+                self.push(BucketEntry {
+                    prime: entry.prime,
+                    next,
+                });
+            }
+        }
+        hits
+    }
+}
+
+/// ホイールの候補（篩のビット配列に含まれる値）かどうか
+fn is_wheel_candidate(n: u64, wheel_type: WheelType) -> bool {
+    match wheel_type {
+        WheelType::Odd => n % 2 == 1,
+        WheelType::Mod6 => matches!(n % 6, 1 | 5),
+        WheelType::Mod30 => MOD30_TO_INDEX[(n % 30) as usize] != 255,
+        WheelType::Mod210 => MOD210_TO_INDEX[(n % 210) as usize] != 255,
+    }
+}
+
+/// 隣り合うホイール候補の最大間隔（倍数を次の候補へ進めるときの最大ステップ数）
+fn wheel_max_gap(wheel_type: WheelType) -> u64 {
+    match wheel_type {
+        WheelType::Odd => 2,
+        WheelType::Mod6 => 4,
+        WheelType::Mod30 => 6,
+        WheelType::Mod210 => 10,
+    }
 }
 
 // ========== ホイール構造関連の関数 ==========
@@ -337,6 +1421,24 @@ const MOD30_TO_INDEX: [u8; 30] = [
     255, 255, 255, 255, 255, 7            // 24-29
 ];
 
+/// mod 210 ホイールの候補パターン (210 と互いに素な余り)
+const MOD210_PATTERN: [u64; 48] = [
+    1, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67,
+    71, 73, 79, 83, 89, 97, 101, 103, 107, 109, 113, 121, 127, 131, 137, 139,
+    143, 149, 151, 157, 163, 167, 169, 173, 179, 181, 187, 191, 193, 197, 199, 209,
+];
+
+/// mod 210 での余りからインデックスへのマッピング（候補でない数は 255）
+const MOD210_TO_INDEX: [u8; 210] = {
+    let mut table = [255u8; 210];
+    let mut i = 0;
+    while i < MOD210_PATTERN.len() {
+        table[MOD210_PATTERN[i] as usize] = i as u8;
+        i += 1;
+    }
+    table
+};
+
 /// ホイールタイプに応じた数値nからインデックスへの変換
 /// low: セグメントの開始位置（調整済み）
 /// 戻り値: Some(index) または None（候補でない場合）
@@ -386,6 +1488,21 @@ fn n_to_index(n: u64, low: u64, wheel_type: WheelType) -> Option<usize> {
             let low_in_period = (low / 30) * 8 + low_idx as u64;
             let n_in_period = (n / 30) * 8 + n_idx as u64;
             
+            Some((n_in_period - low_in_period) as usize)
+        }
+        WheelType::Mod210 => {
+            // mod 210: パターンテーブルを使用
+            let n_idx = MOD210_TO_INDEX[(n % 210) as usize];
+            let low_idx = MOD210_TO_INDEX[(low % 210) as usize];
+
+            if n_idx == 255 || low_idx == 255 {
+                return None;
+            }
+
+            // lowからnまでの候補の個数を数える
+            let low_in_period = (low / 210) * 48 + low_idx as u64;
+            let n_in_period = (n / 210) * 48 + n_idx as u64;
+
             Some((n_in_period - low_in_period) as usize)
         }
     }
@@ -435,64 +1552,102 @@ fn index_to_n(idx: usize, low: u64, wheel_type: WheelType) -> u64 {
             
             period * 30 + MOD30_PATTERN[offset]
         }
+        WheelType::Mod210 => {
+            // mod 210: パターンテーブルを使用
+            let low_idx = MOD210_TO_INDEX[(low % 210) as usize];
+            debug_assert!(low_idx != 255, "low must be a candidate");
+
+            // idx番目の候補を計算
+            let target_in_period = (low / 210) * 48 + low_idx as u64 + idx as u64;
+            let period = target_in_period / 48;
+            let offset = (target_in_period % 48) as usize;
+
+            period * 210 + MOD210_PATTERN[offset]
+        }
     }
 }
 
-/// ホイールタイプに応じたビット配列のサイズを計算
+/// ホイールタイプに応じたビット配列のサイズ（`[low, high]` の候補数）を計算
+/// low: 候補値（`adjust_low` 済み、1 以上）
 fn calculate_bitvec_size(low: u64, high: u64, wheel_type: WheelType) -> usize {
+    debug_assert!(low >= 1, "low must be a candidate");
+    (candidates_up_to(high, wheel_type) - candidates_up_to(low - 1, wheel_type)) as usize
+}
+
+/// `[0, x]` に含まれるホイール候補の個数（u64::MAX まで桁あふれしない）
+fn candidates_up_to(x: u64, wheel_type: WheelType) -> u64 {
     match wheel_type {
         WheelType::Odd => {
-            // 奇数のみ: (high - low) / 2 + 1
-            ((high - low) / 2 + 1) as usize
+            // 奇数のみ: (x + 1) / 2 を桁あふれなしで
+            x / 2 + x % 2
         }
         WheelType::Mod6 => {
-            // mod 6: 6 周期あたり 2 個
-            let range = high - low + 1;
-            ((range / 6) * 2 + 2) as usize  // 余裕を持たせる
+            // mod 6: 6 周期あたり 2 個（6k+1, 6k+5）
+            let rem = x % 6;
+            (x / 6) * 2 + u64::from(rem >= 1) + u64::from(rem >= 5)
         }
         WheelType::Mod30 => {
             // mod 30: 30 周期あたり 8 個
-            let range = high - low + 1;
-            ((range / 30) * 8 + 8) as usize  // 余裕を持たせる
+            let rem = x % 30;
+            (x / 30) * 8 + MOD30_PATTERN.iter().filter(|&&r| r <= rem).count() as u64
+        }
+        WheelType::Mod210 => {
+            // mod 210: 210 周期あたり 48 個
+            let rem = x % 210;
+            (x / 210) * 48 + MOD210_PATTERN.iter().filter(|&&r| r <= rem).count() as u64
         }
     }
 }
 
 /// low を次の候補値に調整
-fn adjust_low(low: u64, wheel_type: WheelType) -> u64 {
+///
+/// u64::MAX 付近で次の候補が表現できない場合は None
+fn adjust_low(low: u64, wheel_type: WheelType) -> Option<u64> {
     match wheel_type {
         WheelType::Odd => {
-            if low % 2 == 0 {
-                low + 1
+            if low.is_multiple_of(2) {
+                low.checked_add(1)
             } else {
-                low
+                Some(low)
             }
         }
         WheelType::Mod6 => {
             let rem = low % 6;
             match rem {
-                1 | 5 => low,  // 既に候補
-                0 => low + 1,  // 6k → 6k+1
-                2 => low + 3,  // 6k+2 → 6k+5
-                3 => low + 2,  // 6k+3 → 6k+5
-                4 => low + 1,  // 6k+4 → 6k+5
+                1 | 5 => Some(low),        // 既に候補
+                0 => low.checked_add(1),   // 6k → 6k+1
+                2 => low.checked_add(3),   // 6k+2 → 6k+5
+                3 => low.checked_add(2),   // 6k+3 → 6k+5
+                4 => low.checked_add(1),   // 6k+4 → 6k+5
                 _ => unreachable!(),
             }
         }
         WheelType::Mod30 => {
             let rem = low % 30;
             if MOD30_TO_INDEX[rem as usize] != 255 {
-                return low;  // 既に候補
+                return Some(low);  // 既に候補
             }
             
             // 次の候補に調整
             for offset in 1..30 {
                 let new_rem = ((rem + offset) % 30) as usize;
                 if MOD30_TO_INDEX[new_rem] != 255 {
-                    return low + offset;
+                    return low.checked_add(offset);
+                }
+            }
+            None  // ここには到達しないはず
+        }
+        WheelType::Mod210 => {
+            let rem = low % 210;
+
+            // 次の候補に調整（候補の最大間隔は 10）
+            for offset in 0..=10 {
+                let new_rem = ((rem + offset) % 210) as usize;
+                if MOD210_TO_INDEX[new_rem] != 255 {
+                    return low.checked_add(offset);
                 }
             }
-            low  // ここには到達しないはず
+            None  // ここには到達しないはず
         }
     }
 }
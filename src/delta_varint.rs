@@ -0,0 +1,235 @@
+//! 差分 varint 形式（`OutputFormat::DeltaVarint`）の素数ファイル。
+//!
+//! ファイル構成（すべて little-endian）:
+//!
+//! | オフセット | サイズ | 内容 |
+//! |---|---|---|
+//! | 0  | 4 | マジック `b"SSDV"` |
+//! | 4  | 4 | 形式バージョン（`u32`） |
+//! | 8  | 8 | 基準値 = ファイル内の最初の素数（`u64`、空なら 0） |
+//! | 16 | 8 | 素数の個数（`u64`） |
+//! | 24 | - | 2 つ目以降の素数の「直前との差 / 2」を LEB128 varint で並べたもの |
+//!
+//! - 奇素数同士の差は必ず偶数なので 1/2 にして格納します（多くの差が 1 バイトに収まる）。
+//! - 唯一の奇数の差である 2 → 3 は、通常は現れない値 `0` で表します。
+//! - 基準値と個数はファイルを閉じるときに書き戻します。
+
+use std::fs::File;
+use std::io::{self, BufReader, Read, Write};
+use std::path::Path;
+
+/// ファイル先頭のマジック
+pub const DELTA_VARINT_MAGIC: [u8; 4] = *b"SSDV";
+/// 形式バージョン
+pub const DELTA_VARINT_VERSION: u32 = 1;
+/// ヘッダのバイト数
+pub const DELTA_VARINT_HEADER_LEN: u64 = 24;
+/// ヘッダ内で基準値が置かれるオフセット（個数はその直後）
+pub const DELTA_VARINT_BASE_OFFSET: u64 = 8;
+
+/// 差分 varint ファイルのヘッダ。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeltaVarintHeader {
+    pub version: u32,
+    /// ファイル内の最初の素数（空ファイルでは 0）
+    pub base: u64,
+    /// ファイル内の素数の個数
+    pub count: u64,
+}
+
+impl DeltaVarintHeader {
+    pub fn new(base: u64, count: u64) -> Self {
+        Self {
+            version: DELTA_VARINT_VERSION,
+            base,
+            count,
+        }
+    }
+
+    pub fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_all(&DELTA_VARINT_MAGIC)?;
+        w.write_all(&self.version.to_le_bytes())?;
+        w.write_all(&self.base.to_le_bytes())?;
+        w.write_all(&self.count.to_le_bytes())
+    }
+
+    pub fn read_from<R: Read>(r: &mut R) -> io::Result<Self> {
+        let mut buf = [0u8; DELTA_VARINT_HEADER_LEN as usize];
+        r.read_exact(&mut buf)?;
+        if buf[0..4] != DELTA_VARINT_MAGIC {
+            return Err(invalid_data("not a delta-varint primes file (bad magic)"));
+        }
+        let version = u32::from_le_bytes(buf[4..8].try_into().unwrap());
+        if version != DELTA_VARINT_VERSION {
+            return Err(invalid_data(format!(
+                "unsupported delta-varint version {version} (expected {DELTA_VARINT_VERSION})"
+            )));
+        }
+        Ok(Self {
+            version,
+            base: u64::from_le_bytes(buf[8..16].try_into().unwrap()),
+            count: u64::from_le_bytes(buf[16..24].try_into().unwrap()),
+        })
+    }
+}
+
+/// `prev` の次の素数 `p` を格納する値（差 / 2、ただし 2 → 3 は 0）。
+pub fn encode_gap(prev: u64, p: u64) -> u64 {
+    if prev == 2 {
+        0
+    } else {
+        (p - prev) / 2
+    }
+}
+
+/// `encode_gap` の逆変換。
+pub fn decode_gap(prev: u64, value: u64) -> Option<u64> {
+    if value == 0 {
+        (prev == 2).then_some(3)
+    } else {
+        value.checked_mul(2).and_then(|gap| prev.checked_add(gap))
+    }
+}
+
+/// LEB128 で `value` を書き込む。
+pub fn write_varint<W: Write>(w: &mut W, mut value: u64) -> io::Result<()> {
+    let mut buf = [0u8; 10];
+    let mut len = 0;
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            buf[len] = byte;
+            len += 1;
+            break;
+        }
+        buf[len] = byte | 0x80;
+        len += 1;
+    }
+    w.write_all(&buf[..len])
+}
+
+/// LEB128 の値を 1 つ読む。データ末尾に達していれば `Ok(None)`。
+pub fn read_varint<R: Read>(r: &mut R) -> io::Result<Option<u64>> {
+    let mut value = 0u64;
+    let mut shift = 0u32;
+    let mut byte = [0u8; 1];
+    loop {
+        if r.read(&mut byte)? == 0 {
+            return if shift == 0 {
+                Ok(None)
+            } else {
+                Err(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated varint"))
+            };
+        }
+        if shift >= 64 || (shift == 63 && byte[0] & 0x7e != 0) {
+            return Err(invalid_data("varint overflows u64"));
+        }
+        value |= ((byte[0] & 0x7f) as u64) << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok(Some(value));
+        }
+        shift += 7;
+    }
+}
+
+/// 差分 varint ファイルを先頭から順に読むリーダー。
+///
+/// - `Iterator<Item = io::Result<u64>>` として素数を昇順に返します。
+/// - ヘッダの個数より実データが短い・長い場合はエラーを返して終了します。
+pub struct DeltaVarintReader<R: Read> {
+    reader: R,
+    header: DeltaVarintHeader,
+    read: u64,
+    prev: u64,
+    finished: bool,
+}
+
+impl DeltaVarintReader<BufReader<File>> {
+    /// ファイルを開いてヘッダを読み込む。
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = File::open(path)?;
+        Self::new(BufReader::with_capacity(8 * 1024 * 1024, file))
+    }
+}
+
+impl<R: Read> DeltaVarintReader<R> {
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let header = DeltaVarintHeader::read_from(&mut reader)?;
+        Ok(Self {
+            reader,
+            header,
+            read: 0,
+            prev: 0,
+            finished: false,
+        })
+    }
+
+    pub fn header(&self) -> &DeltaVarintHeader {
+        &self.header
+    }
+
+    fn next_value(&mut self) -> io::Result<Option<u64>> {
+        if self.read == self.header.count {
+            // 個数分を読み終えたら、余分なデータが残っていないことを確認する
+            let mut byte = [0u8; 1];
+            if self.reader.read(&mut byte)? != 0 {
+                return Err(invalid_data(format!(
+                    "trailing data after {} values",
+                    self.header.count
+                )));
+            }
+            return Ok(None);
+        }
+
+        let value = if self.read == 0 {
+            self.header.base
+        } else {
+            let encoded = read_varint(&mut self.reader)?.ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    format!(
+                        "file ends after {} of {} values",
+                        self.read, self.header.count
+                    ),
+                )
+            })?;
+            decode_gap(self.prev, encoded).ok_or_else(|| {
+                invalid_data(format!(
+                    "invalid gap value {encoded} after {} at value {}",
+                    self.prev,
+                    self.read + 1
+                ))
+            })?
+        };
+
+        self.read += 1;
+        self.prev = value;
+        Ok(Some(value))
+    }
+}
+
+impl<R: Read> Iterator for DeltaVarintReader<R> {
+    type Item = io::Result<u64>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        match self.next_value() {
+            Ok(Some(value)) => Some(Ok(value)),
+            Ok(None) => {
+                self.finished = true;
+                None
+            }
+            Err(e) => {
+                self.finished = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+fn invalid_data(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
@@ -1,4 +1,7 @@
 use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 // エンジン層（CPU / GPU / 検証）で共有するエラー型と進捗情報の定義。
 //
@@ -34,10 +37,12 @@ pub struct Progress {
 /// - `processed` / `total` は 0 以上で、`processed <= total` を想定しています。
 /// - 進捗 0% の間は `None` を返し、ある程度進んでから ETA を表示する前提です。
 /// - CPU / GPU エンジン双方から呼び出され、UI に渡す `Progress::eta_secs` の元になります。
-pub fn compute_eta(processed: u64, total: u64, elapsed_secs: f64) -> Option<u64> {
+/// - `paused_secs` は経過時間のうち一時停止（`PauseFlag`）していた秒数で、処理速度の推定から除外します。
+pub fn compute_eta(processed: u64, total: u64, elapsed_secs: f64, paused_secs: f64) -> Option<u64> {
     if total == 0 {
         return None;
     }
+    let elapsed_secs = (elapsed_secs - paused_secs).max(0.0);
     let progress = processed.min(total) as f64 / total as f64;
     if progress > 0.0 {
         let total_time = elapsed_secs / progress;
@@ -47,4 +52,71 @@ pub fn compute_eta(processed: u64, total: u64, elapsed_secs: f64) -> Option<u64>
     }
 }
 
+/// 一時停止中にフラグを見直す間隔。
+const PAUSE_POLL_INTERVAL: Duration = Duration::from_millis(50);
 
+/// 長時間実行タスクの一時停止フラグ。
+///
+/// - UI が `pause` / `resume` を呼び、エンジンは `stop_flag` を確認する箇所で
+///   `wait_while_paused` を呼んで、状態を保ったまま再開されるまでブロックします。
+/// - 一時停止していた時間を累計し、ETA の計算（`compute_eta`）から除外できるようにします。
+#[derive(Debug, Default)]
+pub struct PauseFlag {
+    paused: AtomicBool,
+    timing: Mutex<PauseTiming>,
+}
+
+#[derive(Debug, Default)]
+struct PauseTiming {
+    /// これまでに一時停止していた時間の合計（現在の一時停止分を除く）
+    total: Duration,
+    /// 一時停止中ならその開始時刻
+    since: Option<Instant>,
+}
+
+impl PauseFlag {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 一時停止する（既に一時停止中なら何もしない）。
+    pub fn pause(&self) {
+        let mut timing = self.timing.lock().unwrap_or_else(|e| e.into_inner());
+        timing.since.get_or_insert_with(Instant::now);
+        self.paused.store(true, Ordering::SeqCst);
+    }
+
+    /// 再開する（一時停止中でなければ何もしない）。
+    pub fn resume(&self) {
+        let mut timing = self.timing.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(since) = timing.since.take() {
+            timing.total += since.elapsed();
+        }
+        self.paused.store(false, Ordering::SeqCst);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    /// これまでに一時停止していた時間の合計（秒）。一時停止中ならその時点までを含みます。
+    ///
+    /// 値はフラグを作ってからの累計なので、1 回の実行分は開始時の値との差で求めます。
+    pub fn paused_secs(&self) -> f64 {
+        let timing = self.timing.lock().unwrap_or_else(|e| e.into_inner());
+        let current = timing.since.map_or(Duration::ZERO, |since| since.elapsed());
+        (timing.total + current).as_secs_f64()
+    }
+
+    /// 一時停止中なら、再開されるか `stop_flag` が立つまでブロックする。
+    ///
+    /// 実際に待った場合は true を返します。
+    pub fn wait_while_paused(&self, stop_flag: &AtomicBool) -> bool {
+        let mut waited = false;
+        while self.is_paused() && !stop_flag.load(Ordering::SeqCst) {
+            waited = true;
+            std::thread::sleep(PAUSE_POLL_INTERVAL);
+        }
+        waited
+    }
+}
@@ -2,14 +2,16 @@
 //!
 //! - π(x) vs x/log x のアニメーショングラフを描画するためのデータを生成します。
 //! - primecount を使って π(x) を計算し、UI にデータポイントを送信します。
+//! - どのループも `stop_flag` を確認する箇所で `pause_flag` も確認し、一時停止中はそこで待ちます。
 
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{mpsc, Arc};
 
+use crate::engine_types::PauseFlag;
 use crate::prime_pi_engine::compute_prime_pi;
 use crate::sieve_math::simple_sieve;
-use crate::worker_message::WorkerMessage;
 use crate::verify::is_probable_prime;
+use crate::worker_message::WorkerMessage;
 
 /// Explore モードのアニメーションを開始する。
 ///
@@ -21,6 +23,7 @@ pub fn start_explore_animation(
     prime_max: u64,
     speed: f32,
     stop_flag: Arc<AtomicBool>,
+    pause_flag: Arc<PauseFlag>,
     sender: mpsc::Sender<WorkerMessage>,
 ) {
     std::thread::spawn(move || {
@@ -50,19 +53,24 @@ pub fn start_explore_animation(
         let mut step = 0;
 
         while x <= prime_max && !stop_flag.load(Ordering::SeqCst) {
+            wait_if_paused(&pause_flag, &stop_flag, &sender);
+            if stop_flag.load(Ordering::SeqCst) {
+                break;
+            }
+
             // π(x) を計算
             match compute_prime_pi(x) {
                 Ok(pi_x) => {
-                    if sender
-                        .send(WorkerMessage::ExploreData { x, pi_x })
-                        .is_err()
-                    {
+                    if sender.send(WorkerMessage::ExploreData { x, pi_x }).is_err() {
                         break;
                     }
                 }
                 Err(e) => {
                     sender
-                        .send(WorkerMessage::Log(format!("Error computing π({}): {}", x, e)))
+                        .send(WorkerMessage::Log(format!(
+                            "Error computing π({}): {}",
+                            x, e
+                        )))
                         .ok();
                     break;
                 }
@@ -90,6 +98,7 @@ pub fn start_explore_animation(
             }
         }
 
+        wait_if_paused(&pause_flag, &stop_flag, &sender);
         if stop_flag.load(Ordering::SeqCst) {
             sender.send(WorkerMessage::Stopped).ok();
         } else {
@@ -114,6 +123,7 @@ pub fn start_gap_animation(
     prime_max: u64,
     speed: f32,
     stop_flag: Arc<AtomicBool>,
+    pause_flag: Arc<PauseFlag>,
     sender: mpsc::Sender<WorkerMessage>,
 ) {
     std::thread::spawn(move || {
@@ -192,6 +202,7 @@ pub fn start_gap_animation(
         };
 
         for (idx, (prev, prime, gap)) in gaps.into_iter().enumerate() {
+            wait_if_paused(&pause_flag, &stop_flag, &sender);
             if stop_flag.load(Ordering::SeqCst) {
                 sender.send(WorkerMessage::Stopped).ok();
                 return;
@@ -242,6 +253,7 @@ pub fn start_density_animation(
     interval_size: u64,
     speed: f32,
     stop_flag: Arc<AtomicBool>,
+    pause_flag: Arc<PauseFlag>,
     sender: mpsc::Sender<WorkerMessage>,
 ) {
     std::thread::spawn(move || {
@@ -287,6 +299,7 @@ pub fn start_density_animation(
         let mut start = prime_min;
 
         while start <= prime_max {
+            wait_if_paused(&pause_flag, &stop_flag, &sender);
             if stop_flag.load(Ordering::SeqCst) {
                 sender.send(WorkerMessage::Stopped).ok();
                 return;
@@ -330,6 +343,7 @@ pub fn start_density_animation(
         };
 
         for (i, (start, count)) in intervals.into_iter().enumerate() {
+            wait_if_paused(&pause_flag, &stop_flag, &sender);
             if stop_flag.load(Ordering::SeqCst) {
                 sender.send(WorkerMessage::Stopped).ok();
                 return;
@@ -380,6 +394,7 @@ pub fn start_spiral_generation(
     size: usize,
     speed: f32,
     stop_flag: Arc<AtomicBool>,
+    pause_flag: Arc<PauseFlag>,
     sender: mpsc::Sender<WorkerMessage>,
 ) {
     std::thread::spawn(move || {
@@ -410,6 +425,7 @@ pub fn start_spiral_generation(
         // ステップ順一次元配列として、center, center+1, ... の素数判定を行う
         let update_every: u64 = (size as u64).max(1);
         for step in 0..total_cells {
+            wait_if_paused(&pause_flag, &stop_flag, &sender);
             if stop_flag.load(Ordering::SeqCst) {
                 sender.send(WorkerMessage::Stopped).ok();
                 return;
@@ -448,3 +464,16 @@ pub fn start_spiral_generation(
     });
 }
 
+/// 一時停止中なら再開されるか停止されるまで待ち、その前後で `WorkerMessage::Paused` を送る。
+fn wait_if_paused(
+    pause_flag: &PauseFlag,
+    stop_flag: &AtomicBool,
+    sender: &mpsc::Sender<WorkerMessage>,
+) {
+    if !pause_flag.is_paused() {
+        return;
+    }
+    sender.send(WorkerMessage::Paused(true)).ok();
+    pause_flag.wait_while_paused(stop_flag);
+    sender.send(WorkerMessage::Paused(false)).ok();
+}
@@ -1,8 +1,14 @@
 pub mod config;
 pub mod output;
+pub mod delta_varint;
+pub mod wheel_bitmap;
+pub mod prime_index;
 pub mod engine_types;
 pub mod sieve_math;
 pub mod cpu_engine;
+pub mod sieve_engine;
+pub mod prime_iter;
+pub mod checkpoint;
 pub mod prime_pi_engine;
 mod app_state;
 mod app_style;
@@ -22,5 +28,6 @@ pub mod ui_panel_spiral;
 pub mod worker_message;
 pub mod worker_jobs;
 pub mod explore_engine;
+pub mod cli;
 
 
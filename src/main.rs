@@ -1,9 +1,17 @@
 #![cfg_attr(all(windows, not(debug_assertions)), windows_subsystem = "windows")]
 
+use std::path::PathBuf;
+
 use eframe::NativeOptions;
 use sosu_seisei_main2::prime_pi_engine::compute_prime_pi;
 
 fn main() -> eframe::Result<()> {
+    // リリースビルドの Windows では GUI サブシステムで起動するため、引数付き（CLI 利用）の
+    // 場合は呼び出し元のコンソールに接続して標準出力・標準エラーを表示できるようにする。
+    if std::env::args_os().len() > 1 {
+        attach_parent_console();
+    }
+
     env_logger::init();
 
     // CLI モード: `--prime-pi <x>` が指定されている場合は GUI を起動せず、
@@ -12,6 +20,13 @@ fn main() -> eframe::Result<()> {
         return Ok(());
     }
 
+    // CLI サブコマンド（`generate` など）が指定されている場合も GUI は起動しない。
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(code) = sosu_seisei_main2::cli::try_run_subcommand(&args) {
+        std::process::exit(code);
+    }
+
+    let config_path = gui_config_path(&args);
     let options = NativeOptions {
         viewport: eframe::egui::ViewportBuilder::default()
             .with_inner_size([900.0, 700.0])
@@ -21,10 +36,42 @@ fn main() -> eframe::Result<()> {
     eframe::run_native(
         "Sosu-Seisei Settings 2",
         options,
-        Box::new(|cc| Ok(Box::new(sosu_seisei_main2::app::MyApp::new(cc)))),
+        Box::new(move |cc| {
+            Ok(Box::new(sosu_seisei_main2::app::MyApp::new(
+                cc,
+                config_path.as_deref(),
+            )))
+        }),
     )
 }
 
+/// 呼び出し元プロセスのコンソールに接続する（Windows 以外では何もしない）。
+///
+/// - コンソールから起動されていない場合（エクスプローラーからの起動など）は接続に失敗するが、無視してよい。
+#[cfg(windows)]
+fn attach_parent_console() {
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+
+    // SAFETY: 引数は定数のみで、失敗しても戻り値を無視するだけで副作用はない。
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(windows))]
+fn attach_parent_console() {}
+
+/// GUI 起動時の `--config <path>`（読み書きする設定ファイル）を取り出す。
+fn gui_config_path(args: &[String]) -> Option<PathBuf> {
+    let position = args.iter().position(|a| a == "--config")?;
+    args.get(position + 1).map(PathBuf::from)
+}
+
 /// `--prime-pi <x>` 形式の CLI オプションを処理する。
 ///
 /// - 対応例:
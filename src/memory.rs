@@ -1,5 +1,9 @@
-use sysinfo::System;
-use crate::config::WheelType;
+use std::path::Path;
+
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System};
+use crate::config::{Config, WheelType};
+use crate::cpu_engine::use_bucket_sieve;
+use crate::sieve_math::integer_sqrt;
 
 /// システムの物理メモリ総量を取得（バイト単位）
 pub fn get_total_memory() -> u64 {
@@ -8,12 +12,80 @@ pub fn get_total_memory() -> u64 {
     sys.total_memory()
 }
 
+/// このプロセスの常駐メモリ（RSS）とシステム全体の使用量を繰り返し取得するためのハンドル
+pub struct ProcessMemory {
+    sys: System,
+    pid: Option<Pid>,
+}
+
+impl ProcessMemory {
+    pub fn new() -> Self {
+        Self {
+            sys: System::new(),
+            pid: sysinfo::get_current_pid().ok(),
+        }
+    }
+
+    /// このプロセスの RSS（バイト単位）。取得できない環境では None
+    pub fn rss(&mut self) -> Option<u64> {
+        let pid = self.pid?;
+        self.sys.refresh_processes_specifics(
+            ProcessesToUpdate::Some(&[pid]),
+            false,
+            ProcessRefreshKind::new().with_memory(),
+        );
+        self.sys.process(pid).map(|process| process.memory())
+    }
+
+    /// システム全体のメモリ使用量（バイト単位）
+    pub fn system_used(&mut self) -> u64 {
+        self.sys.refresh_memory();
+        self.sys.used_memory()
+    }
+}
+
+impl Default for ProcessMemory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// プロセスの RSS に対するハードリミット。
+///
+/// 篩側は次のセグメントグループを始める前に `usage` を確認し、上限に近づいたら
+/// グループを小さくし、超えたら書き出し待ちのグループが捌けるまで待ちます。
+pub struct MemoryCeiling {
+    limit: u64,
+    process: ProcessMemory,
+}
+
+impl MemoryCeiling {
+    /// `limit_mb` が 0 なら上限なし（None）
+    pub fn from_mb(limit_mb: u64) -> Option<Self> {
+        (limit_mb > 0).then(|| Self {
+            limit: limit_mb.saturating_mul(1024 * 1024),
+            process: ProcessMemory::new(),
+        })
+    }
+
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    /// 現在の RSS と、上限に対する割合（1.0 で上限）。RSS を取得できなければ None
+    pub fn usage(&mut self) -> Option<(u64, f64)> {
+        let rss = self.process.rss()?;
+        Some((rss, rss as f64 / self.limit as f64))
+    }
+}
+
 /// ホイールタイプに応じたメモリ圧縮率を取得
 pub fn get_wheel_compression_ratio(wheel_type: WheelType) -> f64 {
     match wheel_type {
         WheelType::Odd => 0.5,      // 1/2
         WheelType::Mod6 => 0.333,   // 1/3
         WheelType::Mod30 => 0.267,  // 8/30
+        WheelType::Mod210 => 0.229, // 48/210
     }
 }
 
@@ -29,16 +101,101 @@ pub fn estimate_segment_memory(segment_size: u64, wheel_type: WheelType) -> u64
     (bytes as f64 * 1.2) as u64
 }
 
+/// 区間 `[x, x + segment_size)` の素数の個数を `segment_size / (ln x − 1.1)` で見積もる（x は `low` と `segment_size` の大きい方）
+///
+/// x ≥ 60184 では π(x) < x / (ln x − 1.1) が成り立つので、多めの見積もりになります。
+fn estimate_segment_prime_count(segment_size: u64, low: u64) -> u64 {
+    let x = low.max(segment_size) as f64;
+    let density = 1.0 / (x.ln() - 1.1).max(1.0);
+    (segment_size as f64 * density).ceil() as u64
+}
+
+/// セグメント 1 つ分の篩の結果として保持する素数ベクタ（`Vec<u64>`）の大きさを推定（バイト単位）
+/// segment_size: セグメントに含まれる数値の範囲
+/// low: セグメントの開始値（小さいほど素数が密なので多めに見積もられる）
+pub fn estimate_prime_vector_memory(segment_size: u64, low: u64) -> u64 {
+    estimate_segment_prime_count(segment_size, low) * 8
+}
+
+/// √prime_max 以下の素数表（`Vec<u64>`）と、バケット篩のエントリの大きさを推定（バイト単位）
+pub fn estimate_small_prime_memory(prime_max: u64, segment_size: u64) -> u64 {
+    let root = integer_sqrt(prime_max) + 1;
+    let count = if root < 2 {
+        0
+    } else {
+        estimate_segment_prime_count(root, 0)
+    };
+    // バケット篩では、セグメント幅を超える素数ごとに (素数, 次の倍数) の 16 バイトを追加で持つ
+    let per_prime = if use_bucket_sieve(prime_max, segment_size) {
+        8 + 16
+    } else {
+        8
+    };
+    count * per_prime
+}
+
+/// メモリ見積もりに使う実行条件
+#[derive(Debug, Clone, Copy)]
+pub struct MemoryModel {
+    pub wheel_type: WheelType,
+    /// 並列スレッド数（1 グループのセグメント数）
+    pub num_threads: usize,
+    pub prime_min: u64,
+    pub prime_max: u64,
+    /// Writer が個数しか使わない（Last Prime Only）なら素数ベクタも書き出しバッファも持たない
+    pub counts_only: bool,
+    pub writer_buffer_size: usize,
+    pub pipeline_depth: usize,
+}
+
+impl MemoryModel {
+    pub fn from_config(cfg: &Config, num_threads: usize, counts_only: bool) -> Self {
+        Self {
+            wheel_type: cfg.wheel_type,
+            num_threads,
+            prime_min: cfg.prime_min,
+            prime_max: cfg.prime_max,
+            counts_only,
+            writer_buffer_size: cfg.writer_buffer_size,
+            pipeline_depth: cfg.pipeline_depth,
+        }
+    }
+
+    /// 素数ベクタを保持したまま同時に存在しうるセグメント結果の数。
+    /// 交互実行では 1 グループ分、パイプライン時は篩中・キュー内・書き出し中のグループ分
+    fn results_in_flight(&self) -> u64 {
+        if self.counts_only {
+            return 0;
+        }
+        let groups = if self.pipeline_depth == 0 {
+            1
+        } else {
+            self.pipeline_depth as u64 + 2
+        };
+        groups * self.threads()
+    }
+
+    fn threads(&self) -> u64 {
+        (self.num_threads as u64).max(1)
+    }
+
+    fn writer_buffer(&self) -> u64 {
+        if self.counts_only {
+            0
+        } else {
+            self.writer_buffer_size as u64
+        }
+    }
+}
+
 /// メモリ制限に基づいて最適なセグメントサイズを計算
 /// memory_usage_percent: システムメモリの何%まで使用するか (10.0 ~ 90.0)
-/// num_threads: 並列スレッド数
-/// wheel_type: 使用するホイールタイプ
+/// model: スレッド数・範囲・書き出しモードなどの実行条件
 /// returns: 推奨セグメントサイズ
-pub fn calculate_optimal_segment_size(
-    memory_usage_percent: f64,
-    num_threads: usize,
-    wheel_type: WheelType,
-) -> u64 {
+///
+/// 書き出しバッファと小さい素数表を先に差し引き、残りを「スレッドごとの篩のビット配列」と
+/// 「同時に保持するセグメント結果の素数ベクタ」で分け合うようにサイズを逆算します。
+pub fn calculate_optimal_segment_size(memory_usage_percent: f64, model: &MemoryModel) -> u64 {
     let total_memory = get_total_memory();
     
     // メモリ使用率を 10.0 ~ 90.0 の範囲にクランプ
@@ -50,37 +207,166 @@ pub fn calculate_optimal_segment_size(
     // 安全係数 (他のプロセスやシステムのために余裕を持たせる)
     let safety_factor = 2.0;
     
-    // スレッドあたりの許容メモリ
-    let per_thread_memory = allowed_memory / (num_threads as u64).max(1);
-    let safe_memory = (per_thread_memory as f64 / safety_factor) as u64;
-    
-    // セグメントサイズの逆算
-    // estimate_segment_memory(size, wheel) ≈ size * compression * 1.2 / 8 = safe_memory
-    // size = safe_memory * 8 / (compression * 1.2)
-    let compression = get_wheel_compression_ratio(wheel_type);
-    let segment_size = (safe_memory as f64 * 8.0 / (compression * 1.2)) as u64;
-    
     // 最小値と最大値を設定
     let min_size = 1_000_000u64;       // 最小 100万
     let max_size = 100_000_000u64;     // 最大 1億
     
+    // セグメントサイズに依存しない分を差し引く（素数表は最小サイズのとき＝バケット篩を使うときで見積もる）
+    let fixed_memory =
+        model.writer_buffer() + estimate_small_prime_memory(model.prime_max, min_size);
+    let safe_memory =
+        (allowed_memory.saturating_sub(fixed_memory) as f64 / safety_factor) as u64;
+    
+    // セグメントサイズの逆算
+    // 1 整数あたり: ビット配列 compression * 1.2 / 8 バイト × スレッド数
+    //             + 素数ベクタ 8 / (ln x − 1.1) バイト × 同時に保持する結果数
+    let compression = get_wheel_compression_ratio(model.wheel_type);
+    let bitmap_per_number = compression * 1.2 / 8.0 * model.threads() as f64;
+    let primes_per_number = estimate_segment_prime_count(1_000_000, model.prime_min) as f64
+        / 1_000_000.0
+        * 8.0
+        * model.results_in_flight() as f64;
+    let segment_size = (safe_memory as f64 / (bitmap_per_number + primes_per_number)) as u64;
+    
     segment_size.clamp(min_size, max_size)
 }
 
+/// 論理 CPU 1 つあたりの CPU キャッシュ容量（バイト単位）。検出できなかったレベルは None
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CpuCacheInfo {
+    /// L1 データキャッシュ
+    pub l1d: Option<u64>,
+    /// L2 キャッシュ
+    pub l2: Option<u64>,
+}
+
+impl CpuCacheInfo {
+    pub fn format(&self) -> String {
+        let kb = |size: Option<u64>| match size {
+            Some(bytes) => format!("{}KB", bytes / 1024),
+            None => "unknown".to_string(),
+        };
+        format!("L1d {}, L2 {} (論理 CPU あたり)", kb(self.l1d), kb(self.l2))
+    }
+}
+
+/// `/sys/devices/system/cpu/cpu0/cache` から CPU キャッシュ容量を検出する（Linux 以外では常に未検出）
+pub fn detect_cpu_cache() -> CpuCacheInfo {
+    read_cpu_cache_info(Path::new("/sys/devices/system/cpu/cpu0/cache"))
+}
+
+/// sysfs 形式のキャッシュ情報ディレクトリ（`index*/level`, `type`, `size`, `shared_cpu_list`）を読む。
+///
+/// 複数の論理 CPU（SMT の兄弟スレッドなど）で共有されるキャッシュは、共有数で割った容量を返します。
+pub fn read_cpu_cache_info(cache_dir: &Path) -> CpuCacheInfo {
+    let mut info = CpuCacheInfo::default();
+    let Ok(entries) = std::fs::read_dir(cache_dir) else {
+        return info;
+    };
+
+    for entry in entries.flatten() {
+        let dir = entry.path();
+        if !dir.file_name().is_some_and(|n| n.to_string_lossy().starts_with("index")) {
+            continue;
+        }
+        let read = |name: &str| std::fs::read_to_string(dir.join(name)).ok();
+        let (Some(level), Some(kind), Some(size)) = (read("level"), read("type"), read("size"))
+        else {
+            continue;
+        };
+        if kind.trim() == "Instruction" {
+            continue;
+        }
+        let Some(size) = parse_cache_size(&size) else {
+            continue;
+        };
+        let shared = read("shared_cpu_list")
+            .map(|list| count_cpu_list(&list))
+            .unwrap_or(1)
+            .max(1);
+        let per_cpu = size / shared;
+
+        match level.trim() {
+            "1" => info.l1d = Some(per_cpu),
+            "2" => info.l2 = Some(per_cpu),
+            _ => {}
+        }
+    }
+    info
+}
+
+/// `"48K"` / `"2048K"` / `"30M"` 形式のキャッシュサイズをバイト数に変換
+fn parse_cache_size(s: &str) -> Option<u64> {
+    let s = s.trim();
+    let (digits, unit) = match s.char_indices().find(|(_, c)| !c.is_ascii_digit()) {
+        Some((i, _)) => s.split_at(i),
+        None => (s, ""),
+    };
+    let value: u64 = digits.parse().ok()?;
+    let multiplier = match unit.trim() {
+        "" => 1,
+        "K" => 1024,
+        "M" => 1024 * 1024,
+        "G" => 1024 * 1024 * 1024,
+        _ => return None,
+    };
+    Some(value * multiplier)
+}
+
+/// `"0-1,4"` 形式の CPU リストに含まれる CPU 数を数える
+fn count_cpu_list(list: &str) -> u64 {
+    list.trim()
+        .split(',')
+        .filter(|part| !part.is_empty())
+        .map(|part| match part.split_once('-') {
+            Some((a, b)) => match (a.parse::<u64>(), b.parse::<u64>()) {
+                (Ok(a), Ok(b)) if b >= a => b - a + 1,
+                _ => 1,
+            },
+            None => 1,
+        })
+        .sum()
+}
+
+/// キャッシュに収めるセグメントの最小サイズ（数値の範囲）
+const MIN_CACHE_SEGMENT_SIZE: u64 = 1 << 16;
+
+/// セグメントのビット配列が CPU キャッシュに収まるセグメントサイズを求める。
+///
+/// - L2 があればその半分（残りは篩に使う素数表などのため）、なければ L1d 全体にビット配列を収めます。
+/// - ビット配列の大きさはホイールの圧縮率で決まるので、同じキャッシュでも `Mod210` ほど広い範囲を扱えます。
+/// - キャッシュ容量を検出できなかった場合は None を返します。
+pub fn calculate_cache_segment_size(cache: &CpuCacheInfo, wheel_type: WheelType) -> Option<u64> {
+    let target_bytes = match (cache.l2, cache.l1d) {
+        (Some(l2), _) => l2 / 2,
+        (None, Some(l1d)) => l1d,
+        (None, None) => return None,
+    };
+    let compression = get_wheel_compression_ratio(wheel_type);
+    let segment_size = (target_bytes as f64 * 8.0 / compression) as u64;
+    Some(segment_size.max(MIN_CACHE_SEGMENT_SIZE))
+}
+
 /// メモリ使用量の情報を表示用に取得
-pub fn get_memory_info(
-    segment_size: u64,
-    num_threads: usize,
-    wheel_type: WheelType,
-) -> MemoryInfo {
+pub fn get_memory_info(segment_size: u64, model: &MemoryModel) -> MemoryInfo {
     let total_memory = get_total_memory();
-    let segment_memory = estimate_segment_memory(segment_size, wheel_type);
-    let estimated_total = segment_memory * num_threads as u64;
+    let segment_memory = estimate_segment_memory(segment_size, model.wheel_type);
+    let sieve_memory = segment_memory * model.threads();
+    let prime_vector_memory = estimate_prime_vector_memory(segment_size, model.prime_min)
+        * model.results_in_flight();
+    let writer_buffer_memory = model.writer_buffer();
+    let small_prime_memory = estimate_small_prime_memory(model.prime_max, segment_size);
+    let estimated_total =
+        sieve_memory + prime_vector_memory + writer_buffer_memory + small_prime_memory;
     let usage_percent = (estimated_total as f64 / total_memory as f64) * 100.0;
     
     MemoryInfo {
         total_memory,
         segment_memory,
+        sieve_memory,
+        prime_vector_memory,
+        writer_buffer_memory,
+        small_prime_memory,
         estimated_total,
         usage_percent,
     }
@@ -89,20 +375,34 @@ pub fn get_memory_info(
 #[derive(Debug, Clone)]
 pub struct MemoryInfo {
     pub total_memory: u64,
+    /// セグメント 1 つ分の篩のビット配列
     pub segment_memory: u64,
+    /// 全スレッドの篩のビット配列
+    pub sieve_memory: u64,
+    /// 同時に保持するセグメント結果の素数ベクタ
+    pub prime_vector_memory: u64,
+    /// 出力ファイルの書き出しバッファ
+    pub writer_buffer_memory: u64,
+    /// √prime_max 以下の素数表とバケット篩
+    pub small_prime_memory: u64,
     pub estimated_total: u64,
     pub usage_percent: f64,
 }
 
 impl MemoryInfo {
     pub fn format(&self) -> String {
+        let mb = |bytes: u64| bytes as f64 / (1024.0 * 1024.0);
         format!(
-            "メモリ: システム {:.1}GB, セグメント {:.1}MB, 推定使用量 {:.1}MB ({:.1}%)",
+            "メモリ: システム {:.1}GB, セグメント {:.1}MB, 推定使用量 {:.1}MB ({:.1}%) \
+             [篩 {:.1}MB, 素数ベクタ {:.1}MB, 書き出しバッファ {:.1}MB, 素数表 {:.1}MB]",
             self.total_memory as f64 / (1024.0 * 1024.0 * 1024.0),
-            self.segment_memory as f64 / (1024.0 * 1024.0),
-            self.estimated_total as f64 / (1024.0 * 1024.0),
-            self.usage_percent
+            mb(self.segment_memory),
+            mb(self.estimated_total),
+            self.usage_percent,
+            mb(self.sieve_memory),
+            mb(self.prime_vector_memory),
+            mb(self.writer_buffer_memory),
+            mb(self.small_prime_memory),
         )
     }
 }
-
@@ -1,14 +1,54 @@
+use std::fmt;
 use std::fs::{create_dir_all, File, OpenOptions};
-use std::io::{self, BufWriter, Write};
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
 
 use chrono::Local;
+use serde::{Deserialize, Serialize};
 
-use crate::config::{Config, OutputFormat};
+use crate::config::{Config, ConfigSources, OutputFormat};
+use crate::delta_varint::{encode_gap, write_varint, DeltaVarintHeader, DELTA_VARINT_BASE_OFFSET};
+use crate::prime_index::{supports_format, PrimeIndexBuilder, PRIME_INDEX_FILE_SUFFIX};
+use crate::wheel_bitmap::{WheelBitmapEncoder, WheelBitmapHeader, WHEEL_BITMAP_RANGE_OFFSET};
+
+/// マニフェストのファイル名（タイムスタンプ接頭辞を除いた部分）
+pub const MANIFEST_FILE_SUFFIX: &str = "primes.manifest.json";
+
+/// 書き込み中の出力ファイル名に付ける接尾辞（`FilePrimeWriter::commit` で外す）
+pub const PARTIAL_SUFFIX: &str = ".partial";
+
+/// 生成の終わり方
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum RunStatus {
+    /// 最後まで生成した
+    #[default]
+    Complete,
+    /// 停止された
+    Stopped,
+    /// エラーで終了した
+    Failed,
+}
+
+impl fmt::Display for RunStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunStatus::Complete => write!(f, "complete"),
+            RunStatus::Stopped => write!(f, "stopped"),
+            RunStatus::Failed => write!(f, "failed"),
+        }
+    }
+}
 
 /// 素数生成のメタデータ
-#[derive(Debug, Clone)]
+///
+/// - `write_to_file` で人間向けの `primes.meta.txt` を、
+///   `write_manifest` で機械可読な `primes.manifest.json` を出力します。
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct OutputMetadata {
+    /// 生成の終わり方（complete / stopped / failed）
+    #[serde(default)]
+    pub status: RunStatus,
     pub range: (u64, u64),
     pub count: u64,
     pub pi_x_verified: bool,
@@ -21,6 +61,30 @@ pub struct OutputMetadata {
     pub primecount_version: Option<String>,
     /// primecount アルゴリズムモード（`pi(x)` がどのモードで呼ばれているかの説明）
     pub primecount_mode: Option<String>,
+    /// 各設定値の出どころ（default / file / env / cli）。CLI 実行時のみ記録される。
+    #[serde(default)]
+    pub config_sources: Option<ConfigSources>,
+    /// 出力ファイルごとの件数・最初/最後の素数・サイズ（`FilePrimeWriter::file_states`）
+    #[serde(default)]
+    pub files: Vec<OutputFileState>,
+    /// 素数を生成したエンジン名（`SieveEngine::name`）
+    #[serde(default)]
+    pub engine: Option<String>,
+}
+
+/// マニフェスト形式のバージョン
+pub const MANIFEST_VERSION: u32 = 1;
+
+/// `primes.manifest.json` の内容。
+///
+/// - `primes.meta.txt` と同じ情報に、ファイルごとの詳細と `Config` 全体を加えたもの。
+/// - u64 の上限付近の値を扱えるよう、TOML（整数が i64）ではなく JSON で保存します。
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RunManifest {
+    pub manifest_version: u32,
+    #[serde(flatten)]
+    pub metadata: OutputMetadata,
+    pub config: Config,
 }
 
 impl OutputMetadata {
@@ -35,6 +99,7 @@ impl OutputMetadata {
         primecount_mode: Option<String>,
     ) -> Self {
         Self {
+            status: RunStatus::Complete,
             range,
             count,
             pi_x_verified,
@@ -44,7 +109,77 @@ impl OutputMetadata {
             output_files,
             primecount_version,
             primecount_mode,
+            config_sources: None,
+            files: Vec::new(),
+            engine: None,
+        }
+    }
+
+    /// 機械可読なマニフェスト（`primes.manifest.json`）を書き出す。
+    ///
+    /// - ファイル名の規則は `write_to_file` と同じ（`timestamp_prefix` を先頭に付与）。
+    pub fn write_manifest(
+        &self,
+        output_dir: &str,
+        cfg: &Config,
+        timestamp_prefix: Option<&str>,
+    ) -> io::Result<PathBuf> {
+        let base_dir = PathBuf::from(output_dir);
+        if !output_dir.is_empty() {
+            create_dir_all(&base_dir)?;
+        }
+
+        let prefix = timestamp_prefix.unwrap_or("");
+        let manifest_path = base_dir.join(format!("{prefix}{MANIFEST_FILE_SUFFIX}"));
+        let manifest = RunManifest {
+            manifest_version: MANIFEST_VERSION,
+            metadata: self.clone(),
+            config: cfg.clone(),
+        };
+
+        let mut writer = BufWriter::new(File::create(&manifest_path)?);
+        serde_json::to_writer_pretty(&mut writer, &manifest)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        writeln!(writer)?;
+        writer.flush()?;
+
+        Ok(manifest_path)
+    }
+
+    /// `write_manifest` で書き出したマニフェストを読み込む。
+    pub fn read_manifest(path: impl AsRef<Path>) -> io::Result<RunManifest> {
+        let file = File::open(path.as_ref())?;
+        let manifest: RunManifest = serde_json::from_reader(io::BufReader::new(file))
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if manifest.manifest_version > MANIFEST_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "unsupported manifest version {} (expected <= {MANIFEST_VERSION})",
+                    manifest.manifest_version
+                ),
+            ));
         }
+        Ok(manifest)
+    }
+
+    /// ディレクトリ内のマニフェスト（`*primes.manifest.json`）をファイル名順に列挙する。
+    ///
+    /// - タイムスタンプ接頭辞付きの場合、ファイル名順がそのまま実行順になります。
+    pub fn find_manifests(dir: impl AsRef<Path>) -> io::Result<Vec<PathBuf>> {
+        let mut manifests = Vec::new();
+        for entry in std::fs::read_dir(dir.as_ref())? {
+            let path = entry?.path();
+            let is_manifest = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.ends_with(MANIFEST_FILE_SUFFIX));
+            if is_manifest && path.is_file() {
+                manifests.push(path);
+            }
+        }
+        manifests.sort();
+        Ok(manifests)
     }
 
     /// メタデータをTXTファイルに書き出す
@@ -69,6 +204,7 @@ impl OutputMetadata {
         let mut writer = BufWriter::new(file);
 
         writeln!(writer, "=== Prime Generation Report ===")?;
+        writeln!(writer, "Status: {}", self.status)?;
         writeln!(writer, "Range: {} - {}", self.range.0, self.range.1)?;
         writeln!(writer, "Count: {}", self.count)?;
         writeln!(
@@ -79,6 +215,9 @@ impl OutputMetadata {
         writeln!(writer, "Execution Time: {} ms", self.execution_time_ms)?;
         writeln!(writer, "Generated: {}", self.generated_at)?;
         writeln!(writer, "Tool Version: {}", self.tool_version)?;
+        if let Some(ref engine) = self.engine {
+            writeln!(writer, "Engine: {engine}")?;
+        }
 
         // 出力ファイル一覧
         if !self.output_files.is_empty() {
@@ -112,24 +251,166 @@ impl OutputMetadata {
         writeln!(writer, "output_format = {:?}", cfg.output_format)?;
         writeln!(writer, "output_dir = {}", cfg.output_dir)?;
         writeln!(writer, "split_count = {}", cfg.split_count)?;
+        writeln!(writer, "split_width = {}", cfg.split_width)?;
+        writeln!(writer, "index_stride = {}", cfg.index_stride)?;
         writeln!(writer, "last_prime_only = {}", cfg.last_prime_only)?;
         writeln!(writer, "wheel_type = {:?}", cfg.wheel_type)?;
         writeln!(writer, "memory_usage_percent = {}", cfg.memory_usage_percent)?;
+        writeln!(writer, "memory_ceiling_mb = {}", cfg.memory_ceiling_mb)?;
+        writeln!(writer, "resume = {}", cfg.resume)?;
+        writeln!(writer, "delete_partials_on_stop = {}", cfg.delete_partials_on_stop)?;
+        writeln!(writer, "pipeline_depth = {}", cfg.pipeline_depth)?;
+        writeln!(writer, "threads = {}", cfg.threads)?;
+        writeln!(writer, "auto_segment_size = {}", cfg.auto_segment_size)?;
+        writeln!(writer, "engine = {:?}", cfg.engine)?;
+
+        // 設定値の出どころ
+        if let Some(ref sources) = self.config_sources {
+            writeln!(writer)?;
+            writeln!(writer, "--- Settings Sources ---")?;
+            for (key, source) in sources {
+                writeln!(writer, "{key} = {source}")?;
+            }
+        }
         writer.flush()?;
 
         Ok(meta_path)
     }
 }
 
+/// 出力ファイル 1 つ分の状態（チェックポイントとマニフェストで共用）。
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct OutputFileState {
+    pub path: String,
+    /// ディスク上で確定しているバイト数（チェックポイントではこれより後ろを再開時に切り捨てる）
+    pub bytes: u64,
+    /// このファイルに書き込まれた素数の個数
+    pub count: u64,
+    /// このファイルの最初の素数
+    #[serde(default)]
+    pub first_prime: Option<u64>,
+    /// このファイルの最後の素数
+    #[serde(default)]
+    pub last_prime: Option<u64>,
+    /// 値の範囲で分割したときに、このファイルが受け持つ範囲 `[start, end)`
+    #[serde(default)]
+    pub value_range: Option<(u64, u64)>,
+}
+
+/// `PrimeWriter::sync_state` が返す、Writer の確定済み状態。
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct WriterState {
+    /// これまでに書き込まれた素数の総数
+    pub total_count: u64,
+    /// 最後に書き込まれた素数
+    pub last_prime: Option<u64>,
+    /// 出力ファイルごとの状態（ファイルを持たない Writer では空）
+    pub files: Vec<OutputFileState>,
+}
+
 pub trait PrimeWriter {
     fn write_prime(&mut self, p: u64) -> io::Result<()>;
     fn finish(&mut self) -> io::Result<()>;
+
+    /// 書き込み済みのデータをディスクへ確定させ、途中再開に必要な状態を返す。
+    ///
+    /// - チェックポイントに対応しない Writer は `None` を返す（デフォルト）。
+    fn sync_state(&mut self) -> io::Result<Option<WriterState>> {
+        Ok(None)
+    }
+
+    /// `high` 以下の素数をすべて書き込み終えた（そこまで篩い終えた）ことを知らせる。
+    ///
+    /// - エンジンはセグメントを書き終えるたびに呼びます。停止した実行でも、どこまで篩ったかが分かります。
+    /// - デフォルトは何もしません（WheelBitmap のヘッダの終了値に使います）。
+    fn mark_sieved(&mut self, high: u64) -> io::Result<()> {
+        let _ = high;
+        Ok(())
+    }
+
+    /// 個々の素数ではなく「区間内の個数と最大の素数」だけを受け取る Writer なら `true`。
+    ///
+    /// - `true` の場合、CPU エンジンは素数列を作らずに篩のビット配列を数え、`write_count` を呼びます。
+    fn counts_only(&self) -> bool {
+        false
+    }
+
+    /// 区間内の素数の個数 `count` と、その中で最大の素数 `last_prime` を書き込む。
+    ///
+    /// - `counts_only` が `true` の Writer だけが呼ばれます（デフォルトはエラー）。
+    fn write_count(&mut self, count: u64, last_prime: Option<u64>) -> io::Result<()> {
+        let _ = (count, last_prime);
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "this writer needs individual primes",
+        ))
+    }
+
+    /// 篩のビット配列を mod 30 のバイト列（`wheel_bitmap` の本体と同じ配置）で受け取る Writer なら `true`。
+    ///
+    /// - `true` の場合、CPU エンジンは素数列を作らずにセグメントのビット配列を変換し、`write_wheel_bits` を呼びます。
+    /// - 他のエンジンやホイールで除外される小さい素数は、これまでどおり `write_prime` で届きます。
+    fn wants_wheel_bits(&self) -> bool {
+        false
+    }
+
+    /// ブロック `first_block`（`n / 30`）から始まる mod 30 のバイト列 `bits` を書き込む。
+    ///
+    /// - バイトの k 番目はブロック `first_block + k`、ビット j は余り `[1, 7, 11, 13, 17, 19, 23, 29][j]` を表します。
+    /// - セグメントの境界は 30 の倍数に揃っていないため、先頭のバイトは直前の書き込みの末尾と同じブロックになることがあります（ビットの OR として扱う）。
+    /// - 2, 3, 5 は `bits` に現れませんが、`count`（区間内の素数の個数）と `first_prime` / `last_prime`（最小・最大の素数）には含みます。
+    /// - `wants_wheel_bits` が `true` の Writer だけが呼ばれます（デフォルトはエラー）。
+    fn write_wheel_bits(
+        &mut self,
+        first_block: u64,
+        bits: &[u8],
+        count: u64,
+        first_prime: Option<u64>,
+        last_prime: Option<u64>,
+    ) -> io::Result<()> {
+        let _ = (first_block, bits, count, first_prime, last_prime);
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "this writer needs individual primes",
+        ))
+    }
+}
+
+/// 出力ファイルの分割方法
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileSplit {
+    /// 1 ファイルにまとめる
+    None,
+    /// 素数 `n` 個ごとに次のファイルへ（`primes_1.bin`, `primes_2.bin`, ...）
+    ByCount(u64),
+    /// 値の範囲 `[k·W, (k+1)·W)` ごとに 1 ファイル（`primes_0-1000000.bin`, ...）
+    ByValue(u64),
+}
+
+impl FileSplit {
+    /// `n` 個ごとの分割（0 なら分割しない）
+    pub fn by_count(n: u64) -> Self {
+        if n > 0 {
+            FileSplit::ByCount(n)
+        } else {
+            FileSplit::None
+        }
+    }
+
+    /// 設定から分割方法を決める（`split_width` が `split_count` より優先）
+    pub fn from_config(cfg: &Config) -> Self {
+        if cfg.split_width > 0 {
+            FileSplit::ByValue(cfg.split_width)
+        } else {
+            Self::by_count(cfg.split_count)
+        }
+    }
 }
 
 pub struct FilePrimeWriter {
     format: OutputFormat,
     base_dir: PathBuf,
-    split_count: u64,
+    split: FileSplit,
     buf_size: usize,
     timestamp_prefix: Option<String>,
 
@@ -139,8 +420,30 @@ pub struct FilePrimeWriter {
     first_item_in_json: bool,
     /// これまでに書き込まれた素数の総数（ファイル分割をまたいだ合計）
     total_count: u64,
-    /// 実際に書き出したファイルパス一覧
+    /// 実際に書き出したファイルパス一覧（`commit` までは `*.partial`）
     output_files: Vec<PathBuf>,
+    /// 書き込みを終えた（クローズ済みの）ファイルの状態
+    closed_files: Vec<OutputFileState>,
+    last_prime: Option<u64>,
+    /// 現在のファイルの最初の素数
+    current_first: Option<u64>,
+    /// 値の範囲で分割しているときの、現在のファイルの範囲 `[start, end)`
+    current_range: Option<(u64, u64)>,
+    /// 生成範囲 `[min, max]`（WheelBitmap のヘッダに使用。`set_value_range` で設定）
+    value_range: Option<(u64, u64)>,
+    /// WheelBitmap 形式の書きかけバイト
+    bitmap: WheelBitmapEncoder,
+    /// `mark_sieved` で知らされた、篩い終えた範囲の終端
+    sieved_through: Option<u64>,
+    /// 疎なインデックスの記録点（`enable_index` で有効化）
+    index: Option<PrimeIndexBuilder>,
+    /// `commit` で書き出したインデックスファイル
+    index_path: Option<PathBuf>,
+}
+
+/// `*.partial` のパスから接尾辞を外したパス（書き込み中のファイルでなければ `None`）
+fn committed_path(path: &Path) -> Option<PathBuf> {
+    path.to_str()?.strip_suffix(PARTIAL_SUFFIX).map(PathBuf::from)
 }
 
 impl FilePrimeWriter {
@@ -150,16 +453,43 @@ impl FilePrimeWriter {
         split_count: u64,
         buf_size: usize,
         timestamp_prefix: Option<String>,
+    ) -> io::Result<Self> {
+        Self::with_split(
+            output_dir,
+            format,
+            FileSplit::by_count(split_count),
+            buf_size,
+            timestamp_prefix,
+        )
+    }
+
+    /// 分割方法を指定して Writer を作る。
+    ///
+    /// - `FileSplit::ByValue` では、最初の素数を受け取るまでファイルを作りません。
+    ///   素数を 1 つも含まない範囲のファイルも作りません。
+    pub fn with_split(
+        output_dir: &str,
+        format: OutputFormat,
+        split: FileSplit,
+        buf_size: usize,
+        timestamp_prefix: Option<String>,
     ) -> io::Result<Self> {
         let base_dir = PathBuf::from(output_dir);
         if !output_dir.is_empty() {
             create_dir_all(&base_dir)?;
         }
 
+        // WheelBitmap は値の範囲でファイルを表すため、ファイルの分割は行わない
+        let split = if format == OutputFormat::WheelBitmap {
+            FileSplit::None
+        } else {
+            split
+        };
+
         let mut writer = Self {
             format,
             base_dir,
-            split_count,
+            split,
             buf_size,
             timestamp_prefix,
             current_writer: None,
@@ -168,35 +498,282 @@ impl FilePrimeWriter {
             first_item_in_json: true,
             total_count: 0,
             output_files: Vec::new(),
+            closed_files: Vec::new(),
+            last_prime: None,
+            current_first: None,
+            current_range: None,
+            value_range: None,
+            bitmap: WheelBitmapEncoder::new(),
+            sieved_through: None,
+            index: None,
+            index_path: None,
         };
 
-        writer.open_next_file()?;
+        if !matches!(writer.split, FileSplit::ByValue(_)) {
+            writer.open_next_file()?;
+        }
         Ok(writer)
     }
 
-    fn open_next_file(&mut self) -> io::Result<()> {
-        if let Some(mut w) = self.current_writer.take() {
-            if let OutputFormat::JSON = self.format {
-                write!(w, "]")?;
+    /// チェックポイントの `WriterState` から出力を再開する。
+    ///
+    /// - クローズ済みのファイルはそのまま残し、最後のファイルを追記モードで開き直す。
+    /// - 各ファイルはチェックポイント時点のバイト数に切り詰める（停止・クラッシュ時の書きかけ部分を破棄）。
+    /// - チェックポイント後に分割で作られた後続ファイルは削除する。
+    pub fn resume(
+        output_dir: &str,
+        format: OutputFormat,
+        split_count: u64,
+        buf_size: usize,
+        timestamp_prefix: Option<String>,
+        state: &WriterState,
+    ) -> io::Result<Self> {
+        Self::resume_with_split(
+            output_dir,
+            format,
+            FileSplit::by_count(split_count),
+            buf_size,
+            timestamp_prefix,
+            state,
+        )
+    }
+
+    /// 分割方法を指定して `resume` する。
+    ///
+    /// - `FileSplit::ByValue` では、まだファイルを作っていないチェックポイントからも再開できます。
+    pub fn resume_with_split(
+        output_dir: &str,
+        format: OutputFormat,
+        split: FileSplit,
+        buf_size: usize,
+        timestamp_prefix: Option<String>,
+        state: &WriterState,
+    ) -> io::Result<Self> {
+        let current = state.files.last();
+        if current.is_none() && !matches!(split, FileSplit::ByValue(_)) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "checkpoint does not contain any output file",
+            ));
+        }
+        let closed = &state.files[..state.files.len().saturating_sub(1)];
+
+        for file_state in &state.files {
+            let file = OpenOptions::new().write(true).open(&file_state.path)?;
+            let len = file.metadata()?.len();
+            if len < file_state.bytes {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "output file {} is shorter than the checkpoint ({len} < {} bytes)",
+                        file_state.path, file_state.bytes
+                    ),
+                ));
             }
-            w.flush()?;
+            file.set_len(file_state.bytes)?;
         }
 
-        let (base_name, ext) = match self.format {
-            OutputFormat::Text => ("primes", "txt"),
-            OutputFormat::CSV => ("primes", "csv"),
-            OutputFormat::JSON => ("primes", "json"),
-            OutputFormat::Binary => ("primes", "bin"),
+        let current_writer = match current {
+            Some(current) => {
+                let mut file = OpenOptions::new().write(true).open(&current.path)?;
+                file.seek(SeekFrom::End(0))?;
+                Some(BufWriter::with_capacity(buf_size, file))
+            }
+            None => None,
+        };
+
+        let writer = Self {
+            format,
+            base_dir: PathBuf::from(output_dir),
+            split,
+            buf_size,
+            timestamp_prefix,
+            current_writer,
+            current_count: current.map_or(0, |c| c.count),
+            file_index: state.files.len() as u64 + 1,
+            first_item_in_json: current.is_none_or(|c| c.count == 0),
+            total_count: state.total_count,
+            output_files: state.files.iter().map(|f| PathBuf::from(&f.path)).collect(),
+            closed_files: closed.to_vec(),
+            last_prime: state.last_prime,
+            current_first: current.and_then(|c| c.first_prime),
+            current_range: current.and_then(|c| c.value_range),
+            value_range: None,
+            bitmap: WheelBitmapEncoder::new(),
+            sieved_through: None,
+            index: None,
+            index_path: None,
         };
 
+        // チェックポイント後に作られた分割ファイルを削除
+        match writer.split {
+            FileSplit::ByCount(_) => {
+                let mut index = writer.file_index;
+                loop {
+                    let stale = writer
+                        .base_dir
+                        .join(format!("{}{PARTIAL_SUFFIX}", writer.file_name(index)));
+                    if !stale.exists() {
+                        break;
+                    }
+                    std::fs::remove_file(&stale)?;
+                    index += 1;
+                }
+            }
+            FileSplit::ByValue(_) => {
+                let resumed_start = writer.current_range.map(|(start, _)| start);
+                for (start, stale) in writer.range_files()? {
+                    if resumed_start.is_none_or(|resumed| start > resumed) {
+                        std::fs::remove_file(&stale)?;
+                    }
+                }
+            }
+            FileSplit::None => {}
+        }
+
+        Ok(writer)
+    }
+
+    /// 出力形式ごとの拡張子
+    fn extension(&self) -> &'static str {
+        match self.format {
+            OutputFormat::Text => "txt",
+            OutputFormat::CSV => "csv",
+            OutputFormat::JSON => "json",
+            OutputFormat::Binary => "bin",
+            OutputFormat::DeltaVarint => "dvar",
+            OutputFormat::WheelBitmap => "w30",
+        }
+    }
+
+    /// `file_index` 番目の出力ファイル名（プレフィックス・分割番号込み）
+    fn file_name(&self, file_index: u64) -> String {
+        let prefix = self.timestamp_prefix.as_deref().unwrap_or("");
+        let ext = self.extension();
+        if let FileSplit::ByCount(_) = self.split {
+            format!("{prefix}primes_{file_index}.{ext}")
+        } else {
+            format!("{prefix}primes.{ext}")
+        }
+    }
+
+    /// 範囲 `[start, end)` を受け持つ出力ファイル名（`FileSplit::ByValue` 用）
+    fn range_file_name(&self, (start, end): (u64, u64)) -> String {
         let prefix = self.timestamp_prefix.as_deref().unwrap_or("");
-        let file_name = if self.split_count > 0 {
-            format!("{prefix}{base_name}_{}.{ext}", self.file_index)
+        format!("{prefix}primes_{start}-{end}.{}", self.extension())
+    }
+
+    /// 出力フォルダにある、このプレフィックス・形式の書き込み中の範囲分割ファイルを
+    /// `(範囲の開始値, パス)` で列挙する。
+    fn range_files(&self) -> io::Result<Vec<(u64, PathBuf)>> {
+        let dir = if self.base_dir.as_os_str().is_empty() {
+            Path::new(".")
         } else {
-            format!("{prefix}{base_name}.{ext}")
+            self.base_dir.as_path()
         };
+        let head = format!("{}primes_", self.timestamp_prefix.as_deref().unwrap_or(""));
+        let tail = format!(".{}{PARTIAL_SUFFIX}", self.extension());
 
-        let full_path = self.base_dir.join(Path::new(&file_name));
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+                continue;
+            };
+            let range = name
+                .strip_prefix(head.as_str())
+                .and_then(|n| n.strip_suffix(tail.as_str()))
+                .and_then(|range| range.split_once('-'));
+            if let Some((start, end)) = range {
+                if let (Ok(start), Ok(_)) = (start.parse::<u64>(), end.parse::<u64>()) {
+                    files.push((start, self.base_dir.join(&name)));
+                }
+            }
+        }
+        Ok(files)
+    }
+
+    /// 現在のファイルを閉じ、その状態を `closed_files` に記録する。
+    fn close_current_file(&mut self) -> io::Result<()> {
+        if let Some(mut w) = self.current_writer.take() {
+            match self.format {
+                OutputFormat::JSON => write!(w, "]")?,
+                OutputFormat::DeltaVarint => {
+                    // 閉じるときに基準値と個数をヘッダへ書き戻す
+                    w.seek(SeekFrom::Start(DELTA_VARINT_BASE_OFFSET))?;
+                    w.write_all(&self.current_first.unwrap_or(0).to_le_bytes())?;
+                    w.write_all(&self.current_count.to_le_bytes())?;
+                }
+                OutputFormat::WheelBitmap => {
+                    // 残りのバイトを篩い終えた位置まで埋め、範囲をヘッダへ書き戻す。
+                    // 停止した実行では篩っていない範囲を含めない（0 埋めすると素数が無いことになるため）
+                    let start_hint = self.value_range.map(|(min, _)| min);
+                    let sieved = match (self.sieved_through, self.value_range) {
+                        (Some(high), Some((_, max))) => high.min(max),
+                        (Some(high), None) => high,
+                        (None, _) => 0,
+                    };
+                    let end = sieved.max(self.last_prime.unwrap_or(0));
+                    let (start, end) = self.bitmap.finish(&mut w, start_hint, end)?;
+                    w.seek(SeekFrom::Start(WHEEL_BITMAP_RANGE_OFFSET))?;
+                    w.write_all(&start.to_le_bytes())?;
+                    w.write_all(&end.to_le_bytes())?;
+                }
+                _ => {}
+            }
+            w.flush()?;
+            let bytes = w.get_ref().metadata()?.len();
+            if let Some(state) = self.current_file_state(bytes) {
+                self.closed_files.push(state);
+            }
+        }
+        Ok(())
+    }
+
+    /// 現在のファイルの状態（`bytes` は呼び出し側で確定させたサイズ）
+    fn current_file_state(&self, bytes: u64) -> Option<OutputFileState> {
+        let path = self.output_files.last()?;
+        Some(OutputFileState {
+            path: path.to_string_lossy().to_string(),
+            bytes,
+            count: self.current_count,
+            first_prime: self.current_first,
+            last_prime: if self.current_count > 0 {
+                self.last_prime
+            } else {
+                None
+            },
+            value_range: self.current_range,
+        })
+    }
+
+    fn open_next_file(&mut self) -> io::Result<()> {
+        let file_name = self.file_name(self.file_index);
+        self.open_file(&file_name)
+    }
+
+    /// 素数 `p` を含む幅 `width` の範囲のファイルへ切り替える（`FileSplit::ByValue` 用）。
+    ///
+    /// - 最後の範囲の終端は `u64::MAX` で打ち切る（`u64::MAX` 自体は素数ではない）。
+    fn open_range_file(&mut self, width: u64, p: u64) -> io::Result<()> {
+        let start = p / width * width;
+        let range = (start, start.saturating_add(width));
+        let file_name = self.range_file_name(range);
+        self.open_file(&file_name)?;
+        self.current_range = Some(range);
+        Ok(())
+    }
+
+    /// 現在のファイルを閉じ、`file_name` を新しく作って書き込み先にする。
+    ///
+    /// - `commit` までは `file_name` に `PARTIAL_SUFFIX` を付けた名前で書き込む。
+    fn open_file(&mut self, file_name: &str) -> io::Result<()> {
+        self.close_current_file()?;
+
+        let full_path = self.base_dir.join(format!("{file_name}{PARTIAL_SUFFIX}"));
+        if let Some(index) = self.index.as_mut() {
+            index.start_file(file_name.to_string());
+        }
         let file = OpenOptions::new()
             .create(true)
             .truncate(true)
@@ -204,13 +781,26 @@ impl FilePrimeWriter {
             .open(&full_path)?;
 
         let mut writer = BufWriter::with_capacity(self.buf_size, file);
-        if let OutputFormat::JSON = self.format {
-            write!(writer, "[")?;
-            self.first_item_in_json = true;
+        match self.format {
+            OutputFormat::JSON => {
+                write!(writer, "[")?;
+                self.first_item_in_json = true;
+            }
+            OutputFormat::DeltaVarint => {
+                // 基準値と個数は close_current_file で確定させる
+                DeltaVarintHeader::new(0, 0).write_to(&mut writer)?;
+            }
+            OutputFormat::WheelBitmap => {
+                // 範囲は close_current_file で確定させる
+                WheelBitmapHeader::new(1, 0).write_to(&mut writer)?;
+                self.bitmap = WheelBitmapEncoder::new();
+            }
+            _ => {}
         }
 
         self.current_writer = Some(writer);
         self.current_count = 0;
+        self.current_first = None;
         self.file_index += 1;
         self.output_files.push(full_path);
         Ok(())
@@ -218,20 +808,139 @@ impl FilePrimeWriter {
 
     /// ファイルに書き込まれた素数の総数を返します。
     ///
-    /// - `split_count` によるファイル分割をまたいだ全体の件数です。
+    /// - ファイル分割をまたいだ全体の件数です。
     /// - 計算中も「これまでに書き出された素数の数」として参照できます。
     pub fn total_primes_written(&self) -> u64 {
         self.total_count
     }
 
-    /// 出力されたファイルパス一覧を取得する。
+    /// 出力されたファイルパス一覧を取得する（`commit` 前は書き込み中の `*.partial` のパス）。
     pub fn output_file_paths(&self) -> &[PathBuf] {
         &self.output_files
     }
+
+    /// 生成範囲 `[min, max]` を設定する（WheelBitmap のヘッダに記録される範囲）。
+    ///
+    /// - ヘッダの終了値は `max` と、エンジンが `mark_sieved` で知らせた篩い終えた位置の小さい方です。
+    /// - 未設定の場合は、最初の素数から篩い終えた位置（知らされていなければ最後の素数）までを範囲とします。
+    pub fn set_value_range(&mut self, min: u64, max: u64) {
+        self.value_range = Some((min, max));
+    }
+
+    /// K 個ごとの素数の位置を記録した疎なインデックス（`primes.index`）を `commit` で書き出す。
+    ///
+    /// - Binary / DeltaVarint 形式でのみ有効です（それ以外の形式では何もせず `false` を返します）。
+    /// - 途中再開した Writer では、書き出し済みのファイルを読み直して記録点を作り直します。
+    /// - 読み出しには `prime_index::PrimeIndex` を使います。
+    pub fn enable_index(&mut self, stride: u64) -> io::Result<bool> {
+        if stride == 0 || !supports_format(self.format) {
+            return Ok(false);
+        }
+
+        // インデックスには commit 後のファイル名を記録する
+        let committed_name = |path: &Path| {
+            let path = committed_path(path).unwrap_or_else(|| path.to_path_buf());
+            path.file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default()
+        };
+
+        let mut index = PrimeIndexBuilder::new(self.format, stride);
+        let mut first_index = 0;
+        for file_state in &self.closed_files {
+            let path = Path::new(&file_state.path);
+            index.scan_file(
+                path,
+                committed_name(path),
+                first_index,
+                file_state.first_prime,
+                file_state.count,
+            )?;
+            first_index += file_state.count;
+        }
+        if let Some(w) = self.current_writer.as_mut() {
+            w.flush()?;
+            if let Some(path) = self.output_files.last() {
+                index.scan_file(
+                    path,
+                    committed_name(path),
+                    first_index,
+                    self.current_first,
+                    self.current_count,
+                )?;
+            }
+        }
+        self.index = Some(index);
+        Ok(true)
+    }
+
+    /// `commit` で書き出したインデックスファイルのパス
+    pub fn index_path(&self) -> Option<&Path> {
+        self.index_path.as_deref()
+    }
+
+    /// 書き込み中の `*.partial` ファイルを本来のファイル名へ変更し、出力を確定させる。
+    ///
+    /// - `finish` が成功した後、最後まで生成できた場合にだけ呼びます。
+    ///   停止・エラーで終わった実行のファイルは `*.partial` のまま残り、途中再開ではその続きに書き込みます。
+    /// - `enable_index` で有効にしたインデックスもここで書き出します。
+    pub fn commit(&mut self) -> io::Result<()> {
+        self.close_current_file()?;
+
+        for path in &mut self.output_files {
+            if let Some(committed) = committed_path(path) {
+                std::fs::rename(&*path, &committed)?;
+                *path = committed;
+            }
+        }
+        for file_state in &mut self.closed_files {
+            if let Some(committed) = committed_path(Path::new(&file_state.path)) {
+                file_state.path = committed.to_string_lossy().to_string();
+            }
+        }
+
+        if let Some(index) = &self.index {
+            let prefix = self.timestamp_prefix.as_deref().unwrap_or("");
+            let path = self.base_dir.join(format!("{prefix}{PRIME_INDEX_FILE_SUFFIX}"));
+            index.write_to_file(&path, self.total_count)?;
+            self.index_path = Some(path);
+        }
+        Ok(())
+    }
+
+    /// 書き込み中の `*.partial` ファイルをすべて削除する（停止した実行の出力を残さない場合）。
+    pub fn discard(&mut self) -> io::Result<()> {
+        self.current_writer = None;
+        for path in self.output_files.drain(..) {
+            if committed_path(&path).is_none() {
+                continue;
+            }
+            match std::fs::remove_file(&path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+        }
+        self.closed_files.clear();
+        Ok(())
+    }
+
+    /// 閉じ終えたファイルごとの状態（件数・最初/最後の素数・サイズ）。
+    ///
+    /// - `finish` 後に呼ぶと、全出力ファイルの最終状態になります。
+    pub fn file_states(&self) -> &[OutputFileState] {
+        &self.closed_files
+    }
 }
 
 impl PrimeWriter for FilePrimeWriter {
     fn write_prime(&mut self, p: u64) -> io::Result<()> {
+        if let FileSplit::ByValue(width) = self.split {
+            // 素数は昇順に届くので、範囲の終端に達したら次のファイルへ切り替える
+            if self.current_range.is_none_or(|(_, end)| p >= end) {
+                self.open_range_file(width, p)?;
+            }
+        }
+
         let writer = self
             .current_writer
             .as_mut()
@@ -255,11 +964,34 @@ impl PrimeWriter for FilePrimeWriter {
             OutputFormat::Binary => {
                 writer.write_all(&p.to_le_bytes())?;
             }
+            OutputFormat::DeltaVarint => {
+                // ファイル内の最初の素数はヘッダの基準値に入る
+                if self.current_count > 0 {
+                    let prev = self.last_prime.expect("last_prime is set after the first write");
+                    write_varint(writer, encode_gap(prev, p))?;
+                }
+            }
+            OutputFormat::WheelBitmap => {
+                self.bitmap
+                    .push(writer, p, self.value_range.map(|(min, _)| min))?;
+            }
+        }
+
+        if let Some(index) = self.index.as_mut() {
+            if index.wants(self.total_count, self.current_count == 0) {
+                // flush せずに、ファイル上の位置とバッファ内のバイト数から現在位置を求める
+                let offset = writer.get_mut().stream_position()? + writer.buffer().len() as u64;
+                index.push(self.total_count, p, offset);
+            }
         }
 
+        if self.current_count == 0 {
+            self.current_first = Some(p);
+        }
         self.current_count += 1;
         self.total_count += 1;
-        if self.split_count > 0 && self.current_count >= self.split_count {
+        self.last_prime = Some(p);
+        if matches!(self.split, FileSplit::ByCount(n) if self.current_count >= n) {
             self.open_next_file()?;
         }
 
@@ -267,14 +999,70 @@ impl PrimeWriter for FilePrimeWriter {
     }
 
     fn finish(&mut self) -> io::Result<()> {
-        if let Some(mut w) = self.current_writer.take() {
-            if let OutputFormat::JSON = self.format {
-                write!(w, "]")?;
+        self.close_current_file()
+    }
+
+    fn mark_sieved(&mut self, high: u64) -> io::Result<()> {
+        self.sieved_through = Some(self.sieved_through.map_or(high, |h| h.max(high)));
+        Ok(())
+    }
+
+    fn wants_wheel_bits(&self) -> bool {
+        self.format == OutputFormat::WheelBitmap
+    }
+
+    fn write_wheel_bits(
+        &mut self,
+        first_block: u64,
+        bits: &[u8],
+        count: u64,
+        first_prime: Option<u64>,
+        last_prime: Option<u64>,
+    ) -> io::Result<()> {
+        if self.format != OutputFormat::WheelBitmap {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "wheel bits can only be written to the WheelBitmap format",
+            ));
+        }
+        let writer = self
+            .current_writer
+            .as_mut()
+            .expect("FilePrimeWriter not initialized");
+        // 篩のバイトをそのまま本体へ書き出す（WheelBitmap はファイルを分割せず、インデックスも持たない）
+        self.bitmap
+            .push_blocks(writer, first_block, bits, self.value_range.map(|(min, _)| min))?;
+
+        if count > 0 {
+            if self.current_count == 0 {
+                self.current_first = first_prime;
             }
-            w.flush()?;
+            self.current_count += count;
+            self.total_count += count;
+            self.last_prime = last_prime;
         }
         Ok(())
     }
+
+    fn sync_state(&mut self) -> io::Result<Option<WriterState>> {
+        // WheelBitmap は書きかけのバイトをメモリに持つため途中再開に対応しない
+        if self.format == OutputFormat::WheelBitmap {
+            return Ok(None);
+        }
+
+        let mut files = self.closed_files.clone();
+        if let Some(w) = self.current_writer.as_mut() {
+            w.flush()?;
+            w.get_ref().sync_data()?;
+            let bytes = w.get_ref().metadata()?.len();
+            files.extend(self.current_file_state(bytes));
+        }
+        Ok(Some(WriterState {
+            total_count: self.total_count,
+            last_prime: self.last_prime,
+            files,
+        }))
+    }
 }
 
 /// 最後の素数だけを保持し、ファイル書き出しは一切しない Writer。
@@ -293,6 +1081,14 @@ impl LastPrimeWriter {
         }
     }
 
+    /// チェックポイントの `WriterState` から個数と最後の素数を復元する。
+    pub fn from_state(state: &WriterState) -> Self {
+        Self {
+            last_prime: state.last_prime,
+            total_count: state.total_count,
+        }
+    }
+
     /// これまでに書き込まれた最後の素数を取得します。
     pub fn get_last_prime(&self) -> Option<u64> {
         self.last_prime
@@ -323,6 +1119,26 @@ impl PrimeWriter for LastPrimeWriter {
     fn finish(&mut self) -> io::Result<()> {
         Ok(())
     }
+
+    fn counts_only(&self) -> bool {
+        true
+    }
+
+    fn write_count(&mut self, count: u64, last_prime: Option<u64>) -> io::Result<()> {
+        self.total_count += count;
+        if last_prime.is_some() {
+            self.last_prime = last_prime;
+        }
+        Ok(())
+    }
+
+    fn sync_state(&mut self) -> io::Result<Option<WriterState>> {
+        Ok(Some(WriterState {
+            total_count: self.total_count,
+            last_prime: self.last_prime,
+            files: Vec::new(),
+        }))
+    }
 }
 
 
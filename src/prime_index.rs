@@ -0,0 +1,427 @@
+//! 素数ファイルの疎なインデックス（`primes.index`）。
+//!
+//! `FilePrimeWriter` が K 個ごとの素数について「通し番号・値・ファイル・バイト位置」を記録するサイドカーファイルです。
+//! `PrimeIndex` は記録点を二分探索し、いちばん近い記録点からファイルを少しだけ読むことで、
+//! n 番目の素数・区間内の素数・x 以下の素数の個数を全体を走査せずに求めます。
+//!
+//! ファイル構成（すべて little-endian）:
+//!
+//! | オフセット | サイズ | 内容 |
+//! |---|---|---|
+//! | 0  | 4 | マジック `b"SSPI"` |
+//! | 4  | 4 | 形式バージョン（`u32`） |
+//! | 8  | 4 | 出力形式（`u32`、1 = Binary / 2 = DeltaVarint） |
+//! | 12 | 4 | ファイル数 F（`u32`） |
+//! | 16 | 8 | 記録間隔 K（`u64`） |
+//! | 24 | 8 | 素数の総数（`u64`） |
+//! | 32 | 8 | 記録点の数 E（`u64`） |
+//! | 40 | - | ファイル表 × F（名前のバイト数 `u32` + UTF-8 のファイル名） |
+//! | -  | - | 記録点 × E（通し番号 `u64` + 値 `u64` + バイト位置 `u64` + ファイル番号 `u32` + 予約 `u32`） |
+//!
+//! - 通し番号は出力全体での 0 始まりの番号です。K の倍数の番号に加え、各ファイルの最初の素数も記録します。
+//!   そのため記録点から次の記録点までの素数は、すべて同じファイルにあります。
+//! - バイト位置はその素数の直後（次の素数を読み始める位置）です。
+//! - ファイル名はインデックスと同じフォルダからの相対名です。
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::config::OutputFormat;
+use crate::delta_varint::{decode_gap, read_varint, DELTA_VARINT_HEADER_LEN};
+
+/// ファイル先頭のマジック
+pub const PRIME_INDEX_MAGIC: [u8; 4] = *b"SSPI";
+/// 形式バージョン
+pub const PRIME_INDEX_VERSION: u32 = 1;
+/// インデックスのファイル名（タイムスタンプ接頭辞を除いた部分）
+pub const PRIME_INDEX_FILE_SUFFIX: &str = "primes.index";
+
+/// インデックスの記録点 1 つ。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexEntry {
+    /// 出力全体での 0 始まりの通し番号
+    pub index: u64,
+    /// 素数の値
+    pub value: u64,
+    /// ファイル表での番号
+    pub file: u32,
+    /// この素数の直後のバイト位置
+    pub offset: u64,
+}
+
+/// インデックスを作れる出力形式か（素数を先頭から順に復元できる Binary / DeltaVarint のみ）
+pub fn supports_format(format: OutputFormat) -> bool {
+    matches!(format, OutputFormat::Binary | OutputFormat::DeltaVarint)
+}
+
+/// ファイルの最初の素数の直後のバイト位置
+fn first_prime_offset(format: OutputFormat) -> u64 {
+    match format {
+        OutputFormat::DeltaVarint => DELTA_VARINT_HEADER_LEN,
+        _ => 8,
+    }
+}
+
+fn format_code(format: OutputFormat) -> u32 {
+    match format {
+        OutputFormat::DeltaVarint => 2,
+        _ => 1,
+    }
+}
+
+fn format_from_code(code: u32) -> io::Result<OutputFormat> {
+    match code {
+        1 => Ok(OutputFormat::Binary),
+        2 => Ok(OutputFormat::DeltaVarint),
+        _ => Err(invalid_data(format!(
+            "unsupported output format code {code}"
+        ))),
+    }
+}
+
+/// 書き出しながら記録点を集めるビルダー（`FilePrimeWriter` 用）。
+#[derive(Debug, Clone)]
+pub struct PrimeIndexBuilder {
+    format: OutputFormat,
+    stride: u64,
+    files: Vec<String>,
+    entries: Vec<IndexEntry>,
+}
+
+impl PrimeIndexBuilder {
+    /// `stride` 個ごとに記録するビルダー（`stride` は 1 以上）
+    pub fn new(format: OutputFormat, stride: u64) -> Self {
+        Self {
+            format,
+            stride: stride.max(1),
+            files: Vec::new(),
+            entries: Vec::new(),
+        }
+    }
+
+    /// 新しい出力ファイルを表に加える（以降の記録点はこのファイルを指す）。
+    pub fn start_file(&mut self, name: String) {
+        self.files.push(name);
+    }
+
+    /// 通し番号 `index` の素数を記録するか（K の倍数か、ファイルの最初の素数）
+    pub fn wants(&self, index: u64, first_in_file: bool) -> bool {
+        first_in_file || index.is_multiple_of(self.stride)
+    }
+
+    /// 現在のファイルにある素数を記録する。`offset` はその素数の直後のバイト位置。
+    pub fn push(&mut self, index: u64, value: u64, offset: u64) {
+        let file = self.files.len().saturating_sub(1) as u32;
+        self.entries.push(IndexEntry {
+            index,
+            value,
+            file,
+            offset,
+        });
+    }
+
+    /// 書き出し済みのファイル `path` を読み直し、`name` として表に加えて記録する（途中再開用）。
+    ///
+    /// - `first_index` はファイルの最初の素数の通し番号、`count` はファイル内の素数の個数です。
+    pub fn scan_file(
+        &mut self,
+        path: &Path,
+        name: String,
+        first_index: u64,
+        first_prime: Option<u64>,
+        count: u64,
+    ) -> io::Result<()> {
+        self.start_file(name);
+        let Some(first_prime) = first_prime.filter(|_| count > 0) else {
+            return Ok(());
+        };
+
+        let offset = first_prime_offset(self.format);
+        self.push(first_index, first_prime, offset);
+        let mut cursor = PrimeCursor::open(path, self.format, offset, first_prime)?;
+        for index in first_index + 1..first_index + count {
+            let p = cursor.next_prime()?;
+            if self.wants(index, false) {
+                self.push(index, p, cursor.offset);
+            }
+        }
+        Ok(())
+    }
+
+    /// インデックスファイルを書き出す。`total_count` は出力全体の素数の個数。
+    pub fn write_to_file(&self, path: &Path, total_count: u64) -> io::Result<()> {
+        let mut w = BufWriter::new(File::create(path)?);
+        w.write_all(&PRIME_INDEX_MAGIC)?;
+        w.write_all(&PRIME_INDEX_VERSION.to_le_bytes())?;
+        w.write_all(&format_code(self.format).to_le_bytes())?;
+        w.write_all(&(self.files.len() as u32).to_le_bytes())?;
+        w.write_all(&self.stride.to_le_bytes())?;
+        w.write_all(&total_count.to_le_bytes())?;
+        w.write_all(&(self.entries.len() as u64).to_le_bytes())?;
+        for name in &self.files {
+            w.write_all(&(name.len() as u32).to_le_bytes())?;
+            w.write_all(name.as_bytes())?;
+        }
+        for entry in &self.entries {
+            w.write_all(&entry.index.to_le_bytes())?;
+            w.write_all(&entry.value.to_le_bytes())?;
+            w.write_all(&entry.offset.to_le_bytes())?;
+            w.write_all(&entry.file.to_le_bytes())?;
+            w.write_all(&0u32.to_le_bytes())?;
+        }
+        w.flush()
+    }
+}
+
+/// インデックスを読み込んだもの。n 番目の素数・区間の素数・x 以下の個数を提供する。
+///
+/// - 対象は出力ファイルに含まれる素数だけです（`prime_min` = 1 で生成した出力なら `nth(0)` = 2、`pi_from_file(x)` = π(x)）。
+/// - 1 回の問い合わせで読むのは、記録点から最大 K 個分の素数です（`range` は区間内の素数も読みます）。
+#[derive(Debug, Clone)]
+pub struct PrimeIndex {
+    dir: PathBuf,
+    format: OutputFormat,
+    stride: u64,
+    total_count: u64,
+    files: Vec<String>,
+    entries: Vec<IndexEntry>,
+}
+
+impl PrimeIndex {
+    /// インデックスファイルを読み込む。出力ファイルは同じフォルダから開きます。
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let mut r = BufReader::new(File::open(path)?);
+
+        let mut magic = [0u8; 4];
+        r.read_exact(&mut magic)?;
+        if magic != PRIME_INDEX_MAGIC {
+            return Err(invalid_data("not a prime index file (bad magic)"));
+        }
+        let version = read_u32(&mut r)?;
+        if version != PRIME_INDEX_VERSION {
+            return Err(invalid_data(format!(
+                "unsupported prime index version {version} (expected {PRIME_INDEX_VERSION})"
+            )));
+        }
+        let format = format_from_code(read_u32(&mut r)?)?;
+        let file_count = read_u32(&mut r)?;
+        let stride = read_u64(&mut r)?;
+        let total_count = read_u64(&mut r)?;
+        let entry_count = read_u64(&mut r)?;
+
+        let mut files = Vec::with_capacity(file_count as usize);
+        for _ in 0..file_count {
+            let mut name = vec![0u8; read_u32(&mut r)? as usize];
+            r.read_exact(&mut name)?;
+            files.push(String::from_utf8(name).map_err(|e| invalid_data(e.to_string()))?);
+        }
+
+        let mut entries = Vec::new();
+        for _ in 0..entry_count {
+            let index = read_u64(&mut r)?;
+            let value = read_u64(&mut r)?;
+            let offset = read_u64(&mut r)?;
+            let file = read_u32(&mut r)?;
+            read_u32(&mut r)?;
+            if file >= file_count {
+                return Err(invalid_data(format!(
+                    "entry {index} refers to unknown file {file}"
+                )));
+            }
+            entries.push(IndexEntry {
+                index,
+                value,
+                file,
+                offset,
+            });
+        }
+        if total_count > 0 && entries.first().map(|e| e.index) != Some(0) {
+            return Err(invalid_data("index does not start at the first prime"));
+        }
+
+        Ok(Self {
+            dir: path.parent().map(Path::to_path_buf).unwrap_or_default(),
+            format,
+            stride,
+            total_count,
+            files,
+            entries,
+        })
+    }
+
+    /// 出力に含まれる素数の個数
+    pub fn len(&self) -> u64 {
+        self.total_count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.total_count == 0
+    }
+
+    /// 記録間隔 K
+    pub fn stride(&self) -> u64 {
+        self.stride
+    }
+
+    pub fn entries(&self) -> &[IndexEntry] {
+        &self.entries
+    }
+
+    /// 出力の `i` 番目（0 始まり）の素数。範囲外なら `None`。
+    pub fn nth(&self, i: u64) -> io::Result<Option<u64>> {
+        if i >= self.total_count {
+            return Ok(None);
+        }
+        let pos = self.entries.partition_point(|e| e.index <= i) - 1;
+        let mut found = None;
+        self.scan_from(pos, |index, p| {
+            if index == i {
+                found = Some(p);
+            }
+            index < i
+        })?;
+        Ok(found)
+    }
+
+    /// `[a, b]` の素数を昇順に返す。
+    pub fn range(&self, a: u64, b: u64) -> io::Result<Vec<u64>> {
+        let mut primes = Vec::new();
+        if a > b || self.entries.is_empty() {
+            return Ok(primes);
+        }
+        let pos = self
+            .entries
+            .partition_point(|e| e.value <= a)
+            .saturating_sub(1);
+        self.scan_from(pos, |_, p| {
+            if p >= a && p <= b {
+                primes.push(p);
+            }
+            p < b
+        })?;
+        Ok(primes)
+    }
+
+    /// 出力に含まれる `x` 以下の素数の個数。
+    pub fn pi_from_file(&self, x: u64) -> io::Result<u64> {
+        let pos = self.entries.partition_point(|e| e.value <= x);
+        if pos == 0 {
+            return Ok(0);
+        }
+        let mut count = 0;
+        self.scan_from(pos - 1, |index, p| {
+            if p <= x {
+                count = index + 1;
+            }
+            p < x
+        })?;
+        Ok(count)
+    }
+
+    /// 記録点 `pos` から順に素数を読み、`f(通し番号, 素数)` が `false` を返すか出力の終わりで止まる。
+    fn scan_from(&self, pos: usize, mut f: impl FnMut(u64, u64) -> bool) -> io::Result<()> {
+        for (k, entry) in self.entries.iter().enumerate().skip(pos) {
+            if !f(entry.index, entry.value) {
+                return Ok(());
+            }
+            let end = self
+                .entries
+                .get(k + 1)
+                .map_or(self.total_count, |next| next.index);
+            if entry.index + 1 >= end {
+                continue;
+            }
+            let path = self.dir.join(&self.files[entry.file as usize]);
+            let mut cursor = PrimeCursor::open(&path, self.format, entry.offset, entry.value)?;
+            for index in entry.index + 1..end {
+                if !f(index, cursor.next_prime()?) {
+                    return Ok(());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// ファイルの途中から素数を順に読むカーソル
+struct PrimeCursor {
+    reader: BufReader<File>,
+    format: OutputFormat,
+    prev: u64,
+    /// 直前に読んだ素数の直後のバイト位置
+    offset: u64,
+}
+
+impl PrimeCursor {
+    /// `offset`（値 `prev` の素数の直後）から読み始める。
+    fn open(path: &Path, format: OutputFormat, offset: u64, prev: u64) -> io::Result<Self> {
+        let mut file = File::open(path)?;
+        file.seek(SeekFrom::Start(offset))?;
+        Ok(Self {
+            reader: BufReader::with_capacity(64 * 1024, file),
+            format,
+            prev,
+            offset,
+        })
+    }
+
+    fn next_prime(&mut self) -> io::Result<u64> {
+        let mut counting = CountingReader {
+            inner: &mut self.reader,
+            bytes: 0,
+        };
+        let p = match self.format {
+            OutputFormat::DeltaVarint => {
+                let encoded = read_varint(&mut counting)?.ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "index points past the end of the file",
+                    )
+                })?;
+                decode_gap(self.prev, encoded).ok_or_else(|| {
+                    invalid_data(format!("invalid gap value {encoded} after {}", self.prev))
+                })?
+            }
+            _ => {
+                let mut buf = [0u8; 8];
+                counting.read_exact(&mut buf)?;
+                u64::from_le_bytes(buf)
+            }
+        };
+        self.offset += counting.bytes;
+        self.prev = p;
+        Ok(p)
+    }
+}
+
+/// 読んだバイト数を数える `Read`
+struct CountingReader<R> {
+    inner: R,
+    bytes: u64,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.bytes += n as u64;
+        Ok(n)
+    }
+}
+
+fn read_u32<R: Read>(r: &mut R) -> io::Result<u32> {
+    let mut buf = [0u8; 4];
+    r.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64<R: Read>(r: &mut R) -> io::Result<u64> {
+    let mut buf = [0u8; 8];
+    r.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn invalid_data(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
@@ -0,0 +1,190 @@
+//! ライブラリ利用者向けの素数イテレータ API。
+//!
+//! - `primes_in(a..=b)`: 区間の素数を昇順に 1 つずつ返す遅延イテレータ
+//! - `par_prime_chunks(a..=b)`: セグメントごとの素数を `Vec<u64>` で返す rayon 並列イテレータ
+//!
+//! どちらも Generator と同じ mod 30 ホイールの分割篩（`cpu_engine`）を使い、
+//! 一度に保持するのは 1 セグメント分の素数だけです。`PrimeWriter` や停止フラグは不要です。
+
+use std::ops::RangeInclusive;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+
+use rayon::prelude::*;
+
+use crate::config::WheelType;
+use crate::cpu_engine::sieve_segment_collect;
+use crate::sieve_math::{integer_sqrt, sieving_primes};
+
+/// イテレータが 1 回に篩うセグメントの幅（整数の個数）
+pub const DEFAULT_ITER_SEGMENT_SIZE: u64 = 1 << 20;
+
+/// mod 30 ホイールで除外される素数（篩の結果には含まれないため別途返す）
+const WHEEL_PRIMES: [u64; 3] = [2, 3, 5];
+
+/// 篩の対象となる最小値（ホイール素数の次）
+const SIEVE_START: u64 = 7;
+
+/// `[low, high]` の篩に必要な √high までの素数
+fn small_primes_for(low: u64, high: u64) -> Vec<u64> {
+    sieving_primes(low, high).unwrap_or_default()
+}
+
+/// `range` に含まれる素数を昇順に返すイテレータを作る。
+///
+/// ```
+/// use sosu_seisei_main2::prime_iter::primes_in;
+///
+/// let primes: Vec<u64> = primes_in(10..=30).collect();
+/// assert_eq!(primes, vec![11, 13, 17, 19, 23, 29]);
+/// ```
+pub fn primes_in(range: RangeInclusive<u64>) -> Primes {
+    let (low, high) = range.into_inner();
+    let wheel_primes: Vec<u64> = WHEEL_PRIMES
+        .into_iter()
+        .filter(|&p| low <= p && p <= high)
+        .collect();
+    let next_low = Some(low.max(SIEVE_START)).filter(|&l| l <= high);
+
+    Primes {
+        wheel_primes: wheel_primes.into_iter(),
+        small_primes: Vec::new(),
+        small_limit: 0,
+        next_low,
+        high,
+        segment_size: DEFAULT_ITER_SEGMENT_SIZE,
+        buffer: Vec::new().into_iter(),
+        stop_flag: AtomicBool::new(false),
+    }
+}
+
+/// `primes_in` が返すイテレータ。
+///
+/// セグメントを 1 つ篩うごとにその素数をバッファし、使い切ったら次のセグメントを篩います。
+pub struct Primes {
+    wheel_primes: std::vec::IntoIter<u64>,
+    /// 篩済みセグメントの上限の平方根までの素数（必要に応じて倍々に伸ばす）
+    small_primes: Vec<u64>,
+    /// `small_primes` が網羅している √ の上限（`small_limit^2` と `high` の小さい方までの篩に使える）
+    small_limit: u64,
+    /// 次に篩うセグメントの開始値（篩い終えたら None）
+    next_low: Option<u64>,
+    high: u64,
+    segment_size: u64,
+    buffer: std::vec::IntoIter<u64>,
+    stop_flag: AtomicBool,
+}
+
+impl Primes {
+    /// セグメント幅を変更する（メモリ使用量とのトレードオフ。0 は 1 として扱う）。
+    pub fn with_segment_size(mut self, segment_size: u64) -> Self {
+        self.segment_size = segment_size.max(1);
+        self
+    }
+
+    /// 次のセグメントを篩ってバッファに詰める。篩う範囲が残っていなければ false。
+    fn refill(&mut self) -> bool {
+        let Some(low) = self.next_low else {
+            return false;
+        };
+        let seg_end = low.saturating_add(self.segment_size - 1).min(self.high);
+        self.next_low = seg_end.checked_add(1).filter(|&n| n <= self.high);
+
+        // 巨大な上限でも √high までの素数を一度に作らず、セグメントの進行に合わせて伸ばす
+        let needed = integer_sqrt(seg_end) + 1;
+        if needed > self.small_limit {
+            let cap = integer_sqrt(self.high) + 1;
+            self.small_limit = needed.saturating_mul(2).min(cap).max(needed);
+            let covered = self.small_limit.saturating_mul(self.small_limit).min(self.high);
+            self.small_primes = small_primes_for(low, covered);
+        }
+
+        let primes = sieve_segment_collect(
+            low,
+            seg_end,
+            &self.small_primes,
+            &[],
+            &self.stop_flag,
+            WheelType::Mod30,
+        );
+        self.buffer = primes.into_iter();
+        true
+    }
+}
+
+impl Iterator for Primes {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        if let Some(p) = self.wheel_primes.next() {
+            return Some(p);
+        }
+        loop {
+            if let Some(p) = self.buffer.next() {
+                return Some(p);
+            }
+            if !self.refill() {
+                return None;
+            }
+        }
+    }
+}
+
+/// `range` をセグメントに分け、各セグメントの素数を並列に篩う rayon イテレータを作る。
+///
+/// - 各要素は 1 セグメント分の素数（昇順）で、`collect` すればセグメント順に並びます。
+/// - 2, 3, 5 が範囲に含まれる場合は最初のチャンクの先頭に入ります。
+pub fn par_prime_chunks(
+    range: RangeInclusive<u64>,
+) -> impl IndexedParallelIterator<Item = Vec<u64>> {
+    par_prime_chunks_with_segment_size(range, DEFAULT_ITER_SEGMENT_SIZE)
+}
+
+/// セグメント幅を指定する `par_prime_chunks`（0 は 1 として扱う）。
+pub fn par_prime_chunks_with_segment_size(
+    range: RangeInclusive<u64>,
+    segment_size: u64,
+) -> impl IndexedParallelIterator<Item = Vec<u64>> {
+    let (low, high) = range.into_inner();
+    let segment_size = segment_size.max(1);
+    let sieve_low = low.max(SIEVE_START);
+    let wheel_primes: Vec<u64> = WHEEL_PRIMES
+        .into_iter()
+        .filter(|&p| low <= p && p <= high)
+        .collect();
+
+    let segments = if sieve_low <= high {
+        ((high - sieve_low) / segment_size + 1) as usize
+    } else if wheel_primes.is_empty() {
+        0
+    } else {
+        1
+    };
+    let small_primes = Arc::new(if sieve_low <= high {
+        small_primes_for(sieve_low, high)
+    } else {
+        Vec::new()
+    });
+
+    (0..segments).into_par_iter().map(move |i| {
+        let mut chunk = if i == 0 {
+            wheel_primes.clone()
+        } else {
+            Vec::new()
+        };
+        if sieve_low <= high {
+            let seg_low = sieve_low + i as u64 * segment_size;
+            let seg_high = seg_low.saturating_add(segment_size - 1).min(high);
+            let stop_flag = AtomicBool::new(false);
+            chunk.extend(sieve_segment_collect(
+                seg_low,
+                seg_high,
+                &small_primes,
+                &[],
+                &stop_flag,
+                WheelType::Mod30,
+            ));
+        }
+        chunk
+    })
+}
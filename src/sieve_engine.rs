@@ -0,0 +1,403 @@
+//! 素数生成エンジンの共通インターフェース（`SieveEngine`）と実装の切り替え。
+//!
+//! - `SegmentedEngine`: `cpu_engine` の Rayon 並列・ホイール分割篩（通常はこれを使う）
+//! - `ReferenceEngine`: 単一スレッドの素朴な分割エラトステネスの篩（結果の突き合わせ用）
+//! - `AtkinEngine`: 単一スレッドの分割アトキンの篩
+//!
+//! どのエンジンも `PrimeWriter` へ昇順に素数を書き、`Progress` で進捗を報告し、
+//! `stop_flag` が立ったら途中で終了し、`pause_flag` が立っている間は状態を保ったまま待ちます。
+//! 使うエンジンは `Config::engine` で選びます。
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+
+use bitvec::prelude::*;
+
+use crate::config::{Config, SieveEngineKind};
+use crate::cpu_engine::generate_primes_cpu_pausable;
+use crate::engine_types::{compute_eta, PauseFlag, PrimeResult, Progress};
+use crate::output::PrimeWriter;
+use crate::sieve_math::{integer_sqrt, sieving_primes};
+
+/// 素数生成エンジン。
+///
+/// 引数の意味は `cpu_engine::generate_primes_cpu_pausable` と同じです。
+/// - 素数は昇順に `writer` へ書き、終了時（停止時も含む）に `writer.finish()` を呼びます。
+/// - 区間を書き終えるたびに `writer.mark_sieved(high)` でどこまで篩い終えたかを知らせます。
+/// - `resume_from` が `Some(next_start)` の場合は、その位置より前の素数を出力しません。
+pub trait SieveEngine: Send + Sync {
+    /// ログとメタレポートに記録するエンジン名
+    fn name(&self) -> &'static str;
+
+    fn generate(
+        &self,
+        cfg: &Config,
+        stop_flag: &AtomicBool,
+        pause_flag: &PauseFlag,
+        writer: &mut dyn PrimeWriter,
+        callbacks: EngineCallbacks<'_>,
+        resume_from: Option<u64>,
+    ) -> PrimeResult<()>;
+}
+
+/// エンジンが生成中に呼び出すコールバック。
+pub struct EngineCallbacks<'a> {
+    /// 進捗の報告。少なくとも区間のグループごとに 1 回呼びます。
+    pub progress: &'a mut dyn FnMut(Progress),
+    /// `on_group_done(next_start, writer)` は区間のグループを書き終えるたびに呼び、
+    /// `next_start` から再開できることを表します（チェックポイントの保存に使う）。
+    pub on_group_done: &'a mut dyn FnMut(u64, &mut dyn PrimeWriter) -> PrimeResult<()>,
+}
+
+/// 設定値に対応するエンジンを返す。
+pub fn engine_for(kind: SieveEngineKind) -> Box<dyn SieveEngine> {
+    match kind {
+        SieveEngineKind::Segmented => Box::new(SegmentedEngine),
+        SieveEngineKind::Reference => Box::new(ReferenceEngine),
+        SieveEngineKind::Atkin => Box::new(AtkinEngine),
+    }
+}
+
+/// `cfg.engine` が使わない設定のうち、既定値から変えられているもののキー名を返す。
+///
+/// 単一スレッドのエンジン（`Reference` / `Atkin`）は区間を 1 つずつ篩うだけなので、
+/// スレッド数・メモリ上限・セグメントサイズの自動選択・パイプラインはどれも効きません。
+pub fn ignored_settings(cfg: &Config) -> Vec<&'static str> {
+    if cfg.engine == SieveEngineKind::Segmented {
+        return Vec::new();
+    }
+    [
+        ("threads", cfg.threads != 0),
+        ("memory_ceiling_mb", cfg.memory_ceiling_mb != 0),
+        ("auto_segment_size", cfg.auto_segment_size),
+        ("pipeline_depth", cfg.pipeline_depth != 0),
+    ]
+    .into_iter()
+    .filter_map(|(key, set)| set.then_some(key))
+    .collect()
+}
+
+/// Rayon で並列化したホイール分割篩（`cpu_engine`）。
+pub struct SegmentedEngine;
+
+impl SieveEngine for SegmentedEngine {
+    fn name(&self) -> &'static str {
+        "CPU engine (Rayon segmented sieve)"
+    }
+
+    fn generate(
+        &self,
+        cfg: &Config,
+        stop_flag: &AtomicBool,
+        pause_flag: &PauseFlag,
+        writer: &mut dyn PrimeWriter,
+        callbacks: EngineCallbacks<'_>,
+        resume_from: Option<u64>,
+    ) -> PrimeResult<()> {
+        generate_primes_cpu_pausable(
+            cfg,
+            stop_flag,
+            pause_flag,
+            writer,
+            callbacks.progress,
+            resume_from,
+            callbacks.on_group_done,
+        )
+    }
+}
+
+/// 単一スレッドの分割エラトステネスの篩（ホイールなし、1 ビット = 1 整数）。
+///
+/// 実装が単純なので、ほかのエンジンの結果を確かめる基準として使います。
+pub struct ReferenceEngine;
+
+impl SieveEngine for ReferenceEngine {
+    fn name(&self) -> &'static str {
+        "Reference engine (single-threaded sieve of Eratosthenes)"
+    }
+
+    fn generate(
+        &self,
+        cfg: &Config,
+        stop_flag: &AtomicBool,
+        pause_flag: &PauseFlag,
+        writer: &mut dyn PrimeWriter,
+        callbacks: EngineCallbacks<'_>,
+        resume_from: Option<u64>,
+    ) -> PrimeResult<()> {
+        let sieve = SequentialSieve {
+            sieve_segment: sieve_eratosthenes_segment,
+            min_segment_size: 1,
+        };
+        generate_sequential(
+            cfg,
+            stop_flag,
+            pause_flag,
+            writer,
+            callbacks,
+            resume_from,
+            sieve,
+        )
+    }
+}
+
+/// 単一スレッドの分割アトキンの篩。
+///
+/// 二次形式 `4x² + y²` / `3x² + y²` / `3x² − y²` の解の個数の偶奇で候補を決め、
+/// 素数の平方の倍数を取り除きます。
+pub struct AtkinEngine;
+
+impl SieveEngine for AtkinEngine {
+    fn name(&self) -> &'static str {
+        "Atkin engine (single-threaded sieve of Atkin)"
+    }
+
+    fn generate(
+        &self,
+        cfg: &Config,
+        stop_flag: &AtomicBool,
+        pause_flag: &PauseFlag,
+        writer: &mut dyn PrimeWriter,
+        callbacks: EngineCallbacks<'_>,
+        resume_from: Option<u64>,
+    ) -> PrimeResult<()> {
+        // x のループは区間幅によらず O(√high) なので、区間幅を √prime_max 以上にしてその分を均す
+        let sieve = SequentialSieve {
+            sieve_segment: sieve_atkin_segment,
+            min_segment_size: integer_sqrt(cfg.prime_max),
+        };
+        generate_sequential(
+            cfg,
+            stop_flag,
+            pause_flag,
+            writer,
+            callbacks,
+            resume_from,
+            sieve,
+        )
+    }
+}
+
+/// 単一スレッドのエンジンが `segment_size == 0`（自動）のときに使う区間幅
+const SEQUENTIAL_SEGMENT_SIZE: u64 = 1 << 20;
+
+/// 単一スレッドのエンジンで 1 グループにまとめる区間の数（グループごとに `on_group_done` を呼ぶ）。
+///
+/// チェックポイントは保存のたびに同期書き込みをするため、区間ごとには保存しません。
+const SEQUENTIAL_GROUP_SEGMENTS: u64 = 64;
+
+/// 単一スレッドのエンジンが区間を篩う方法
+struct SequentialSieve {
+    /// 区間 `[low, high]` の素数を昇順に返す篩（`small_primes` は区間に倍数を持つ √high 以下の素数をすべて含む）
+    sieve_segment: fn(u64, u64, &[u64]) -> Vec<u64>,
+    /// 区間幅の下限（設定された区間幅がこれより小さければ広げる）
+    min_segment_size: u64,
+}
+
+/// 区間を 1 つずつ篩って書き出す、単一スレッドのエンジンの共通部分。
+fn generate_sequential(
+    cfg: &Config,
+    stop_flag: &AtomicBool,
+    pause_flag: &PauseFlag,
+    writer: &mut dyn PrimeWriter,
+    callbacks: EngineCallbacks<'_>,
+    resume_from: Option<u64>,
+    sieve: SequentialSieve,
+) -> PrimeResult<()> {
+    let prime_min = cfg.prime_min;
+    let prime_max = cfg.prime_max;
+    if prime_min > prime_max {
+        return Err("prime_min must be <= prime_max".into());
+    }
+
+    let start_time = Instant::now();
+    // 実行前の一時停止時間は数えない
+    let paused_at_start = pause_flag.paused_secs();
+    let total_range = (prime_max - prime_min).saturating_add(1);
+    let segment_size = if cfg.segment_size > 0 {
+        cfg.segment_size
+    } else {
+        SEQUENTIAL_SEGMENT_SIZE
+    }
+    .max(sieve.min_segment_size);
+
+    let start = resume_from.map_or(prime_min, |next| next.max(prime_min));
+    // ETA はこの実行で処理した分だけから推定する（再開前の処理済み範囲を除く）
+    let initial_processed = start.saturating_sub(prime_min).min(total_range);
+    let mut next_start = Some(start).filter(|&s| s <= prime_max);
+    log::info!("Segment size: {segment_size}, starting at {start}");
+
+    let small_primes = sieving_primes(prime_min, prime_max)?;
+    // 前回 `on_group_done` を呼んでから書き出した区間の数
+    let mut segments_in_group = 0;
+
+    while let Some(low) = next_start {
+        pause_flag.wait_while_paused(stop_flag);
+        if stop_flag.load(Ordering::SeqCst) {
+            break;
+        }
+
+        let high = low.saturating_add(segment_size - 1).min(prime_max);
+        let primes_end = small_primes.partition_point(|&p| p <= integer_sqrt(high));
+        for p in (sieve.sieve_segment)(low, high, &small_primes[..primes_end]) {
+            writer.write_prime(p)?;
+        }
+        writer.mark_sieved(high)?;
+        next_start = high.checked_add(1).filter(|&n| n <= prime_max);
+
+        let processed = (high - prime_min).saturating_add(1);
+        (callbacks.progress)(Progress {
+            processed,
+            total: total_range,
+            eta_secs: compute_eta(
+                processed - initial_processed,
+                total_range - initial_processed,
+                start_time.elapsed().as_secs_f64(),
+                pause_flag.paused_secs() - paused_at_start,
+            ),
+        });
+        segments_in_group += 1;
+        if segments_in_group == SEQUENTIAL_GROUP_SEGMENTS {
+            (callbacks.on_group_done)(next_start.unwrap_or(prime_max.saturating_add(1)), writer)?;
+            segments_in_group = 0;
+        }
+    }
+    // 最後のグループ（最後まで篩った場合も停止した場合も）を確定する
+    if segments_in_group > 0 {
+        (callbacks.on_group_done)(next_start.unwrap_or(prime_max.saturating_add(1)), writer)?;
+    }
+
+    writer.finish()?;
+    Ok(())
+}
+
+/// エラトステネスの篩で `[low, high]` の素数を求める（1 ビット = 1 整数）。
+fn sieve_eratosthenes_segment(low: u64, high: u64, small_primes: &[u64]) -> Vec<u64> {
+    let mut is_prime = bitvec![1; (high - low + 1) as usize];
+    for n in low..=high.min(1) {
+        is_prime.set((n - low) as usize, false);
+    }
+
+    for &p in small_primes {
+        // p 自身は残し、p^2 以上の倍数だけを消す
+        let Some(first) = low.div_ceil(p).checked_mul(p).map(|m| m.max(p * p)) else {
+            continue;
+        };
+        let mut m = first;
+        while m <= high {
+            is_prime.set((m - low) as usize, false);
+            let Some(next) = m.checked_add(p) else {
+                break;
+            };
+            m = next;
+        }
+    }
+
+    is_prime.iter_ones().map(|i| low + i as u64).collect()
+}
+
+/// アトキンの篩で `[low, high]` の素数を求める。
+///
+/// - 2 と 3 は二次形式では現れないので、区間内にあれば別途加えます。
+/// - `x`, `y` の範囲は `y² ∈ [low − a·x², high − a·x²]` から平方根で直接求めます
+///   （`3x²` は u64 を超えうるので u128 で計算します）。
+/// - x のループは区間幅によらず約 √high 回まわるので、区間幅は √prime_max 以上にして使います。
+fn sieve_atkin_segment(low: u64, high: u64, small_primes: &[u64]) -> Vec<u64> {
+    let mut is_prime = bitvec![0; (high - low + 1) as usize];
+    let (lo, hi) = (low as u128, high as u128);
+
+    // y² ∈ [min_sq, max_sq]（y ≤ y_max）となる y について n = base ± y² を求め、
+    // n mod 12 が `residues` に含まれれば反転する
+    let mut toggle =
+        |base: u128, min_sq: u128, max_sq: u128, y_max: u64, plus: bool, residues: &[u128]| {
+            if min_sq > max_sq {
+                return;
+            }
+            let y_from = ceil_sqrt(min_sq as u64).max(1);
+            let y_to = integer_sqrt(max_sq as u64).min(y_max);
+            for y in y_from..=y_to {
+                let y_sq = y as u128 * y as u128;
+                let n = if plus { base + y_sq } else { base - y_sq };
+                if residues.contains(&(n % 12)) {
+                    let idx = (n - lo) as usize;
+                    let flag = is_prime[idx];
+                    is_prime.set(idx, !flag);
+                }
+            }
+        };
+
+    let mut x = 1u64;
+    loop {
+        let x_sq = x as u128 * x as u128;
+        // 3x² − (x−1)² = 2x² + 2x − 1 が high を超えたら、どの形式も区間に届かない
+        if 2 * x_sq + 2 * x as u128 - 1 > hi {
+            break;
+        }
+
+        // 4x² + y² ≡ 1, 5 (mod 12)
+        let four = 4 * x_sq;
+        if four < hi {
+            toggle(
+                four,
+                lo.saturating_sub(four),
+                hi - four,
+                u64::MAX,
+                true,
+                &[1, 5],
+            );
+        }
+        // 3x² + y² ≡ 7 (mod 12)
+        let three = 3 * x_sq;
+        if three < hi {
+            toggle(
+                three,
+                lo.saturating_sub(three),
+                hi - three,
+                u64::MAX,
+                true,
+                &[7],
+            );
+        }
+        // 3x² − y² ≡ 11 (mod 12)（y < x なので y² ≤ (x−1)² で打ち切る）
+        if three > lo {
+            let y_max = x - 1;
+            let max_sq = (three - lo).min(y_max as u128 * y_max as u128);
+            toggle(three, three.saturating_sub(hi), max_sq, y_max, false, &[11]);
+        }
+        x += 1;
+    }
+
+    // 素数 p ≥ 5 の平方の倍数を取り除く
+    for &p in small_primes.iter().filter(|&&p| p >= 5) {
+        let Some(square) = p.checked_mul(p).filter(|&sq| sq <= high) else {
+            break;
+        };
+        let Some(mut m) = low.div_ceil(square).checked_mul(square) else {
+            continue;
+        };
+        while m <= high {
+            is_prime.set((m - low) as usize, false);
+            let Some(next) = m.checked_add(square) else {
+                break;
+            };
+            m = next;
+        }
+    }
+
+    let mut primes: Vec<u64> = [2u64, 3]
+        .into_iter()
+        .filter(|&p| low <= p && p <= high)
+        .collect();
+    primes.extend(is_prime.iter_ones().map(|i| low + i as u64));
+    primes
+}
+
+/// `n` 以上の最小の整数平方根（⌈√n⌉）
+fn ceil_sqrt(n: u64) -> u64 {
+    let r = integer_sqrt(n);
+    if r * r == n {
+        r
+    } else {
+        r + 1
+    }
+}
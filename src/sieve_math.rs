@@ -1,56 +1,170 @@
+use bitvec::prelude::*;
+
 use crate::engine_types::PrimeResult;
 
 /// `n` 以下の最大の整数平方根を 2 分探索で求める。
+///
+/// `u64::MAX` まで正しく動くよう、探索範囲を `[0, 2^32 - 1]` に絞り、
+/// 中点は上側の中点 `low + (high - low).div_ceil(2)` で求めます
+/// （`low + high` は桁あふれしうるため。`low = mid` で必ず前進させるため上側を取る）。
 pub fn integer_sqrt(n: u64) -> u64 {
     let mut low = 0u64;
-    let mut high = n;
-    while low <= high {
-        let mid = (low + high) >> 1;
-        match mid.checked_mul(mid) {
-            Some(val) if val == n => return mid,
-            Some(val) if val < n => low = mid + 1,
-            _ => high = mid - 1,
+    let mut high = n.min(u32::MAX as u64);
+    while low < high {
+        // 上側の中点（low = mid で必ず前進させる）
+        let mid = low + (high - low).div_ceil(2);
+        if mid * mid <= n {
+            low = mid;
+        } else {
+            high = mid - 1;
         }
     }
-    high
+    low
 }
 
 /// 単純なエラトステネスの篩で `[2, limit]` の素数を列挙する。
+///
+/// - 奇数だけをビット配列で持つため、作業領域は `limit / 16` バイトです。
+/// - 結果はすべて保持するので、区間の篩に使う √high までの素数は `sieving_primes` で求めます
+///   （`limit = 2^32` では約 2 億個、1.6 GB になるため）。
+/// - `limit` がこの環境の `usize` に収まらない場合はエラーを返します。
 pub fn simple_sieve(limit: u64) -> PrimeResult<Vec<u64>> {
     if limit < 2 {
         return Ok(Vec::new());
     }
 
-    let size = (limit + 1) as usize;
-    let mut is_prime = vec![true; size];
-    is_prime[0] = false;
-    if limit >= 1 {
-        is_prime[1] = false;
+    // インデックス i は奇数 2i + 1 を表す（i = 0 の 1 は素数ではない）
+    let size = usize::try_from(limit / 2 + 1)
+        .map_err(|_| format!("simple_sieve limit is too large for this platform: {limit}"))?;
+    let mut is_prime = bitvec![1; size];
+    is_prime.set(0, false);
+    if limit.is_multiple_of(2) {
+        // 2i + 1 <= limit を満たさない最後の要素を落とす
+        is_prime.set(size - 1, false);
     }
 
     let lim_sqrt = integer_sqrt(limit);
-    for i in 2..=lim_sqrt as usize {
-        if is_prime[i] {
-            let mut j = i * i;
-            while j <= limit as usize {
-                is_prime[j] = false;
-                j += i;
+    let mut p = 3u64;
+    while p <= lim_sqrt {
+        if is_prime[(p / 2) as usize] {
+            let mut j = (p * p / 2) as usize;
+            while j < size {
+                is_prime.set(j, false);
+                j += p as usize;
             }
         }
+        p += 2;
     }
 
+    let mut primes = Vec::with_capacity(estimate_prime_count(limit));
+    primes.push(2);
+    primes.extend(is_prime.iter_ones().map(|i| 2 * i as u64 + 1));
+    Ok(primes)
+}
+
+/// π(limit) の上界の目安（`Vec` の事前確保用。x / (ln x - 1.1) を少し上回る）
+fn estimate_prime_count(limit: u64) -> usize {
+    if limit < 100 {
+        return 25;
+    }
+    let x = limit as f64;
+    (x / (x.ln() - 1.1) * 1.05) as usize
+}
+
+/// 区間 `[low, high]` の篩に使う素数を昇順に返す。
+///
+/// - 返すのは √high 以下の素数のうち、`max(low, p^2)` 以上 `high` 以下に倍数を持つものだけです
+///   （倍数を持たない素数は区間の篩に影響しません）。
+/// - √high までの素数は区間篩で少しずつ作って選別するため、`high` が 2^64 付近でも
+///   √high までの素数表（約 2 億個）を一度に持ちません。区間が狭ければほとんどの素数は捨てられます。
+pub fn sieving_primes(low: u64, high: u64) -> PrimeResult<Vec<u64>> {
     let mut primes = Vec::new();
-    for (i, &flag) in is_prime
-        .iter()
-        .enumerate()
-        .take(limit as usize + 1)
-        .skip(2)
-    {
-        if flag {
-            primes.push(i as u64);
+    for_each_prime_up_to(integer_sqrt(high), |p| {
+        // p <= √high なので p^2 は桁あふれしない
+        let from = low.max(p * p);
+        if from <= high && high / p * p >= from {
+            primes.push(p);
         }
-    }
+    })?;
     Ok(primes)
 }
 
+/// `for_each_prime_up_to` が事前篩で消しておく素数
+const BASE_PRESIEVE_PRIMES: [u64; 5] = [3, 5, 7, 11, 13];
+
+/// 事前篩パターンの周期（3・5・7・11・13 の積。奇数の個数で数える）
+const BASE_PRESIEVE_PERIOD: usize = 15_015;
+
+/// `for_each_prime_up_to` の 1 セグメントの語数（約 117 KiB）。
+///
+/// 64 ビット × 周期なので、どのセグメントも事前篩パターンの先頭から始まります。
+const BASE_SEGMENT_WORDS: usize = BASE_PRESIEVE_PERIOD;
+
+/// `[2, limit]` の素数を小さい順に `f` へ渡す（奇数だけの区間篩。`limit` は 2^32 程度まで）。
+///
+/// √(2^64) までの約 2 億個の素数を数秒で流せるよう、3〜13 の倍数は事前篩パターンの
+/// コピーで消し、残りの素数はビット位置を直接進めて消します。
+fn for_each_prime_up_to(limit: u64, mut f: impl FnMut(u64)) -> PrimeResult<()> {
+    for p in std::iter::once(2).chain(BASE_PRESIEVE_PRIMES) {
+        if p <= limit {
+            f(p);
+        }
+    }
+    if limit < 17 {
+        return Ok(());
+    }
 
+    // ビット i が奇数 3 + 2i を表す（立っていれば合成数）
+    let segment_bits = BASE_SEGMENT_WORDS * 64;
+    let mut pattern = vec![0u64; BASE_SEGMENT_WORDS];
+    for p in BASE_PRESIEVE_PRIMES {
+        let mut bit = ((p - 3) / 2) as usize;
+        while bit < segment_bits {
+            pattern[bit / 64] |= 1 << (bit % 64);
+            bit += p as usize;
+        }
+    }
+
+    // √limit までの 17 以上の素数と、それぞれが次に消す倍数のビット位置
+    let base: Vec<u64> = simple_sieve(integer_sqrt(limit))?
+        .into_iter()
+        .filter(|&p| p >= 17)
+        .collect();
+    let mut next: Vec<u64> = base.iter().map(|&p| (p * p - 3) / 2).collect();
+    let mut words = vec![0u64; BASE_SEGMENT_WORDS];
+
+    let last_bit = (limit - 3) / 2;
+    let mut seg_bit = 0u64;
+    while seg_bit <= last_bit {
+        words.copy_from_slice(&pattern);
+        let seg_end = seg_bit + segment_bits as u64;
+        for (&p, next_bit) in base.iter().zip(next.iter_mut()) {
+            if *next_bit >= seg_end {
+                continue;
+            }
+            let step = p as usize;
+            let mut bit = (*next_bit - seg_bit) as usize;
+            while bit < segment_bits {
+                words[bit / 64] |= 1 << (bit % 64);
+                bit += step;
+            }
+            *next_bit = seg_bit + bit as u64;
+        }
+
+        let bits = (last_bit - seg_bit + 1).min(segment_bits as u64);
+        for (w, &word) in words.iter().enumerate().take(bits.div_ceil(64) as usize) {
+            let mut primes = !word;
+            let valid = bits - w as u64 * 64;
+            if valid < 64 {
+                primes &= (1u64 << valid) - 1;
+            }
+            while primes != 0 {
+                let bit = seg_bit + w as u64 * 64 + primes.trailing_zeros() as u64;
+                f(3 + 2 * bit);
+                primes &= primes - 1;
+            }
+        }
+        seg_bit = seg_end;
+    }
+    Ok(())
+}
@@ -171,13 +171,26 @@ fn render_progress_card(ui: &mut egui::Ui, app: &MyApp, _width: f32, height: f32
 
         ui.add_space(12.0);
 
+        // このプロセスの RSS とシステム全体の使用量は別々に表示する
+        let mb = |bytes: u64| bytes as f64 / (1024.0 * 1024.0);
         ui.horizontal(|ui| {
-            ui.label(field_label("Memory"));
+            ui.label(field_label("Process"));
             ui.label(
-                egui::RichText::new(format!("{} / {} KB", app.mem_usage, app.total_mem))
+                egui::RichText::new(format!("{:.1} MB", mb(app.process_mem_usage)))
                     .size(font_sizes::LABEL)
                     .color(colors::TEXT_SECONDARY),
             );
+            ui.add_space(16.0);
+            ui.label(field_label("System"));
+            ui.label(
+                egui::RichText::new(format!(
+                    "{:.1} / {:.1} MB",
+                    mb(app.mem_usage),
+                    mb(app.total_mem)
+                ))
+                .size(font_sizes::LABEL)
+                .color(colors::TEXT_SECONDARY),
+            );
         });
     });
 }
@@ -363,8 +363,7 @@ where
         let mut ring: Vec<(i32, i32)> = Vec::with_capacity((6 * radius) as usize);
         let mut q = dirs[4].0 * k;
         let mut r = dirs[4].1 * k;
-        for dir in 0..6 {
-            let (dq, dr) = dirs[dir];
+        for &(dq, dr) in &dirs {
             for _ in 0..radius {
                 ring.push((q, r));
                 q += dq;
@@ -384,11 +383,9 @@ where
         //   画面上で最も右側（world_x が最大）にあるセルをスタートにする。
         //   これにより、7→8 が「7 の右隣」になるような自然な渦巻きを作る。
         let start_idx = if radius == 1 {
-            if let Some(idx) = ring.iter().position(|&(q, r)| q == 1 && r == 0) {
-                idx
-            } else {
-                0
-            }
+            ring.iter()
+                .position(|&(q, r)| q == 1 && r == 0)
+                .unwrap_or_default()
         } else {
             // last に隣接する外側リングセルをすべて探し、その中で最も右側にあるものを選ぶ
             let sqrt3 = 3.0_f32.sqrt();
@@ -576,6 +573,7 @@ fn handle_spiral_zoom_and_pan(
 }
 
 /// スパイラルのセルを描画し、可視セルと素数セルの数を返す
+#[allow(clippy::too_many_arguments)]
 fn draw_spiral_cells(
     painter: &egui::Painter,
     rect: egui::Rect,
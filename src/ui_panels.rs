@@ -7,7 +7,7 @@
 use eframe::egui;
 
 use crate::app::{AppTab, MyApp};
-use crate::config::{OutputFormat, WheelType};
+use crate::config::{OutputFormat, SieveEngineKind, WheelType};
 use crate::ui_components::{field_label, section_title, styled_text_edit};
 use crate::ui_panel_density::render_density_panel;
 use crate::ui_panel_explore::render_explore_panel;
@@ -105,7 +105,7 @@ fn render_header_buttons(app: &mut MyApp, ui: &mut egui::Ui) {
         ui.add_space(8.0);
     }
 
-    // Run / Stop ボタン
+    // Run / Stop（+ Pause / Resume）ボタン
     if !app.is_running {
         if ui
             .add(
@@ -124,16 +124,38 @@ fn render_header_buttons(app: &mut MyApp, ui: &mut egui::Ui) {
                 AppTab::Spiral => app.start_spiral(),
             }
         }
-    } else if ui
-        .add(
-            egui::Button::new(egui::RichText::new("Stop").color(egui::Color32::WHITE))
-                .fill(colors::DANGER)
-                .min_size(run_button_size),
-        )
-        .clicked()
-    {
-        app.stop_flag
-            .store(true, std::sync::atomic::Ordering::SeqCst);
+    } else {
+        if ui
+            .add(
+                egui::Button::new(egui::RichText::new("Stop").color(egui::Color32::WHITE))
+                    .fill(colors::DANGER)
+                    .min_size(run_button_size),
+            )
+            .clicked()
+        {
+            app.stop_flag
+                .store(true, std::sync::atomic::Ordering::SeqCst);
+            // 一時停止中でも待機を解いて停止させる
+            app.pause_flag.resume();
+        }
+        ui.add_space(8.0);
+
+        // 一時停止中のワーカーは状態を保ったまま待ち、Resume でその続きから再開する
+        let pause_label = if app.pause_flag.is_paused() {
+            "Resume"
+        } else {
+            "Pause"
+        };
+        if ui
+            .add(egui::Button::new(pause_label).min_size(button_size))
+            .clicked()
+        {
+            if app.pause_flag.is_paused() {
+                app.pause_flag.resume();
+            } else {
+                app.pause_flag.pause();
+            }
+        }
     }
 }
 
@@ -197,6 +219,8 @@ pub fn render_advanced_options_window(app: &mut MyApp, ctx: &egui::Context) {
 /// Advanced Options のフィールド群を描画
 fn render_advanced_options_fields(app: &mut MyApp, ui: &mut egui::Ui) {
     let input_height = 32.0;
+    // 単一スレッドのエンジン（Reference / Atkin）が使わない項目は無効にする
+    let segmented = app.selected_engine == SieveEngineKind::Segmented;
 
     // Split Count
     ui.label(field_label("Split Count"));
@@ -207,6 +231,24 @@ fn render_advanced_options_fields(app: &mut MyApp, ui: &mut egui::Ui) {
     );
     ui.add_space(12.0);
 
+    // Split Width
+    ui.label(field_label("Split Width (0 = off)"));
+    ui.add_space(4.0);
+    ui.add_sized(
+        [ui.available_width(), input_height],
+        styled_text_edit(&mut app.split_width_input),
+    );
+    ui.add_space(12.0);
+
+    // Index Stride
+    ui.label(field_label("Index Stride (0 = none)"));
+    ui.add_space(4.0);
+    ui.add_sized(
+        [ui.available_width(), input_height],
+        styled_text_edit(&mut app.index_stride_input),
+    );
+    ui.add_space(12.0);
+
     // Segment Size
     ui.label(field_label("Segment Size"));
     ui.add_space(4.0);
@@ -214,6 +256,17 @@ fn render_advanced_options_fields(app: &mut MyApp, ui: &mut egui::Ui) {
         [ui.available_width(), input_height],
         styled_text_edit(&mut app.segment_size_input),
     );
+    ui.add_space(4.0);
+    ui.add_enabled_ui(segmented, |ui| {
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut app.auto_segment_size, "");
+            ui.label(
+                egui::RichText::new("Auto (fit CPU cache)")
+                    .size(font_sizes::BODY)
+                    .color(colors::TEXT_PRIMARY),
+            );
+        });
+    });
     ui.add_space(12.0);
 
     // Buffer Size
@@ -225,6 +278,35 @@ fn render_advanced_options_fields(app: &mut MyApp, ui: &mut egui::Ui) {
     );
     ui.add_space(12.0);
 
+    ui.add_enabled_ui(segmented, |ui| {
+        // Pipeline Depth
+        ui.label(field_label("Pipeline Depth (0 = off)"));
+        ui.add_space(4.0);
+        ui.add_sized(
+            [ui.available_width(), input_height],
+            styled_text_edit(&mut app.pipeline_depth_input),
+        );
+        ui.add_space(12.0);
+
+        // Threads
+        ui.label(field_label("Threads (0 = all cores)"));
+        ui.add_space(4.0);
+        ui.add_sized(
+            [ui.available_width(), input_height],
+            styled_text_edit(&mut app.threads_input),
+        );
+        ui.add_space(12.0);
+
+        // Memory Ceiling
+        ui.label(field_label("Memory Ceiling MB (0 = none)"));
+        ui.add_space(4.0);
+        ui.add_sized(
+            [ui.available_width(), input_height],
+            styled_text_edit(&mut app.memory_ceiling_mb_input),
+        );
+    });
+    ui.add_space(12.0);
+
     // Format
     ui.label(field_label("Format"));
     ui.add_space(4.0);
@@ -235,6 +317,16 @@ fn render_advanced_options_fields(app: &mut MyApp, ui: &mut egui::Ui) {
             ui.selectable_value(&mut app.selected_format, OutputFormat::CSV, "CSV");
             ui.selectable_value(&mut app.selected_format, OutputFormat::JSON, "JSON");
             ui.selectable_value(&mut app.selected_format, OutputFormat::Binary, "Binary");
+            ui.selectable_value(
+                &mut app.selected_format,
+                OutputFormat::DeltaVarint,
+                "DeltaVarint (compressed)",
+            );
+            ui.selectable_value(
+                &mut app.selected_format,
+                OutputFormat::WheelBitmap,
+                "WheelBitmap (mod 30)",
+            );
         });
     ui.add_space(12.0);
 
@@ -251,9 +343,48 @@ fn render_advanced_options_fields(app: &mut MyApp, ui: &mut egui::Ui) {
                 WheelType::Mod30,
                 "Mod30 (Recommended)",
             );
+            ui.selectable_value(
+                &mut app.selected_wheel_type,
+                WheelType::Mod210,
+                "Mod210 (Large ranges)",
+            );
         });
     ui.add_space(12.0);
 
+    // Sieve Engine
+    ui.label(field_label("Sieve Engine"));
+    ui.add_space(4.0);
+    egui::ComboBox::new("sieve_engine", "")
+        .selected_text(format!("{:?}", app.selected_engine))
+        .show_ui(ui, |ui| {
+            ui.selectable_value(
+                &mut app.selected_engine,
+                SieveEngineKind::Segmented,
+                "Segmented (Recommended)",
+            );
+            ui.selectable_value(
+                &mut app.selected_engine,
+                SieveEngineKind::Reference,
+                "Reference (single-threaded)",
+            );
+            ui.selectable_value(
+                &mut app.selected_engine,
+                SieveEngineKind::Atkin,
+                "Atkin (single-threaded)",
+            );
+        });
+    if !segmented {
+        ui.add_space(4.0);
+        ui.label(
+            egui::RichText::new(
+                "Pipeline depth, threads, memory ceiling and auto segment size apply to the Segmented engine only",
+            )
+            .size(font_sizes::LABEL)
+            .color(colors::TEXT_SECONDARY),
+        );
+    }
+    ui.add_space(12.0);
+
     // Timestamp prefix option
     ui.horizontal(|ui| {
         ui.checkbox(&mut app.use_timestamp_prefix, "");
@@ -263,6 +394,28 @@ fn render_advanced_options_fields(app: &mut MyApp, ui: &mut egui::Ui) {
                 .color(colors::TEXT_PRIMARY),
         );
     });
+    ui.add_space(8.0);
+
+    // Resume from checkpoint option
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut app.resume, "");
+        ui.label(
+            egui::RichText::new("Resume from checkpoint if present")
+                .size(font_sizes::BODY)
+                .color(colors::TEXT_PRIMARY),
+        );
+    });
+    ui.add_space(8.0);
+
+    // Delete partial files on stop option
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut app.delete_partials_on_stop, "");
+        ui.label(
+            egui::RichText::new("Delete partial files on stop")
+                .size(font_sizes::BODY)
+                .color(colors::TEXT_PRIMARY),
+        );
+    });
 }
 
 /// メインパネル（タブに応じて Generator / Explore / Gap / Density / Spiral を描画）
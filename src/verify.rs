@@ -3,7 +3,9 @@ use std::fs::File;
 use std::io::{BufRead, BufReader, Read};
 use std::path::Path;
 
+use crate::delta_varint::DeltaVarintReader;
 use crate::engine_types::PrimeResult;
+use crate::wheel_bitmap::WheelBitmap;
 
 #[derive(Debug, Clone)]
 pub struct VerifyReport {
@@ -35,14 +37,14 @@ pub fn is_probable_prime(n: u64) -> bool {
     if n == 2 || n == 3 {
         return true;
     }
-    if n % 2 == 0 {
+    if n.is_multiple_of(2) {
         return false;
     }
 
     // n-1 = d * 2^s を求める
     let mut d = n - 1;
     let mut s = 0u32;
-    while d % 2 == 0 {
+    while d.is_multiple_of(2) {
         d /= 2;
         s += 1;
     }
@@ -97,6 +99,8 @@ fn mod_pow(mut base: u64, mut exp: u64, m: u64) -> u64 {
 ///
 /// - `.txt` / 拡張子なしなど: 1行1素数のテキストとして扱う
 /// - `.bin`: little-endian `u64` の連続バイナリとして扱う
+/// - `.dvar`: 差分 varint 形式（`DeltaVarintReader`）として扱う
+/// - `.w30`: mod 30 ビットマップ形式（`WheelBitmap`）として扱う
 ///
 /// 進捗・ログの契約:
 /// - `log_cb` が与えられている場合、テキスト/バイナリともに「約 100万件ごと」に進捗ログを出します。
@@ -111,6 +115,8 @@ pub fn verify_primes_file<P: AsRef<Path>>(
     let path_ref = path.as_ref();
     match path_ref.extension().and_then(|e| e.to_str()) {
         Some("bin") => verify_primes_binary_file(path_ref, sample_tail, log_cb),
+        Some("dvar") => verify_primes_delta_varint_file(path_ref, sample_tail, log_cb),
+        Some("w30") => verify_primes_wheel_bitmap_file(path_ref, sample_tail, log_cb),
         _ => verify_primes_text_file(path_ref, sample_tail, log_cb),
     }
 }
@@ -165,7 +171,7 @@ fn verify_primes_text_file(
             }
         }
 
-        if n != 2 && n % 2 == 0 {
+        if n != 2 && n.is_multiple_of(2) {
             return Err(
                 format!("Even composite candidate at line {line_no}: {n}")
                     .into(),
@@ -187,7 +193,7 @@ fn verify_primes_text_file(
         }
 
         // 進捗ログ（100万行ごと）
-        if line_no % LOG_INTERVAL == 0 {
+        if line_no.is_multiple_of(LOG_INTERVAL) {
             if let Some(ref mut cb) = log_cb {
                 cb(format!(
                     "Verified {line_no} lines (current value: {n})..."
@@ -231,7 +237,7 @@ fn verify_primes_text_file(
 fn verify_primes_binary_file(
     path: &Path,
     sample_tail: usize,
-    mut log_cb: Option<LogCallback>,
+    log_cb: Option<LogCallback>,
 ) -> PrimeResult<VerifyReport> {
     let file = File::open(path).map_err(|e| {
         if let Some(code) = e.raw_os_error() {
@@ -257,7 +263,42 @@ fn verify_primes_binary_file(
 
     let total_records = metadata.len() / 8;
     let mut reader = BufReader::with_capacity(8 * 1024 * 1024, file);
+    let records = (0..total_records).map(move |_| {
+        let mut buf = [0u8; 8];
+        reader.read_exact(&mut buf)?;
+        Ok(u64::from_le_bytes(buf))
+    });
+    verify_prime_values(records, sample_tail, log_cb)
+}
+
+fn verify_primes_delta_varint_file(
+    path: &Path,
+    sample_tail: usize,
+    log_cb: Option<LogCallback>,
+) -> PrimeResult<VerifyReport> {
+    let reader = DeltaVarintReader::open(path)
+        .map_err(|e| format!("Failed to open primes file {path:?}: {e}"))?;
+    verify_prime_values(reader, sample_tail, log_cb)
+}
+
+fn verify_primes_wheel_bitmap_file(
+    path: &Path,
+    sample_tail: usize,
+    log_cb: Option<LogCallback>,
+) -> PrimeResult<VerifyReport> {
+    let mut bitmap = WheelBitmap::open(path)
+        .map_err(|e| format!("Failed to open primes file {path:?}: {e}"))?;
+    verify_prime_values(bitmap.iter(), sample_tail, log_cb)
+}
 
+/// デコード済みの値の列を検証する（バイナリ / 差分 varint / ビットマップ形式で共用）。
+///
+/// - 単調増加・偶数の混入・末尾サンプルの Miller-Rabin をテキスト / バイナリと同じ基準で確認します。
+fn verify_prime_values(
+    values: impl Iterator<Item = std::io::Result<u64>>,
+    sample_tail: usize,
+    mut log_cb: Option<LogCallback>,
+) -> PrimeResult<VerifyReport> {
     let mut prev: Option<u64> = None;
     let mut index: u64 = 0;
     let mut min_val: Option<u64> = None;
@@ -267,16 +308,10 @@ fn verify_primes_binary_file(
 
     const LOG_INTERVAL: u64 = 1_000_000; // 100万レコードごとにログ
 
-    let mut buf = [0u8; 8];
-
-    while index < total_records {
-        reader
-            .read_exact(&mut buf)
-            .map_err(|e| format!("I/O error at record {}: {e}", index + 1))?;
+    for value in values {
+        let n = value.map_err(|e| format!("I/O error at record {}: {e}", index + 1))?;
         index += 1;
 
-        let n = u64::from_le_bytes(buf);
-
         if let Some(p) = prev {
             if n <= p {
                 return Err(
@@ -288,7 +323,7 @@ fn verify_primes_binary_file(
             }
         }
 
-        if n != 2 && n % 2 == 0 {
+        if n != 2 && n.is_multiple_of(2) {
             return Err(
                 format!(
                     "Even composite candidate at record {index}: {n}",
@@ -312,7 +347,7 @@ fn verify_primes_binary_file(
         }
 
         // 進捗ログ（100万レコードごと）
-        if index % LOG_INTERVAL == 0 {
+        if index.is_multiple_of(LOG_INTERVAL) {
             if let Some(ref mut cb) = log_cb {
                 cb(format!(
                     "Verified {index} records (current value: {n})...",
@@ -352,4 +387,3 @@ fn verify_primes_binary_file(
         max: max_val.unwrap(),
     })
 }
-
@@ -0,0 +1,482 @@
+//! mod 30 ホイールのビットマップ形式（`OutputFormat::WheelBitmap`）の素数ファイル。
+//!
+//! 篩と同じ mod 30 ホイール（`MOD30_PATTERN` = 1, 7, 11, 13, 17, 19, 23, 29）で、
+//! 30 個の整数を 1 バイト（8 ビット）で表します。10^9 までで約 33 MB（10^9 / 30 バイト）で、
+//! 任意の値の素数判定をファイル上のビット参照だけで行えます。
+//!
+//! ファイル構成（すべて little-endian）:
+//!
+//! | オフセット | サイズ | 内容 |
+//! |---|---|---|
+//! | 0  | 4 | マジック `b"SSWB"` |
+//! | 4  | 4 | 形式バージョン（`u32`） |
+//! | 8  | 4 | ホイールの周期（`u32`、常に 30） |
+//! | 12 | 4 | 予約（0） |
+//! | 16 | 8 | 範囲の開始値 `start`（`u64`） |
+//! | 24 | 8 | 範囲の終了値 `end`（`u64`、空なら `end < start`） |
+//! | 32 | - | ビットマップ本体 |
+//!
+//! - 本体の k バイト目は `[base + 30k, base + 30k + 29]`（`base = start - start % 30`）を表し、
+//!   ビット j（LSB が 0）が立っていれば `base + 30k + MOD30_PATTERN[j]` が素数です。
+//! - 2, 3, 5 はビットマップに現れないため、範囲に含まれていれば素数として扱います。
+//! - `end` は実際に篩い終えた位置です。停止した実行では生成範囲の終わりより手前になり、
+//!   その先を 0 で埋めることはしません。
+//!
+//! ファイルの配置は `Config::wheel_type` によらず常に mod 30 です。篩のセグメントのビット配列は
+//! ホイール（Odd / Mod6 / Mod30 / Mod210）ごとに配置が異なり、セグメントの開始位置も 30 の倍数に
+//! 揃っていないため、そのまま書き出すとホイールやセグメントサイズによってファイルの形式が変わってしまいます。
+//! そこで CPU エンジンは各セグメントのビット配列を mod 30 のバイト列に変換して
+//! `PrimeWriter::write_wheel_bits` で渡し（Mod30 ではビットをずらして詰めるだけ）、
+//! `WheelBitmapEncoder::push_blocks` がセグメント境界で重なるバイトを OR でまとめて書き出します。
+//! 素数を 1 個ずつ受け取る経路（他のエンジンやホイールで除外される素数）は `WheelBitmapEncoder::push` です。
+
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+/// ファイル先頭のマジック
+pub const WHEEL_BITMAP_MAGIC: [u8; 4] = *b"SSWB";
+/// 形式バージョン
+pub const WHEEL_BITMAP_VERSION: u32 = 1;
+/// ヘッダのバイト数
+pub const WHEEL_BITMAP_HEADER_LEN: u64 = 32;
+/// ヘッダ内で `start` が置かれるオフセット（`end` はその直後）
+pub const WHEEL_BITMAP_RANGE_OFFSET: u64 = 16;
+
+const WHEEL: u64 = 30;
+
+/// 1 バイト内のビット位置に対応する mod 30 の余り
+const MOD30_PATTERN: [u64; 8] = [1, 7, 11, 13, 17, 19, 23, 29];
+
+/// mod 30 の余りからビット位置へのマッピング（候補でない余りは 255）
+const MOD30_TO_BIT: [u8; 30] = [
+    255, 0, 255, 255, 255, 255, 255, 1, 255, 255, 255, 2, 255, 3, 255, 255, 255, 4, 255, 5, 255,
+    255, 255, 6, 255, 255, 255, 255, 255, 7,
+];
+
+/// ホイールで除外される小さい素数（ビットマップには含まれない）
+const WHEEL_PRIMES: [u64; 3] = [2, 3, 5];
+
+/// ビットマップファイルのヘッダ。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WheelBitmapHeader {
+    pub version: u32,
+    /// ホイールの周期（30）
+    pub wheel: u32,
+    /// 範囲の開始値（この値を含む）
+    pub start: u64,
+    /// 範囲の終了値（この値を含む）。`end < start` なら空
+    pub end: u64,
+}
+
+impl WheelBitmapHeader {
+    pub fn new(start: u64, end: u64) -> Self {
+        Self {
+            version: WHEEL_BITMAP_VERSION,
+            wheel: WHEEL as u32,
+            start,
+            end,
+        }
+    }
+
+    pub fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_all(&WHEEL_BITMAP_MAGIC)?;
+        w.write_all(&self.version.to_le_bytes())?;
+        w.write_all(&self.wheel.to_le_bytes())?;
+        w.write_all(&0u32.to_le_bytes())?;
+        w.write_all(&self.start.to_le_bytes())?;
+        w.write_all(&self.end.to_le_bytes())
+    }
+
+    pub fn read_from<R: Read>(r: &mut R) -> io::Result<Self> {
+        let mut buf = [0u8; WHEEL_BITMAP_HEADER_LEN as usize];
+        r.read_exact(&mut buf)?;
+        if buf[0..4] != WHEEL_BITMAP_MAGIC {
+            return Err(invalid_data("not a wheel bitmap primes file (bad magic)"));
+        }
+        let version = u32::from_le_bytes(buf[4..8].try_into().unwrap());
+        if version != WHEEL_BITMAP_VERSION {
+            return Err(invalid_data(format!(
+                "unsupported wheel bitmap version {version} (expected {WHEEL_BITMAP_VERSION})"
+            )));
+        }
+        let wheel = u32::from_le_bytes(buf[8..12].try_into().unwrap());
+        if wheel as u64 != WHEEL {
+            return Err(invalid_data(format!("unsupported wheel modulus {wheel}")));
+        }
+        Ok(Self {
+            version,
+            wheel,
+            start: u64::from_le_bytes(buf[16..24].try_into().unwrap()),
+            end: u64::from_le_bytes(buf[24..32].try_into().unwrap()),
+        })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.end < self.start
+    }
+
+    /// 本体のバイト数
+    pub fn body_len(&self) -> u64 {
+        if self.is_empty() {
+            0
+        } else {
+            self.end / WHEEL - self.start / WHEEL + 1
+        }
+    }
+}
+
+/// 昇順に届く素数からビットマップ本体を逐次書き出すエンコーダ（`FilePrimeWriter` 用）。
+///
+/// - 完成したバイトから順に書き出すため、保持するのは書きかけの 1 バイトだけです。
+#[derive(Debug, Clone, Default)]
+pub struct WheelBitmapEncoder {
+    /// ヘッダに書く開始値（最初の素数、または `start_hint`）
+    start: Option<u64>,
+    /// 書きかけのバイトが表すブロック番号（`n / 30`）
+    block: u64,
+    pending: u8,
+}
+
+impl WheelBitmapEncoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn begin(&mut self, start: u64) {
+        self.start = Some(start);
+        self.block = start / WHEEL;
+        self.pending = 0;
+    }
+
+    /// 書きかけのバイトを `block` まで進める（間のバイトは 0 で埋める）。
+    fn advance_to<W: Write>(&mut self, w: &mut W, block: u64) -> io::Result<()> {
+        while self.block < block {
+            w.write_all(&[self.pending])?;
+            self.pending = 0;
+            self.block += 1;
+        }
+        Ok(())
+    }
+
+    /// 素数 `p` を追加する。
+    ///
+    /// - `start_hint` は範囲の開始値（分かっていれば）。最初の呼び出しでのみ使われます。
+    pub fn push<W: Write>(&mut self, w: &mut W, p: u64, start_hint: Option<u64>) -> io::Result<()> {
+        if self.start.is_none() {
+            self.begin(start_hint.map_or(p, |s| s.min(p)));
+        }
+        let bit = MOD30_TO_BIT[(p % WHEEL) as usize];
+        if bit == 255 {
+            // 2, 3, 5 はヘッダの範囲から判断する
+            return Ok(());
+        }
+        self.advance_to(w, p / WHEEL)?;
+        self.pending |= 1 << bit;
+        Ok(())
+    }
+
+    /// 篩が mod 30 の配置に変換したバイト列 `bytes`（ブロック `first_block` から）を追加する。
+    ///
+    /// - 先頭のバイトが書きかけのバイトと同じブロックなら OR でまとめます。
+    ///   最後のバイトは次の書き込みと重なりうるので、書きかけのまま残します。
+    /// - `start_hint` は `push` と同じく、最初の呼び出しでのみ使われます。
+    pub fn push_blocks<W: Write>(
+        &mut self,
+        w: &mut W,
+        first_block: u64,
+        bytes: &[u8],
+        start_hint: Option<u64>,
+    ) -> io::Result<()> {
+        if self.start.is_none() {
+            // 範囲の開始値が分からなければ、最初に立っているビットの値から始める
+            let first = bytes.iter().position(|&b| b != 0).map(|k| {
+                (first_block + k as u64) * WHEEL + MOD30_PATTERN[bytes[k].trailing_zeros() as usize]
+            });
+            match start_hint.into_iter().chain(first).min() {
+                Some(start) => self.begin(start),
+                // まだ素数も範囲も無い
+                None => return Ok(()),
+            }
+        }
+        // 開始値のブロックより前（最初の素数より手前の 0 のバイト）は読み飛ばす
+        let skip = self
+            .block
+            .saturating_sub(first_block)
+            .min(bytes.len() as u64);
+        let (first_block, bytes) = (first_block + skip, &bytes[skip as usize..]);
+        let Some((&last, rest)) = bytes.split_last() else {
+            return Ok(());
+        };
+
+        self.advance_to(w, first_block)?;
+        if let Some((&head, middle)) = rest.split_first() {
+            w.write_all(&[self.pending | head])?;
+            w.write_all(middle)?;
+            self.pending = last;
+        } else {
+            self.pending |= last;
+        }
+        self.block = first_block + rest.len() as u64;
+        Ok(())
+    }
+
+    /// 残りのバイトを `end`（篩い終えた位置）まで書き出し、ヘッダに書く `(start, end)` を返す。
+    pub fn finish<W: Write>(
+        &mut self,
+        w: &mut W,
+        start_hint: Option<u64>,
+        end: u64,
+    ) -> io::Result<(u64, u64)> {
+        let start = match (self.start, start_hint) {
+            (Some(start), _) => start,
+            (None, Some(start)) if start <= end => {
+                self.begin(start);
+                start
+            }
+            // 素数も範囲も無い空のファイル
+            _ => return Ok((1, 0)),
+        };
+        let end = end.max(start);
+        self.advance_to(w, end / WHEEL)?;
+        w.write_all(&[self.pending])?;
+        self.pending = 0;
+        self.block += 1;
+        self.start = None;
+        Ok((start, end))
+    }
+}
+
+/// 個数・列挙で本体を読み込むときの 1 回あたりのバイト数
+const READ_CHUNK: usize = 64 * 1024;
+
+/// ビットマップファイルを開いたもの。素数判定・個数・列挙を提供する。
+///
+/// - 開くときに読み込むのはヘッダだけです。本体はファイルに置いたまま、
+///   必要なバイトの位置（`WHEEL_BITMAP_HEADER_LEN + n / 30 - start / 30`）へシークして読みます。
+/// - 読み込みでファイル位置が動くため、参照するメソッドは `&mut self` を取ります。
+#[derive(Debug)]
+pub struct WheelBitmap<R = File> {
+    header: WheelBitmapHeader,
+    reader: R,
+}
+
+impl WheelBitmap<File> {
+    /// ファイルを開き、ヘッダを読み込む。
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::from_reader(File::open(path)?)
+    }
+}
+
+impl<R: Read + Seek> WheelBitmap<R> {
+    /// 先頭にヘッダのあるビットマップを読む。本体の長さがヘッダの範囲と合わなければエラー。
+    pub fn from_reader(mut reader: R) -> io::Result<Self> {
+        reader.seek(SeekFrom::Start(0))?;
+        let header = WheelBitmapHeader::read_from(&mut reader)?;
+        let body_len = reader.seek(SeekFrom::End(0))? - WHEEL_BITMAP_HEADER_LEN;
+        if body_len != header.body_len() {
+            return Err(invalid_data(format!(
+                "bitmap body is {} bytes, header range [{}, {}] needs {}",
+                body_len,
+                header.start,
+                header.end,
+                header.body_len()
+            )));
+        }
+        Ok(Self { header, reader })
+    }
+
+    pub fn header(&self) -> &WheelBitmapHeader {
+        &self.header
+    }
+
+    /// ファイルが表す範囲に `n` が含まれるか
+    pub fn contains(&self, n: u64) -> bool {
+        self.header.start <= n && n <= self.header.end
+    }
+
+    /// 本体の `k` バイト目から `buf.len()` バイトを読む。
+    fn read_body(&mut self, k: u64, buf: &mut [u8]) -> io::Result<()> {
+        self.reader
+            .seek(SeekFrom::Start(WHEEL_BITMAP_HEADER_LEN + k))?;
+        self.reader.read_exact(buf)
+    }
+
+    /// `n` が素数かどうか。範囲外なら `None`。
+    pub fn is_prime(&mut self, n: u64) -> io::Result<Option<bool>> {
+        if !self.contains(n) {
+            return Ok(None);
+        }
+        if WHEEL_PRIMES.contains(&n) {
+            return Ok(Some(true));
+        }
+        let bit = MOD30_TO_BIT[(n % WHEEL) as usize];
+        if bit == 255 {
+            return Ok(Some(false));
+        }
+        let mut byte = [0u8];
+        self.read_body(n / WHEEL - self.header.start / WHEEL, &mut byte)?;
+        Ok(Some(byte[0] & (1 << bit) != 0))
+    }
+
+    /// `[a, b]` の素数の個数。範囲外の部分は数えない。
+    pub fn count(&mut self, a: u64, b: u64) -> io::Result<u64> {
+        let a = a.max(self.header.start);
+        let b = b.min(self.header.end);
+        if a > b {
+            return Ok(0);
+        }
+
+        let small = WHEEL_PRIMES.iter().filter(|&&p| a <= p && p <= b).count() as u64;
+        let base_block = self.header.start / WHEEL;
+        let first = a / WHEEL - base_block;
+        let last = b / WHEEL - base_block;
+
+        // 本体を `READ_CHUNK` バイトずつ読み、両端のバイトは範囲外のビットを落として数える
+        let mut total = 0;
+        let mut buf = vec![0u8; READ_CHUNK.min((last - first + 1) as usize)];
+        let mut k = first;
+        while k <= last {
+            let chunk = &mut buf[..READ_CHUNK.min((last - k + 1) as usize)];
+            self.read_body(k, chunk)?;
+            if k == first {
+                chunk[0] &= mask_from(a % WHEEL);
+            }
+            k += chunk.len() as u64;
+            if k > last {
+                chunk[chunk.len() - 1] &= mask_to(b % WHEEL);
+            }
+            total += count_ones(chunk);
+        }
+        Ok(small + total)
+    }
+
+    /// 範囲内のすべての素数を昇順に列挙する。
+    pub fn iter(&mut self) -> impl Iterator<Item = io::Result<u64>> + '_ {
+        let (start, end) = (self.header.start, self.header.end);
+        self.iter_range(start, end)
+    }
+
+    /// `[a, b]` の素数を昇順に列挙する。範囲外の部分は含まない。
+    ///
+    /// - 本体は `READ_CHUNK` バイトずつ読み込みます。読み込みに失敗したらエラーを 1 回返して終わります。
+    pub fn iter_range(&mut self, a: u64, b: u64) -> impl Iterator<Item = io::Result<u64>> + '_ {
+        let a = a.max(self.header.start);
+        let b = b.min(self.header.end);
+        let base_block = self.header.start / WHEEL;
+        let blocks = if a <= b {
+            (a / WHEEL - base_block)..(b / WHEEL - base_block + 1)
+        } else {
+            0..0
+        };
+
+        let small = WHEEL_PRIMES
+            .into_iter()
+            .filter(move |&p| a <= p && p <= b)
+            .map(Ok);
+        let wheel = BodyPrimes {
+            bitmap: self,
+            range: (a, b),
+            blocks,
+            buf: Vec::new(),
+            buf_block: 0,
+            pos: 0,
+            byte: 0,
+        };
+        small.chain(wheel)
+    }
+}
+
+/// `WheelBitmap::iter_range` で、本体のバイトを `READ_CHUNK` バイトずつ読みながら素数を列挙する。
+struct BodyPrimes<'a, R> {
+    bitmap: &'a mut WheelBitmap<R>,
+    /// 列挙する値の範囲 `[a, b]`
+    range: (u64, u64),
+    /// まだ読み込んでいない本体のバイト位置
+    blocks: std::ops::Range<u64>,
+    buf: Vec<u8>,
+    /// `buf[0]` の本体でのバイト位置
+    buf_block: u64,
+    /// 次に取り出す `buf` の位置
+    pos: usize,
+    /// 取り出し中のバイトの、まだ列挙していないビット
+    byte: u8,
+}
+
+impl<R: Read + Seek> Iterator for BodyPrimes<'_, R> {
+    type Item = io::Result<u64>;
+
+    fn next(&mut self) -> Option<io::Result<u64>> {
+        let (a, b) = self.range;
+        let base_block = self.bitmap.header.start / WHEEL;
+        loop {
+            if self.byte != 0 {
+                let bit = self.byte.trailing_zeros() as usize;
+                self.byte &= self.byte - 1;
+                let block = base_block + self.buf_block + self.pos as u64 - 1;
+                match (block * WHEEL).checked_add(MOD30_PATTERN[bit]) {
+                    Some(n) if a <= n && n <= b => return Some(Ok(n)),
+                    _ => continue,
+                }
+            }
+
+            if self.pos == self.buf.len() {
+                if self.blocks.is_empty() {
+                    return None;
+                }
+                let k = self.blocks.start;
+                let len = READ_CHUNK.min((self.blocks.end - k) as usize);
+                self.buf.resize(len, 0);
+                if let Err(e) = self.bitmap.read_body(k, &mut self.buf) {
+                    // 読み込みに失敗したらエラーを 1 回返して終わる
+                    self.blocks = 0..0;
+                    self.buf.clear();
+                    self.pos = 0;
+                    return Some(Err(e));
+                }
+                self.blocks.start += len as u64;
+                self.buf_block = k;
+                self.pos = 0;
+            }
+            self.byte = self.buf[self.pos];
+            self.pos += 1;
+        }
+    }
+}
+
+/// バイト列の立っているビットの数
+fn count_ones(bytes: &[u8]) -> u64 {
+    let mut chunks = bytes.chunks_exact(8);
+    let mut total = 0;
+    for chunk in &mut chunks {
+        total += u64::from_le_bytes(chunk.try_into().unwrap()).count_ones() as u64;
+    }
+    total
+        + chunks
+            .remainder()
+            .iter()
+            .map(|b| b.count_ones() as u64)
+            .sum::<u64>()
+}
+
+/// 余りが `r` 以上の候補のビットマスク
+fn mask_from(r: u64) -> u8 {
+    MOD30_PATTERN
+        .iter()
+        .enumerate()
+        .filter(|&(_, &res)| res >= r)
+        .fold(0u8, |m, (bit, _)| m | (1 << bit))
+}
+
+/// 余りが `r` 以下の候補のビットマスク
+fn mask_to(r: u64) -> u8 {
+    MOD30_PATTERN
+        .iter()
+        .enumerate()
+        .filter(|&(_, &res)| res <= r)
+        .fold(0u8, |m, (bit, _)| m | (1 << bit))
+}
+
+fn invalid_data(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
@@ -1,13 +1,31 @@
 //! バックグラウンド処理の補助関数。
 //!
-//! 現在このモジュールで利用されているのは、UI にメモリ使用量を送る
-//! `start_resource_monitor` です。
+//! - UI にメモリ使用量を送る `start_resource_monitor`
+//! - Generator タブと CLI の `generate` サブコマンドが共有する素数生成ジョブ `run_generate_job`
+//!   （セグメントグループごとのチェックポイント保存と `Config::resume` による途中再開を含む）
+//!   と、その一時停止対応版 `run_generate_job_pausable`
 
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc;
 
-use crate::worker_message::WorkerMessage;
+use chrono::Local;
+use serde::Serialize;
 
-/// メモリ使用量を 500ms ごとにポーリングし、`WorkerMessage::MemUsage` として送信する。
+use crate::checkpoint::{config_hash, Checkpoint};
+use crate::config::{Config, ConfigSources};
+use crate::engine_types::{PauseFlag, PrimeResult, Progress};
+use crate::memory::ProcessMemory;
+use crate::output::{
+    FilePrimeWriter, FileSplit, LastPrimeWriter, OutputMetadata, PrimeWriter, RunStatus,
+    PARTIAL_SUFFIX,
+};
+use crate::prime_pi_engine::{compute_prime_pi, PRIMECOUNT_MODE, PRIMECOUNT_VERSION};
+use crate::sieve_engine::{engine_for, ignored_settings, EngineCallbacks};
+use crate::verify::{verify_primes_file, LogCallback};
+use crate::worker_message::{format_eta, WorkerMessage};
+
+/// メモリ使用量を 500ms ごとにポーリングし、システム全体の使用量を `WorkerMessage::MemUsage`、
+/// このプロセスの RSS を `WorkerMessage::ProcessMemUsage` として送信する。
 ///
 /// - このスレッドはメインの計算とは独立して動作し、UI の「Memory Usage」表示を更新します。
 /// - sender 側がドロップされた場合（計算終了・画面クローズなど）はループを終了します。
@@ -15,20 +33,755 @@ pub fn start_resource_monitor(
     sender: mpsc::Sender<WorkerMessage>,
 ) -> std::thread::JoinHandle<()> {
     std::thread::spawn(move || {
-        let mut sys = sysinfo::System::new_all();
-        sys.refresh_memory();
+        let mut process = ProcessMemory::new();
 
         loop {
             std::thread::sleep(std::time::Duration::from_millis(500));
-            sys.refresh_memory();
-
-            let mem_usage = sys.used_memory();
 
+            let mem_usage = process.system_used();
             if sender.send(WorkerMessage::MemUsage(mem_usage)).is_err() {
                 break;
             }
+            if let Some(rss) = process.rss() {
+                if sender.send(WorkerMessage::ProcessMemUsage(rss)).is_err() {
+                    break;
+                }
+            }
         }
     })
 }
 
+/// `run_generate_job` の結果（CLI の終了コード判定などに使用）。
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct GenerateOutcome {
+    /// 篩で検出した素数の総数。
+    pub total_primes: u64,
+    /// 最後の素数（Last Prime Only モードのみ）。
+    pub last_prime: Option<u64>,
+    /// π(x) との一致結果。primecount の計算に失敗した場合は None。
+    pub pi_x_verified: Option<bool>,
+    /// 出力ファイルの自動検証結果。検証をスキップした場合は None。
+    pub file_verified: Option<bool>,
+}
+
+/// Generator の素数生成ジョブ本体。
+///
+/// - `cfg.last_prime_only` に応じて `LastPrimeWriter` / `FilePrimeWriter` を使い分け、
+///   π(x) 照合・`primes.meta.txt` / `primes.manifest.json` の出力・バイナリ出力の自動検証までを行います。
+/// - 進捗・ETA・ログはすべて `sender` 経由の `WorkerMessage` として通知します。
+/// - `config_sources` が与えられた場合、各設定値の出どころをメタレポートに記録します（CLI・GUI とも）。
+/// - セグメントグループごとに出力フォルダへチェックポイントを保存し、正常終了時に削除します。
+///   `cfg.resume` が有効でチェックポイントがあれば、その位置から再開します。
+/// - 素数ファイルは `*.partial` に書き込み、最後まで生成できた場合にだけ本来の名前へ変更します。
+///   メタレポートには実行の終わり方（complete / stopped / failed）を記録します。
+/// - `Done` / `Stopped` の送信は呼び出し側（GUI のワーカースレッド / CLI）の責務です。
+pub fn run_generate_job(
+    cfg: &Config,
+    config_sources: Option<&ConfigSources>,
+    stop_flag: &AtomicBool,
+    sender: &mpsc::Sender<WorkerMessage>,
+) -> PrimeResult<GenerateOutcome> {
+    run_generate_job_pausable(cfg, config_sources, stop_flag, &PauseFlag::new(), sender)
+}
+
+/// 一時停止に対応した `run_generate_job`。
+///
+/// - `pause_flag` が立っている間、エンジンは状態を保ったまま待ちます（`SieveEngine::generate`）。
+/// - ジョブの実行中は一時停止状態の変化を監視し、`WorkerMessage::Paused` として通知します。
+pub fn run_generate_job_pausable(
+    cfg: &Config,
+    config_sources: Option<&ConfigSources>,
+    stop_flag: &AtomicBool,
+    pause_flag: &PauseFlag,
+    sender: &mpsc::Sender<WorkerMessage>,
+) -> PrimeResult<GenerateOutcome> {
+    let job_done = AtomicBool::new(false);
+    std::thread::scope(|scope| {
+        scope.spawn(|| forward_pause_state(pause_flag, &job_done, sender));
+        let result = generate_job(cfg, config_sources, stop_flag, pause_flag, sender);
+        job_done.store(true, Ordering::SeqCst);
+        result
+    })
+}
+
+/// `job_done` が立つまで `pause_flag` をポーリングし、状態が変わるたびに `WorkerMessage::Paused` を送る。
+fn forward_pause_state(
+    pause_flag: &PauseFlag,
+    job_done: &AtomicBool,
+    sender: &mpsc::Sender<WorkerMessage>,
+) {
+    let mut paused = false;
+    while !job_done.load(Ordering::SeqCst) {
+        if pause_flag.is_paused() != paused {
+            paused = !paused;
+            if sender.send(WorkerMessage::Paused(paused)).is_err() {
+                break;
+            }
+        }
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    }
+    // 一時停止中に停止された場合も、UI 側の表示を戻しておく
+    if paused {
+        sender.send(WorkerMessage::Paused(false)).ok();
+    }
+}
+
+fn generate_job(
+    cfg: &Config,
+    config_sources: Option<&ConfigSources>,
+    stop_flag: &AtomicBool,
+    pause_flag: &PauseFlag,
+    sender: &mpsc::Sender<WorkerMessage>,
+) -> PrimeResult<GenerateOutcome> {
+    let mut outcome = GenerateOutcome::default();
+    let engine = engine_for(cfg.engine);
+    let ignored = ignored_settings(cfg);
+    if !ignored.is_empty() {
+        sender
+            .send(WorkerMessage::Log(format!(
+                "{} is ignored by the {:?} engine",
+                ignored.join(" / "),
+                cfg.engine
+            )))
+            .ok();
+    }
+
+    if cfg.last_prime_only {
+        // 最後の素数だけモード: ファイル書き出し無し（CPU 専用）
+        let checkpoint = load_resume_checkpoint(cfg, sender)?;
+        let mut writer = match checkpoint {
+            Some(ref cp) => LastPrimeWriter::from_state(&cp.writer),
+            None => LastPrimeWriter::new(),
+        };
+
+        let mut progress = ProgressForwarder::new(sender);
+
+        sender
+            .send(WorkerMessage::Log(format!(
+                "Using {} - Last Prime Only Mode",
+                engine.name()
+            )))
+            .ok();
+        let sieve_start = std::time::Instant::now();
+        // メタレポートのファイル名プレフィックス（チェックポイントはプレフィックス無しのまま）
+        let report_prefix = cfg
+            .use_timestamp_prefix
+            .then(|| Local::now().format("%Y%m%d_%H%M%S_").to_string());
+        let generated = engine.generate(
+            cfg,
+            stop_flag,
+            pause_flag,
+            &mut writer,
+            EngineCallbacks {
+                progress: &mut |p| progress.forward(p),
+                on_group_done: &mut checkpoint_saver(cfg, None),
+            },
+            checkpoint.map(|cp| cp.next_seg_start),
+        );
+        if let Err(e) = generated {
+            let metadata = run_metadata(
+                cfg,
+                writer.total_primes_written(),
+                RunStatus::Failed,
+                false,
+                sieve_start.elapsed().as_millis() as u64,
+                config_sources,
+                engine.name(),
+            );
+            write_reports(&metadata, cfg, report_prefix.as_deref(), sender);
+            return Err(e);
+        }
+        finish_checkpoint(cfg, None, stop_flag, sender);
+        let status = if stop_flag.load(Ordering::SeqCst) {
+            RunStatus::Stopped
+        } else {
+            RunStatus::Complete
+        };
+
+        progress.finish();
+
+        // 最後の素数を表示
+        outcome.last_prime = writer.get_last_prime();
+        if let Some(last) = writer.get_last_prime() {
+            sender
+                .send(WorkerMessage::Log(format!("Last prime found: {last}")))
+                .ok();
+        } else {
+            sender
+                .send(WorkerMessage::Log(
+                    "No primes found in range.".to_string(),
+                ))
+                .ok();
+        }
+
+        // 検出した素数の総数と prime_pi によるカウントをログ出力・検証
+        let total_primes = writer.total_primes_written();
+        outcome.total_primes = total_primes;
+        sender
+            .send(WorkerMessage::Log(format!(
+                "total primes found : {total_primes}"
+            )))
+            .ok();
+
+        // 停止した場合は範囲の途中までしか無いので、π(x) とは照合しない
+        if status == RunStatus::Complete {
+            outcome.pi_x_verified = check_prime_count(cfg, total_primes, sender);
+        }
+
+        // メタデータファイルを出力（出力ファイルは無いので件数と終わり方だけを記録する）
+        let metadata = run_metadata(
+            cfg,
+            total_primes,
+            status,
+            outcome.pi_x_verified == Some(true),
+            sieve_start.elapsed().as_millis() as u64,
+            config_sources,
+            engine.name(),
+        );
+        write_reports(&metadata, cfg, report_prefix.as_deref(), sender);
+    } else {
+        // 通常モード: ファイルに書き出す（CPU 専用）
+        let sieve_start = std::time::Instant::now();
+
+        let checkpoint = load_resume_checkpoint(cfg, sender)?;
+
+        // ファイル名プレフィックス（オプション。再開時はチェックポイントのものを引き継ぐ）
+        let timestamp_prefix = if let Some(ref cp) = checkpoint {
+            cp.timestamp_prefix.clone()
+        } else if cfg.use_timestamp_prefix {
+            Some(Local::now().format("%Y%m%d_%H%M%S_").to_string())
+        } else {
+            None
+        };
+
+        let split = FileSplit::from_config(cfg);
+        let mut writer = match checkpoint {
+            Some(ref cp) => FilePrimeWriter::resume_with_split(
+                &cfg.output_dir,
+                cfg.output_format,
+                split,
+                cfg.writer_buffer_size,
+                timestamp_prefix.clone(),
+                &cp.writer,
+            )?,
+            None => FilePrimeWriter::with_split(
+                &cfg.output_dir,
+                cfg.output_format,
+                split,
+                cfg.writer_buffer_size,
+                timestamp_prefix.clone(),
+            )?,
+        };
+        writer.set_value_range(cfg.prime_min, cfg.prime_max);
+        if cfg.output_format == crate::config::OutputFormat::WheelBitmap && split != FileSplit::None {
+            sender
+                .send(WorkerMessage::Log(
+                    "split_count / split_width is ignored for WheelBitmap output".to_string(),
+                ))
+                .ok();
+        }
+        if cfg.index_stride > 0 && !writer.enable_index(cfg.index_stride)? {
+            sender
+                .send(WorkerMessage::Log(format!(
+                    "index_stride is ignored for {:?} output",
+                    cfg.output_format
+                )))
+                .ok();
+        }
+
+        let mut progress = ProgressForwarder::new(sender);
+
+        sender
+            .send(WorkerMessage::Log(format!("Using {}", engine.name())))
+            .ok();
+        let generated = engine.generate(
+            cfg,
+            stop_flag,
+            pause_flag,
+            &mut writer,
+            EngineCallbacks {
+                progress: &mut |p| progress.forward(p),
+                on_group_done: &mut checkpoint_saver(cfg, timestamp_prefix.clone()),
+            },
+            checkpoint.map(|cp| cp.next_seg_start),
+        );
+        let settled = generated.and_then(|()| {
+            settle_output(
+                cfg,
+                timestamp_prefix.as_deref(),
+                stop_flag,
+                &mut writer,
+                sender,
+            )
+        });
+        let status = match settled {
+            Ok(status) => status,
+            Err(e) => {
+                // 生成か確定（リネーム）に失敗した。残ったファイルとチェックポイントはそのままにし、
+                // 失敗したことをメタレポートに記録する
+                sender
+                    .send(WorkerMessage::Log(format!(
+                        "Run failed; partial output files ({PARTIAL_SUFFIX}) kept"
+                    )))
+                    .ok();
+                let metadata = output_metadata(
+                    cfg,
+                    &writer,
+                    RunStatus::Failed,
+                    false,
+                    sieve_start.elapsed().as_millis() as u64,
+                    config_sources,
+                    engine.name(),
+                );
+                write_reports(&metadata, cfg, timestamp_prefix.as_deref(), sender);
+                return Err(e);
+            }
+        };
+        if let Some(index_path) = writer.index_path() {
+            sender
+                .send(WorkerMessage::Log(format!(
+                    "Index written to: {}",
+                    index_path.display()
+                )))
+                .ok();
+        }
+
+        progress.finish();
+
+        // ファイルに書き出した素数の総数と prime_pi によるカウントをログ出力・検証
+        let total_primes = writer.total_primes_written();
+        outcome.total_primes = total_primes;
+        sender
+            .send(WorkerMessage::Log(format!(
+                "total primes found : {total_primes}"
+            )))
+            .ok();
+
+        // 停止した場合は範囲の途中までしか無いので、π(x) との照合もファイル検証も行わない
+        if status == RunStatus::Complete {
+            outcome.pi_x_verified = check_prime_count(cfg, total_primes, sender);
+        }
+
+        // メタデータファイルを出力
+        let metadata = output_metadata(
+            cfg,
+            &writer,
+            status,
+            outcome.pi_x_verified == Some(true),
+            sieve_start.elapsed().as_millis() as u64,
+            config_sources,
+            engine.name(),
+        );
+        write_reports(&metadata, cfg, timestamp_prefix.as_deref(), sender);
+
+        // 自動ファイル検証
+        match cfg.output_format {
+            _ if status != RunStatus::Complete => {
+                sender
+                    .send(WorkerMessage::Log(
+                        "Skipping file verification: generation was stopped".to_string(),
+                    ))
+                    .ok();
+            }
+            crate::config::OutputFormat::Binary
+            | crate::config::OutputFormat::DeltaVarint
+            | crate::config::OutputFormat::WheelBitmap => {
+                if let Some(first_path) = writer.output_file_paths().first() {
+                    let path_str = first_path.to_string_lossy().to_string();
+
+                    sender
+                        .send(WorkerMessage::Log(format!(
+                            "Auto-verifying ({:?}): {path_str}",
+                            cfg.output_format
+                        )))
+                        .ok();
+
+                    let sender_clone = sender.clone();
+                    let log_cb: LogCallback = Box::new(move |msg: String| {
+                        sender_clone.send(WorkerMessage::Log(msg)).ok();
+                    });
+
+                    match verify_primes_file(&path_str, 100, Some(log_cb)) {
+                        Ok(report) => {
+                            outcome.file_verified = Some(true);
+                            sender
+                                .send(WorkerMessage::Log(format!(
+                                    "File verification OK: lines={}, min={}, max={}, tail_checked={}",
+                                    report.line_count, report.min, report.max, report.checked_tail
+                                )))
+                                .ok();
+                        }
+                        Err(e) => {
+                            outcome.file_verified = Some(false);
+                            sender
+                                .send(WorkerMessage::Log(format!(
+                                    "File verification FAILED: {e}"
+                                )))
+                                .ok();
+                        }
+                    }
+                } else {
+                    sender
+                        .send(WorkerMessage::Log(
+                            "Skipping file verification: no binary output file found"
+                                .to_string(),
+                        ))
+                        .ok();
+                }
+            }
+            _ => {
+                sender
+                    .send(WorkerMessage::Log(
+                        "Skipping file verification (only supported for binary formats)"
+                            .to_string(),
+                    ))
+                    .ok();
+            }
+        }
+    }
+
+    Ok(outcome)
+}
+
+/// `cfg.resume` が有効なら出力フォルダのチェックポイントを読み込む。
+///
+/// - チェックポイントが無ければ最初から生成する（`Ok(None)`）。
+/// - 設定（範囲・出力形式など）が一致するものが無い場合はエラーにする。
+///   一致するものが複数あれば、最も新しく保存されたものから再開する。
+/// - WheelBitmap 出力はチェックポイントを保存しないため、再開を指定するとエラーにする。
+fn load_resume_checkpoint(
+    cfg: &Config,
+    sender: &mpsc::Sender<WorkerMessage>,
+) -> PrimeResult<Option<Checkpoint>> {
+    if !cfg.resume {
+        return Ok(None);
+    }
+    if !cfg.last_prime_only && cfg.output_format == crate::config::OutputFormat::WheelBitmap {
+        return Err(
+            "Resume is not supported for WheelBitmap output; start a new run without resume".into(),
+        );
+    }
+
+    let hash = config_hash(cfg);
+    let checkpoints = Checkpoint::load_all(&cfg.output_dir, hash)?;
+    if checkpoints.is_empty() {
+        sender
+            .send(WorkerMessage::Log(
+                "No checkpoint found; starting from the beginning".to_string(),
+            ))
+            .ok();
+        return Ok(None);
+    }
+
+    let found: Vec<String> = checkpoints
+        .iter()
+        .map(|cp| cp.path(&cfg.output_dir).display().to_string())
+        .collect();
+    let mut matching: Vec<Checkpoint> = checkpoints
+        .into_iter()
+        .filter(|cp| cp.config_hash == hash)
+        .collect();
+    let Some(checkpoint) = matching.pop() else {
+        return Err(format!(
+            "No checkpoint matches the current settings (range / format / split / mode / wheel); \
+             restore the settings of the run to resume. Found: {}",
+            found.join(", ")
+        )
+        .into());
+    };
+    if !matching.is_empty() {
+        sender
+            .send(WorkerMessage::Log(format!(
+                "{} checkpoints match the current settings; resuming the most recent one",
+                matching.len() + 1
+            )))
+            .ok();
+    }
+
+    sender
+        .send(WorkerMessage::Log(format!(
+            "Resuming from checkpoint {} (saved {}): next segment starts at {}, {} primes already written",
+            checkpoint.path(&cfg.output_dir).display(),
+            checkpoint.saved_at,
+            checkpoint.next_seg_start,
+            checkpoint.writer.total_count
+        )))
+        .ok();
+    Ok(Some(checkpoint))
+}
+
+/// エンジンの進捗を UI へ転送する。
+///
+/// - ETA は直近 5 回の移動平均で平滑化します。
+struct ProgressForwarder<'a> {
+    sender: &'a mpsc::Sender<WorkerMessage>,
+    eta_history: Vec<u64>,
+    /// 最後に受け取った `Progress::total`（まだ受け取っていなければ 0）
+    last_total: u64,
+}
+
+impl<'a> ProgressForwarder<'a> {
+    fn new(sender: &'a mpsc::Sender<WorkerMessage>) -> Self {
+        Self {
+            sender,
+            eta_history: Vec::new(),
+            last_total: 0,
+        }
+    }
+
+    fn forward(&mut self, p: Progress) {
+        self.last_total = p.total;
+
+        let eta_str = if let Some(eta) = p.eta_secs {
+            self.eta_history.push(eta);
+            if self.eta_history.len() > 5 {
+                self.eta_history.remove(0);
+            }
+            let avg_eta = self.eta_history.iter().sum::<u64>() / self.eta_history.len() as u64;
+            format_eta(Some(avg_eta))
+        } else {
+            format_eta(None)
+        };
+
+        self.sender.send(WorkerMessage::Eta(eta_str)).ok();
+        self.sender
+            .send(WorkerMessage::Progress {
+                current: p.processed,
+                total: p.total,
+            })
+            .ok();
+    }
+
+    /// 生成を終えたら進捗を 100%、ETA を 0 にする（進捗を受け取っていなければ何もしない）。
+    fn finish(&self) {
+        if self.last_total > 0 {
+            self.sender
+                .send(WorkerMessage::Progress {
+                    current: self.last_total,
+                    total: self.last_total,
+                })
+                .ok();
+            self.sender.send(WorkerMessage::Eta(format_eta(Some(0)))).ok();
+        }
+    }
+}
+
+/// セグメントグループ完了ごとにチェックポイントを保存するコールバックを作る。
+fn checkpoint_saver(
+    cfg: &Config,
+    timestamp_prefix: Option<String>,
+) -> impl FnMut(u64, &mut dyn PrimeWriter) -> PrimeResult<()> + '_ {
+    move |next_seg_start, writer| {
+        if let Some(state) = writer.sync_state()? {
+            Checkpoint::new(cfg, next_seg_start, timestamp_prefix.clone(), state)
+                .save(&cfg.output_dir)?;
+        }
+        Ok(())
+    }
+}
+
+/// ファイル出力の生成後に、出力ファイルとチェックポイントを後始末して実行の終わり方を返す。
+///
+/// - 最後まで生成した場合は `*.partial` を本来のファイル名へ変更し、チェックポイントを削除する。
+/// - 停止された場合は `cfg.delete_partials_on_stop` に応じて、`*.partial` とチェックポイントを
+///   削除するか、再開用に残す。
+fn settle_output(
+    cfg: &Config,
+    timestamp_prefix: Option<&str>,
+    stop_flag: &AtomicBool,
+    writer: &mut FilePrimeWriter,
+    sender: &mpsc::Sender<WorkerMessage>,
+) -> PrimeResult<RunStatus> {
+    if !stop_flag.load(Ordering::SeqCst) {
+        writer.commit()?;
+        finish_checkpoint(cfg, timestamp_prefix, stop_flag, sender);
+        return Ok(RunStatus::Complete);
+    }
+
+    if cfg.delete_partials_on_stop {
+        writer.discard()?;
+        Checkpoint::remove(&cfg.output_dir, timestamp_prefix)?;
+        sender
+            .send(WorkerMessage::Log(format!(
+                "Stopped: partial output files ({PARTIAL_SUFFIX}) and checkpoint deleted"
+            )))
+            .ok();
+    } else {
+        finish_checkpoint(cfg, timestamp_prefix, stop_flag, sender);
+        sender
+            .send(WorkerMessage::Log(format!(
+                "Stopped: partial output files ({PARTIAL_SUFFIX}) kept"
+            )))
+            .ok();
+    }
+    Ok(RunStatus::Stopped)
+}
+
+/// primecount で区間 [prime_min, prime_max] の素数個数を求め、`total_primes` と照合する。
+///
+/// - π(x) の計算に失敗した場合はログに残して `None` を返します。
+fn check_prime_count(
+    cfg: &Config,
+    total_primes: u64,
+    sender: &mpsc::Sender<WorkerMessage>,
+) -> Option<bool> {
+    match (|| -> PrimeResult<u64> {
+        let pi_max = compute_prime_pi(cfg.prime_max)?;
+        let pi_before_min = if cfg.prime_min > 0 {
+            compute_prime_pi(cfg.prime_min - 1)?
+        } else {
+            0
+        };
+        Ok(pi_max.saturating_sub(pi_before_min))
+    })() {
+        Ok(pi_count) => {
+            sender
+                .send(WorkerMessage::Log(format!(
+                    "#primes π(x) = {pi_count}"
+                )))
+                .ok();
+            if total_primes == pi_count {
+                sender
+                    .send(WorkerMessage::Log(
+                        "Verification: OK - count matches π(x)".to_string()
+                    ))
+                    .ok();
+            } else {
+                sender
+                    .send(WorkerMessage::Log(format!(
+                        "Verification: MISMATCH - sieve={}, π(x)={}",
+                        total_primes, pi_count
+                    )))
+                    .ok();
+            }
+            Some(total_primes == pi_count)
+        }
+        Err(e) => {
+            sender
+                .send(WorkerMessage::Log(format!(
+                    "Error while computing π(x): {e}"
+                )))
+                .ok();
+            None
+        }
+    }
+}
+
+/// 実行結果をメタデータにまとめる（出力ファイルの情報は含まない）。
+fn run_metadata(
+    cfg: &Config,
+    total_primes: u64,
+    status: RunStatus,
+    pi_x_verified: bool,
+    elapsed_ms: u64,
+    config_sources: Option<&ConfigSources>,
+    engine_name: &str,
+) -> OutputMetadata {
+    let mut metadata = OutputMetadata::new(
+        (cfg.prime_min, cfg.prime_max),
+        total_primes,
+        pi_x_verified,
+        elapsed_ms,
+        Vec::new(),
+        Some(PRIMECOUNT_VERSION.to_string()),
+        Some(PRIMECOUNT_MODE.to_string()),
+    );
+    metadata.status = status;
+    metadata.config_sources = config_sources.cloned();
+    metadata.engine = Some(engine_name.to_string());
+    metadata
+}
+
+/// ファイル出力の実行結果をメタデータにまとめる。
+fn output_metadata(
+    cfg: &Config,
+    writer: &FilePrimeWriter,
+    status: RunStatus,
+    pi_x_verified: bool,
+    elapsed_ms: u64,
+    config_sources: Option<&ConfigSources>,
+    engine_name: &str,
+) -> OutputMetadata {
+    let mut metadata = run_metadata(
+        cfg,
+        writer.total_primes_written(),
+        status,
+        pi_x_verified,
+        elapsed_ms,
+        config_sources,
+        engine_name,
+    );
+    metadata.output_files = writer
+        .output_file_paths()
+        .iter()
+        .map(|p| p.to_string_lossy().to_string())
+        .collect();
+    metadata.files = writer.file_states().to_vec();
+    metadata
+}
+
+/// `primes.meta.txt` と `primes.manifest.json` を書き出し、結果をログに送る。
+fn write_reports(
+    metadata: &OutputMetadata,
+    cfg: &Config,
+    timestamp_prefix: Option<&str>,
+    sender: &mpsc::Sender<WorkerMessage>,
+) {
+    match metadata.write_to_file(&cfg.output_dir, cfg, timestamp_prefix) {
+        Ok(meta_path) => {
+            sender
+                .send(WorkerMessage::Log(format!(
+                    "Metadata written to: {}",
+                    meta_path.display()
+                )))
+                .ok();
+        }
+        Err(e) => {
+            sender
+                .send(WorkerMessage::Log(format!("Failed to write metadata: {e}")))
+                .ok();
+        }
+    }
+    match metadata.write_manifest(&cfg.output_dir, cfg, timestamp_prefix) {
+        Ok(manifest_path) => {
+            sender
+                .send(WorkerMessage::Log(format!(
+                    "Manifest written to: {}",
+                    manifest_path.display()
+                )))
+                .ok();
+        }
+        Err(e) => {
+            sender
+                .send(WorkerMessage::Log(format!("Failed to write manifest: {e}")))
+                .ok();
+        }
+    }
+}
 
+/// 生成終了後のチェックポイント処理。
+///
+/// - 最後まで処理した場合は削除し、停止された場合は再開用に残す。
+fn finish_checkpoint(
+    cfg: &Config,
+    timestamp_prefix: Option<&str>,
+    stop_flag: &AtomicBool,
+    sender: &mpsc::Sender<WorkerMessage>,
+) {
+    let path = Checkpoint::path_for(&cfg.output_dir, timestamp_prefix);
+    if stop_flag.load(Ordering::SeqCst) {
+        // WheelBitmap のようにチェックポイントを保存しない出力では何も残らない
+        if path.exists() {
+            sender
+                .send(WorkerMessage::Log(format!(
+                    "Checkpoint kept at {} (enable resume to continue)",
+                    path.display()
+                )))
+                .ok();
+        }
+    } else if let Err(e) = Checkpoint::remove(&cfg.output_dir, timestamp_prefix) {
+        sender
+            .send(WorkerMessage::Log(format!("Failed to remove checkpoint: {e}")))
+            .ok();
+    }
+}
@@ -14,17 +14,25 @@ use serde::{Deserialize, Serialize};
 /// - `Log`      : 任意のテキストログ。下部ログパネルに *新しいものが上* になるよう表示されます。
 /// - `Progress` : 全体に対する処理済み件数と総件数。プログレスバーと「Processed:」表示に使用されます。
 /// - `Eta`      : 人間に読みやすい ETA 文字列（例: `"12 min 3 sec"`）。`format_eta` で生成されます。
-/// - `MemUsage` : 現在のメモリ使用量（KB）。500ms ごとに `start_resource_monitor` から送信されます。
+/// - `MemUsage` : システム全体のメモリ使用量（バイト）。500ms ごとに `start_resource_monitor` から送信されます。
+/// - `ProcessMemUsage` : このプロセスの常駐メモリ（RSS、バイト）。`MemUsage` と同じタイミングで送信されます。
 /// - `Done`     : 正常完了を表し、UI 側で `is_running` を false にし、receiver を破棄します。
 /// - `Stopped`  : ユーザー操作による停止を表し、「Process stopped by user。」ログを残して終了します。
+/// - `Paused`   : 一時停止状態の変化（true = 一時停止した、false = 再開した）。UI の Pause / Resume 表示に使用されます。
+///
+/// CLI の `--json` モードでは、各メッセージを `{"type": "Progress", "data": {...}}` の形
+/// （データを持たないバリアントは `{"type": "Done"}`）で 1 行ずつ標準出力へ書き出します。
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", content = "data")]
 pub enum WorkerMessage {
     Log(String),
     Progress { current: u64, total: u64 },
     Eta(String),
     MemUsage(u64),
+    ProcessMemUsage(u64),
     Done,
     Stopped,
+    Paused(bool),
     /// Explore モード用: (x, π(x)) のデータポイント
     ExploreData { x: u64, pi_x: u64 },
     /// Gap モード用: 新しい素数とその直前の素数との差（ギャップ）
@@ -0,0 +1,163 @@
+#![cfg(not(windows))]
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use sosu_seisei_main2::config::{Config, SieveEngineKind, WheelType};
+use sosu_seisei_main2::cpu_engine::{
+    count_primes_sieve, generate_primes_cpu, generate_primes_cpu_resumable, use_bucket_sieve,
+};
+use sosu_seisei_main2::output::LastPrimeWriter;
+use sosu_seisei_main2::prime_pi_engine::compute_prime_pi;
+
+mod common;
+use common::{run, CollectWriter};
+
+/// √prime_max がセグメント幅（整数の個数）を超えるときだけバケット篩を使うことを確認する。
+#[test]
+fn bucket_sieve_is_selected_by_segment_span() {
+    assert!(use_bucket_sieve(100_000_000, 9_999));
+    assert!(!use_bucket_sieve(100_000_000, 10_000));
+    assert!(use_bucket_sieve(1 << 40, 1 << 19));
+    // セグメント幅が √prime_max 以上なら大きな篩素数は存在しない
+    assert!(!use_bucket_sieve(1 << 40, 1 << 20));
+}
+
+/// 参照エンジン（単純なエラトステネスの篩）で区間の素数を求める。
+fn reference_primes(prime_min: u64, prime_max: u64) -> Vec<u64> {
+    let cfg = Config {
+        prime_min,
+        prime_max,
+        engine: SieveEngineKind::Reference,
+        ..Config::default()
+    };
+    run(&cfg)
+}
+
+/// 小さいセグメントでバケット篩を通したとき、全ホイールで参照エンジンと同じ素数列になることを確認する。
+#[test]
+fn bucket_sieve_matches_reference_engine() {
+    for (prime_min, prime_max, segment_size) in
+        [(1, 3_000_000, 1_000), (999_000_000, 1_000_500_000, 4_099)]
+    {
+        assert!(use_bucket_sieve(prime_max, segment_size));
+        let expected = reference_primes(prime_min, prime_max);
+
+        for wheel_type in [
+            WheelType::Odd,
+            WheelType::Mod6,
+            WheelType::Mod30,
+            WheelType::Mod210,
+        ] {
+            let cfg = Config {
+                prime_min,
+                prime_max,
+                segment_size,
+                wheel_type,
+                ..Config::default()
+            };
+            assert_eq!(
+                run(&cfg),
+                expected,
+                "[{prime_min}, {prime_max}] {wheel_type:?}"
+            );
+        }
+    }
+}
+
+/// セグメント幅がホイールの周期より小さくても、ホイールの素数 2, 3, 5, 7 はバケットに入らず、
+/// Mod30 / Mod210 で参照エンジンと同じ素数列になることを確認する。
+#[test]
+fn segments_narrower_than_the_wheel_skip_wheel_primes() {
+    for (prime_min, prime_max) in [(1, 20_000), (1_000_000_000, 1_000_020_000)] {
+        let expected = reference_primes(prime_min, prime_max);
+        for segment_size in [1, 5, 16, 29] {
+            assert!(use_bucket_sieve(prime_max, segment_size));
+            for wheel_type in [WheelType::Mod30, WheelType::Mod210] {
+                let cfg = Config {
+                    prime_min,
+                    prime_max,
+                    segment_size,
+                    wheel_type,
+                    ..Config::default()
+                };
+                assert_eq!(
+                    run(&cfg),
+                    expected,
+                    "[{prime_min}, {prime_max}] segment {segment_size} {wheel_type:?}"
+                );
+            }
+        }
+    }
+
+    // 上限が遠くても、ホイールの素数の倍数を上限まで探し続けずに最初のグループを書き出す
+    for (segment_size, wheel_type) in [(1, WheelType::Mod30), (5, WheelType::Mod210)] {
+        let cfg = Config {
+            prime_min: 1,
+            prime_max: 100_000_000_000,
+            segment_size,
+            wheel_type,
+            ..Config::default()
+        };
+        let stop_flag = AtomicBool::new(false);
+        let mut collect = CollectWriter(Vec::new());
+        generate_primes_cpu_resumable(
+            &cfg,
+            &stop_flag,
+            &mut collect,
+            |_| {},
+            None,
+            |_, _| {
+                stop_flag.store(true, Ordering::SeqCst);
+                Ok(())
+            },
+        )
+        .unwrap();
+        let expected = reference_primes(1, collect.0.last().copied().unwrap());
+        assert_eq!(collect.0, expected, "segment {segment_size} {wheel_type:?}");
+    }
+}
+
+/// バケット篩が有効になる範囲で、全ホイールの結果が primecount・通常の篩と一致することを確認する。
+#[test]
+fn bucket_sieve_matches_reference_counts() {
+    let stop_flag = AtomicBool::new(false);
+    let prime_min = 100_000_000_123;
+    let prime_max = prime_min + 2_000_000;
+    let segment_size = 10_007;
+    assert!(use_bucket_sieve(prime_max, segment_size));
+
+    let expected_count =
+        compute_prime_pi(prime_max).unwrap() - compute_prime_pi(prime_min - 1).unwrap();
+    assert_eq!(
+        count_primes_sieve(prime_min, prime_max).unwrap(),
+        expected_count
+    );
+
+    for wheel_type in [
+        WheelType::Odd,
+        WheelType::Mod6,
+        WheelType::Mod30,
+        WheelType::Mod210,
+    ] {
+        let cfg = Config {
+            prime_min,
+            prime_max,
+            segment_size,
+            wheel_type,
+            ..Config::default()
+        };
+
+        let primes = run(&cfg);
+        assert_eq!(primes.len() as u64, expected_count, "{wheel_type:?}");
+        assert!(primes.windows(2).all(|w| w[0] < w[1]));
+
+        let mut last = LastPrimeWriter::new();
+        generate_primes_cpu(&cfg, &stop_flag, &mut last, |_| {}).unwrap();
+        assert_eq!(
+            last.total_primes_written(),
+            expected_count,
+            "{wheel_type:?}"
+        );
+        assert_eq!(last.get_last_prime(), primes.last().copied());
+    }
+}
@@ -0,0 +1,92 @@
+#![cfg(not(windows))]
+
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+
+use sosu_seisei_main2::config::{Config, WheelType};
+use sosu_seisei_main2::cpu_engine::generate_primes_cpu;
+use sosu_seisei_main2::memory::{calculate_cache_segment_size, read_cpu_cache_info, CpuCacheInfo};
+use sosu_seisei_main2::output::LastPrimeWriter;
+
+/// sysfs の `cache/indexN` ディレクトリを 1 つ作る
+fn write_cache_index(dir: &Path, index: usize, level: &str, kind: &str, size: &str, shared: &str) {
+    let index_dir = dir.join(format!("index{index}"));
+    std::fs::create_dir_all(&index_dir).unwrap();
+    std::fs::write(index_dir.join("level"), format!("{level}\n")).unwrap();
+    std::fs::write(index_dir.join("type"), format!("{kind}\n")).unwrap();
+    std::fs::write(index_dir.join("size"), format!("{size}\n")).unwrap();
+    std::fs::write(index_dir.join("shared_cpu_list"), format!("{shared}\n")).unwrap();
+}
+
+/// 命令キャッシュを除き、共有されるキャッシュは論理 CPU あたりの容量に換算されることを確認する。
+#[test]
+fn reads_per_cpu_cache_sizes_from_sysfs() {
+    let dir = std::env::temp_dir().join(format!("sosu_cache_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    write_cache_index(&dir, 0, "1", "Data", "48K", "0,8");
+    write_cache_index(&dir, 1, "1", "Instruction", "32K", "0,8");
+    write_cache_index(&dir, 2, "2", "Unified", "2048K", "0,8");
+    write_cache_index(&dir, 3, "3", "Unified", "30M", "0-15");
+
+    let info = read_cpu_cache_info(&dir);
+    assert_eq!(
+        info,
+        CpuCacheInfo {
+            l1d: Some(24 * 1024),
+            l2: Some(1024 * 1024),
+        }
+    );
+    let _ = std::fs::remove_dir_all(&dir);
+
+    assert_eq!(read_cpu_cache_info(&dir), CpuCacheInfo::default());
+}
+
+/// セグメントサイズはキャッシュに収まり、ホイールの圧縮率が高いほど広い範囲を扱えることを確認する。
+#[test]
+fn cache_segment_size_depends_on_wheel_type() {
+    let cache = CpuCacheInfo {
+        l1d: Some(32 * 1024),
+        l2: Some(1024 * 1024),
+    };
+    let sizes: Vec<u64> = [
+        WheelType::Odd,
+        WheelType::Mod6,
+        WheelType::Mod30,
+        WheelType::Mod210,
+    ]
+    .into_iter()
+    .map(|wheel| calculate_cache_segment_size(&cache, wheel).unwrap())
+    .collect();
+    assert!(sizes.windows(2).all(|w| w[0] < w[1]), "{sizes:?}");
+    // Odd は 1 ビット = 2 整数なので、L2 の半分（512KB）で 8M 個の範囲
+    assert_eq!(sizes[0], 8 * 1024 * 1024);
+
+    let l1_only = CpuCacheInfo {
+        l1d: Some(32 * 1024),
+        l2: None,
+    };
+    assert_eq!(
+        calculate_cache_segment_size(&l1_only, WheelType::Odd),
+        Some(512 * 1024)
+    );
+    assert_eq!(
+        calculate_cache_segment_size(&CpuCacheInfo::default(), WheelType::Mod30),
+        None
+    );
+}
+
+/// 自動モードでも結果は変わらないことを確認する。
+#[test]
+fn auto_segment_size_generates_same_primes() {
+    let cfg = Config {
+        prime_min: 1,
+        prime_max: 10_000_000,
+        segment_size: 1,
+        auto_segment_size: true,
+        ..Config::default()
+    };
+    let mut writer = LastPrimeWriter::new();
+    generate_primes_cpu(&cfg, &AtomicBool::new(false), &mut writer, |_| {}).unwrap();
+    assert_eq!(writer.total_primes_written(), 664_579);
+    assert_eq!(writer.get_last_prime(), Some(9_999_991));
+}
@@ -0,0 +1,253 @@
+#![cfg(not(windows))]
+
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+use std::sync::mpsc;
+
+use sosu_seisei_main2::checkpoint::{config_hash, Checkpoint};
+use sosu_seisei_main2::config::{Config, OutputFormat};
+use sosu_seisei_main2::cpu_engine::generate_primes_cpu_resumable;
+use sosu_seisei_main2::output::{FilePrimeWriter, PrimeWriter};
+use sosu_seisei_main2::worker_jobs::run_generate_job;
+
+mod common;
+use common::test_dir;
+
+/// 小さいセグメントでグループを多く作り、JSON を 10,000 個ずつ分割する設定
+fn test_config(output_dir: &Path) -> Config {
+    Config {
+        segment_size: 1_000,
+        output_format: OutputFormat::JSON,
+        split_count: 10_000,
+        ..common::test_config(output_dir)
+    }
+}
+
+/// 出力フォルダ内の素数ファイル（メタ情報を除く）を名前順に読み込む。
+fn read_prime_files(dir: &Path) -> Vec<(String, Vec<u8>)> {
+    let mut files: Vec<(String, Vec<u8>)> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+        .filter(|p| {
+            let name = p.file_name().unwrap().to_string_lossy();
+            !name.contains("checkpoint") && !name.contains("manifest")
+        })
+        .map(|p| {
+            let name = p.file_name().unwrap().to_string_lossy().to_string();
+            (name, std::fs::read(&p).unwrap())
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+/// 途中で異常終了した実行をチェックポイントから再開すると、
+/// 一度に最後まで生成した場合と同じ出力になることを確認する。
+#[test]
+fn resumed_run_matches_uninterrupted_run() {
+    let (sender, _receiver) = mpsc::channel();
+    let stop_flag = AtomicBool::new(false);
+
+    let full_dir = test_dir("full");
+    run_generate_job(&test_config(&full_dir), None, &stop_flag, &sender).unwrap();
+
+    // 3 グループ目のチェックポイント保存後に異常終了させる
+    let resumed_dir = test_dir("resumed");
+    let cfg = test_config(&resumed_dir);
+    let mut writer = FilePrimeWriter::new(
+        &cfg.output_dir,
+        cfg.output_format,
+        cfg.split_count,
+        cfg.writer_buffer_size,
+        None,
+    )
+    .unwrap();
+    let mut groups = 0;
+    let result = generate_primes_cpu_resumable(
+        &cfg,
+        &stop_flag,
+        &mut writer,
+        |_| {},
+        None,
+        |next_seg_start, writer: &mut dyn PrimeWriter| {
+            let state = writer.sync_state()?.unwrap();
+            Checkpoint::new(&cfg, next_seg_start, None, state).save(&cfg.output_dir)?;
+            groups += 1;
+            if groups == 3 {
+                return Err("simulated crash".into());
+            }
+            Ok(())
+        },
+    );
+    assert!(result.is_err());
+    drop(writer);
+
+    // チェックポイント後に書きかけのデータが残っている状況を再現する
+    let checkpoint = Checkpoint::load_all(&cfg.output_dir, config_hash(&cfg))
+        .unwrap()
+        .pop()
+        .unwrap();
+    let last_file = &checkpoint.writer.files.last().unwrap().path;
+    let mut content = std::fs::read(last_file).unwrap();
+    content.extend_from_slice(b",999999999");
+    std::fs::write(last_file, content).unwrap();
+
+    let resume_cfg = Config {
+        resume: true,
+        ..cfg.clone()
+    };
+    let outcome = run_generate_job(&resume_cfg, None, &stop_flag, &sender).unwrap();
+    assert_eq!(outcome.pi_x_verified, Some(true));
+    assert!(!Checkpoint::path_for(&cfg.output_dir, None).exists());
+
+    assert_eq!(read_prime_files(&full_dir), read_prime_files(&resumed_dir));
+
+    std::fs::remove_dir_all(&full_dir).ok();
+    std::fs::remove_dir_all(&resumed_dir).ok();
+}
+
+/// 設定が異なるチェックポイントからは再開しないことを確認する。
+#[test]
+fn resume_rejects_checkpoint_with_different_settings() {
+    let (sender, _receiver) = mpsc::channel();
+    let stop_flag = AtomicBool::new(false);
+
+    let dir = test_dir("mismatch");
+    let cfg = test_config(&dir);
+    Checkpoint::new(&cfg, 1_000_001, None, Default::default())
+        .save(&cfg.output_dir)
+        .unwrap();
+
+    let other_cfg = Config {
+        prime_max: 3_000_000,
+        resume: true,
+        ..cfg
+    };
+    assert!(run_generate_job(&other_cfg, None, &stop_flag, &sender).is_err());
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+/// `cfg` の実行を `prefix` 付きで 2 グループ目のチェックポイント保存後に異常終了させる。
+fn crash_after_two_groups(cfg: &Config, prefix: &str) {
+    let mut writer = FilePrimeWriter::new(
+        &cfg.output_dir,
+        cfg.output_format,
+        cfg.split_count,
+        cfg.writer_buffer_size,
+        Some(prefix.to_string()),
+    )
+    .unwrap();
+    let mut groups = 0;
+    let result = generate_primes_cpu_resumable(
+        cfg,
+        &AtomicBool::new(false),
+        &mut writer,
+        |_| {},
+        None,
+        |next_seg_start, writer: &mut dyn PrimeWriter| {
+            let state = writer.sync_state()?.unwrap();
+            Checkpoint::new(cfg, next_seg_start, Some(prefix.to_string()), state)
+                .save(&cfg.output_dir)?;
+            groups += 1;
+            if groups == 2 {
+                return Err("simulated crash".into());
+            }
+            Ok(())
+        },
+    );
+    assert!(result.is_err());
+}
+
+/// 同じフォルダへ書き出した 2 つの実行が別々のチェックポイントを持ち、それぞれ再開できることを確認する。
+#[test]
+fn runs_sharing_a_folder_keep_separate_checkpoints() {
+    let (sender, _receiver) = mpsc::channel();
+    let dir = test_dir("shared");
+    let cfg_a = test_config(&dir);
+    let cfg_b = Config {
+        prime_max: 1_500_000,
+        ..test_config(&dir)
+    };
+    crash_after_two_groups(&cfg_a, "run_a_");
+    crash_after_two_groups(&cfg_b, "run_b_");
+    assert!(Checkpoint::path_for(&cfg_a.output_dir, Some("run_a_")).exists());
+    assert!(Checkpoint::path_for(&cfg_b.output_dir, Some("run_b_")).exists());
+    assert_eq!(
+        Checkpoint::load_all(&cfg_a.output_dir, config_hash(&cfg_a))
+            .unwrap()
+            .len(),
+        2
+    );
+
+    for (cfg, prefix) in [(&cfg_b, "run_b_"), (&cfg_a, "run_a_")] {
+        let resume_cfg = Config {
+            resume: true,
+            ..cfg.clone()
+        };
+        let outcome =
+            run_generate_job(&resume_cfg, None, &AtomicBool::new(false), &sender).unwrap();
+        assert_eq!(outcome.pi_x_verified, Some(true), "{prefix}");
+        assert!(!Checkpoint::path_for(&cfg.output_dir, Some(prefix)).exists());
+    }
+    assert!(Checkpoint::load_all(&cfg_a.output_dir, config_hash(&cfg_a))
+        .unwrap()
+        .is_empty());
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+/// 壊れた別のチェックポイントは読み飛ばし、設定が一致するチェックポイントが読めない場合だけエラーになることを確認する。
+#[test]
+fn unreadable_checkpoints_are_skipped_unless_they_match() {
+    let (sender, _receiver) = mpsc::channel();
+    let dir = test_dir("corrupt");
+    let cfg = test_config(&dir);
+    crash_after_two_groups(&cfg, "run_a_");
+    std::fs::write(
+        Checkpoint::path_for(&cfg.output_dir, Some("broken_")),
+        b"{ not json",
+    )
+    .unwrap();
+
+    let hash = config_hash(&cfg);
+    assert_eq!(
+        Checkpoint::load_all(&cfg.output_dir, hash).unwrap().len(),
+        1
+    );
+    let resume_cfg = Config {
+        resume: true,
+        ..cfg.clone()
+    };
+    let outcome = run_generate_job(&resume_cfg, None, &AtomicBool::new(false), &sender).unwrap();
+    assert_eq!(outcome.pi_x_verified, Some(true));
+
+    // 設定は一致するが形式のバージョンが異なるチェックポイントは読み飛ばさない
+    let unsupported = format!(r#"{{"version": 0, "config_hash": {hash}}}"#);
+    std::fs::write(
+        Checkpoint::path_for(&cfg.output_dir, Some("old_")),
+        unsupported,
+    )
+    .unwrap();
+    assert!(Checkpoint::load_all(&cfg.output_dir, hash).is_err());
+    assert!(run_generate_job(&resume_cfg, None, &AtomicBool::new(false), &sender).is_err());
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+/// WheelBitmap 出力では再開を指定するとエラーになることを確認する。
+#[test]
+fn resume_is_rejected_for_wheel_bitmap() {
+    let (sender, _receiver) = mpsc::channel();
+    let dir = test_dir("bitmap");
+    let cfg = Config {
+        output_format: OutputFormat::WheelBitmap,
+        resume: true,
+        ..test_config(&dir)
+    };
+    let err = run_generate_job(&cfg, None, &AtomicBool::new(false), &sender).unwrap_err();
+    assert!(err.to_string().contains("WheelBitmap"), "{err}");
+
+    std::fs::remove_dir_all(&dir).ok();
+}
@@ -0,0 +1,106 @@
+#![cfg(not(windows))]
+
+use sosu_seisei_main2::cli::{try_run_subcommand, EXIT_ERROR, EXIT_OK, EXIT_VERIFY_FAILED};
+use sosu_seisei_main2::worker_message::WorkerMessage;
+
+fn args(list: &[&str]) -> Vec<String> {
+    list.iter().map(|s| s.to_string()).collect()
+}
+
+/// テストごとの出力フォルダ（チェックポイントの保存先が重ならないようにする）。
+fn temp_output_dir(name: &str) -> String {
+    std::env::temp_dir()
+        .join(format!("sosu_cli_{name}_{}", std::process::id()))
+        .to_string_lossy()
+        .to_string()
+}
+
+/// サブコマンド以外の引数では `None` を返し、GUI 起動に委ねることを確認する。
+#[test]
+fn non_subcommand_args_fall_through_to_gui() {
+    assert_eq!(try_run_subcommand(&[]), None);
+    assert_eq!(try_run_subcommand(&args(&["--unknown"])), None);
+}
+
+/// Last Prime Only モードの `generate` が π(x) 照合まで通り、終了コード 0 を返すことを確認する。
+#[test]
+fn generate_last_prime_only_succeeds() {
+    let code = try_run_subcommand(&args(&[
+        "generate",
+        "--min",
+        "1",
+        "--max",
+        "100000",
+        "--last-prime-only",
+        "--output-dir",
+        &temp_output_dir("last_prime"),
+    ]));
+    assert_eq!(code, Some(EXIT_OK));
+}
+
+/// 不正な引数は終了コード 1 になることを確認する。
+#[test]
+fn generate_rejects_invalid_arguments() {
+    let code = try_run_subcommand(&args(&["generate", "--wheel", "mod7"]));
+    assert_eq!(code, Some(EXIT_ERROR));
+
+    let code = try_run_subcommand(&args(&["generate", "--min", "10", "--max", "5"]));
+    assert_eq!(code, Some(EXIT_ERROR));
+}
+
+/// `verify` がテキスト / バイナリの分割出力ディレクトリを検証し、
+/// 合成数を含むファイルでは終了コード 2 を返すことを確認する。
+#[test]
+fn verify_accepts_valid_files_and_rejects_composites() {
+    let dir = std::env::temp_dir().join(format!("sosu_cli_verify_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let first: Vec<u8> = [2u64, 3, 5, 7]
+        .iter()
+        .flat_map(|p| p.to_le_bytes())
+        .collect();
+    let second: Vec<u8> = [11u64, 13, 17]
+        .iter()
+        .flat_map(|p| p.to_le_bytes())
+        .collect();
+    std::fs::write(dir.join("primes_1.bin"), first).unwrap();
+    std::fs::write(dir.join("primes_2.bin"), second).unwrap();
+    std::fs::write(dir.join("primes.meta.txt"), "not a prime list\n").unwrap();
+
+    let dir_str = dir.to_string_lossy().to_string();
+    assert_eq!(try_run_subcommand(&args(&["verify", &dir_str])), Some(EXIT_OK));
+
+    let bad = dir.join("composite.txt");
+    std::fs::write(&bad, "2\n3\n5\n9\n").unwrap();
+    let bad_str = bad.to_string_lossy().to_string();
+    assert_eq!(
+        try_run_subcommand(&args(&["verify", &bad_str, "--tail", "10"])),
+        Some(EXIT_VERIFY_FAILED)
+    );
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+/// `--json` モードで流す `WorkerMessage` の JSON 形式（type / data）を確認する。
+#[test]
+fn worker_messages_serialize_as_tagged_json_objects() {
+    let line = serde_json::to_string(&WorkerMessage::Progress { current: 5, total: 10 }).unwrap();
+    assert_eq!(line, r#"{"type":"Progress","data":{"current":5,"total":10}}"#);
+    assert_eq!(serde_json::to_string(&WorkerMessage::Done).unwrap(), r#"{"type":"Done"}"#);
+
+    let parsed: WorkerMessage = serde_json::from_str(r#"{"type":"Log","data":"hello"}"#).unwrap();
+    assert!(matches!(parsed, WorkerMessage::Log(msg) if msg == "hello"));
+
+    let code = try_run_subcommand(&args(&[
+        "generate",
+        "--min",
+        "1",
+        "--max",
+        "1000",
+        "--last-prime-only",
+        "--output-dir",
+        &temp_output_dir("json"),
+        "--json",
+    ]));
+    assert_eq!(code, Some(EXIT_OK));
+}
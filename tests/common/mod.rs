@@ -0,0 +1,93 @@
+//! 結合テストで共有する出力フォルダ・設定・生成ハーネス。
+//!
+//! 各テストファイルは `mod common;` で取り込み、設定は `..test_config(&dir)` から必要な項目だけ上書きします。
+
+// テストファイルごとに使う関数が違うため、使わないものがあっても警告しない
+#![allow(dead_code)]
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+
+use sosu_seisei_main2::config::{Config, OutputFormat};
+use sosu_seisei_main2::engine_types::PauseFlag;
+use sosu_seisei_main2::output::PrimeWriter;
+use sosu_seisei_main2::sieve_engine::{engine_for, EngineCallbacks};
+
+/// 空の出力フォルダを作る（テストバイナリ名とプロセス ID を付けてほかのテストと分ける）。
+pub fn test_dir(name: &str) -> PathBuf {
+    let test = module_path!().split("::").next().unwrap_or("test");
+    let dir = std::env::temp_dir().join(format!("sosu_{test}_{name}_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// ファイルへ書き出すジョブの基本設定（2,000,000 まで、Binary、50,000 個ずつ分割）。
+pub fn test_config(output_dir: &Path) -> Config {
+    Config {
+        prime_min: 1,
+        prime_max: 2_000_000,
+        segment_size: 10_000,
+        output_format: OutputFormat::Binary,
+        output_dir: output_dir.to_string_lossy().to_string(),
+        split_count: 50_000,
+        last_prime_only: false,
+        use_timestamp_prefix: false,
+        ..Config::default()
+    }
+}
+
+/// 個々の素数を受け取る Writer
+pub struct CollectWriter(pub Vec<u64>);
+
+impl PrimeWriter for CollectWriter {
+    fn write_prime(&mut self, p: u64) -> std::io::Result<()> {
+        self.0.push(p);
+        Ok(())
+    }
+
+    fn finish(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// 1 回の生成で出力された素数・進捗（処理済みの数）・グループ完了時の `next_start`
+#[derive(Debug, PartialEq)]
+pub struct Generated {
+    pub primes: Vec<u64>,
+    pub progress: Vec<u64>,
+    pub group_ends: Vec<u64>,
+}
+
+/// `cfg.engine` のエンジンで生成を 1 回実行する。
+pub fn generate(cfg: &Config, stop_flag: &AtomicBool, resume_from: Option<u64>) -> Generated {
+    let mut collect = CollectWriter(Vec::new());
+    let mut progress = Vec::new();
+    let mut group_ends = Vec::new();
+    engine_for(cfg.engine)
+        .generate(
+            cfg,
+            stop_flag,
+            &PauseFlag::new(),
+            &mut collect,
+            EngineCallbacks {
+                progress: &mut |p| progress.push(p.processed),
+                on_group_done: &mut |next, _| {
+                    group_ends.push(next);
+                    Ok(())
+                },
+            },
+            resume_from,
+        )
+        .unwrap();
+    Generated {
+        primes: collect.0,
+        progress,
+        group_ends,
+    }
+}
+
+/// 最初から最後まで生成し、出力された素数を返す。
+pub fn run(cfg: &Config) -> Vec<u64> {
+    generate(cfg, &AtomicBool::new(false), None).primes
+}
@@ -0,0 +1,94 @@
+use std::path::Path;
+
+use sosu_seisei_main2::config::{ConfigSource, LayeredConfig, WheelType};
+
+fn source_of<'a>(layered: &'a LayeredConfig, key: &str) -> &'a ConfigSource {
+    &layered
+        .sources
+        .iter()
+        .find(|(k, _)| *k == key)
+        .expect("unknown key")
+        .1
+}
+
+/// デフォルト → ファイル → 環境変数 → CLI の順に上書きされ、出どころが記録されることを確認する。
+#[test]
+fn layers_apply_in_order_and_record_sources() {
+    let dir = std::env::temp_dir().join(format!("sosu_config_layering_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("partial.toml");
+    std::fs::write(&path, "prime_max = 5000\nwheel_type = \"Mod6\"\nsplit_count = 7\n").unwrap();
+
+    std::env::set_var("SOSU_SPLIT_COUNT", "42");
+    let loaded = LayeredConfig::load(Some(Path::new(&path)));
+    std::env::remove_var("SOSU_SPLIT_COUNT");
+    let mut layered = loaded.expect("layered config should load");
+
+    layered
+        .set("prime_max", "9000", ConfigSource::Cli("--max".to_string()))
+        .unwrap();
+
+    let cfg = &layered.config;
+    assert_eq!(cfg.prime_max, 9000);
+    assert_eq!(cfg.wheel_type, WheelType::Mod6);
+    assert_eq!(cfg.split_count, 42);
+    assert_eq!(cfg.segment_size, 10_000_000);
+
+    assert_eq!(source_of(&layered, "prime_max"), &ConfigSource::Cli("--max".to_string()));
+    assert!(matches!(source_of(&layered, "wheel_type"), ConfigSource::File(_)));
+    assert_eq!(
+        source_of(&layered, "split_count"),
+        &ConfigSource::Env("SOSU_SPLIT_COUNT".to_string())
+    );
+    assert_eq!(source_of(&layered, "segment_size"), &ConfigSource::Default);
+
+    assert!(layered
+        .set("wheel_type", "mod7", ConfigSource::Cli("--wheel".to_string()))
+        .is_err());
+    layered
+        .set("wheel_type", "mod210", ConfigSource::Cli("--wheel".to_string()))
+        .unwrap();
+    assert_eq!(layered.config.wheel_type, WheelType::Mod210);
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+/// GUI の保存では、実行ごとのフラグと環境変数の値を書き込まず、変更した値は `gui` として記録されることを確認する。
+#[test]
+fn gui_save_skips_run_only_flags_and_env_values() {
+    let dir = std::env::temp_dir().join(format!("sosu_config_gui_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("gui.toml");
+    std::fs::write(&path, "prime_max = 5000\nresume = true\nthreads = 2\n").unwrap();
+
+    std::env::set_var("SOSU_THREADS", "3");
+    let loaded = LayeredConfig::load_or_create(Some(Path::new(&path)));
+    std::env::remove_var("SOSU_THREADS");
+    let layered = loaded.expect("layered config should load");
+
+    // 設定ファイルの resume は読み込まない
+    assert!(!layered.config.resume);
+    assert_eq!(layered.config.threads, 3);
+
+    let mut edited = layered.config.clone();
+    edited.prime_min = 10;
+    edited.resume = true;
+    edited.delete_partials_on_stop = true;
+
+    let sources = layered.sources_for(&edited);
+    let source = |key: &str| sources.iter().find(|(k, _)| k == key).unwrap().1.clone();
+    assert_eq!(source("prime_min"), ConfigSource::Gui);
+    assert_eq!(source("resume"), ConfigSource::Gui);
+    assert_eq!(source("threads"), ConfigSource::Env("SOSU_THREADS".to_string()));
+    assert!(matches!(source("prime_max"), ConfigSource::File(_)));
+
+    layered.save(&edited).unwrap();
+    let saved: toml::Table = toml::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(saved["prime_min"].as_integer(), Some(10));
+    assert_eq!(saved["prime_max"].as_integer(), Some(5000));
+    assert_eq!(saved["threads"].as_integer(), Some(2));
+    assert!(!saved.contains_key("resume"));
+    assert!(!saved.contains_key("delete_partials_on_stop"));
+
+    std::fs::remove_dir_all(&dir).ok();
+}
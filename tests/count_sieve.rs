@@ -0,0 +1,76 @@
+#![cfg(not(windows))]
+
+use std::sync::atomic::AtomicBool;
+
+use sosu_seisei_main2::config::{Config, WheelType};
+use sosu_seisei_main2::cpu_engine::{count_primes_sieve, generate_primes_cpu};
+use sosu_seisei_main2::output::LastPrimeWriter;
+use sosu_seisei_main2::prime_pi_engine::compute_prime_pi;
+use sosu_seisei_main2::sieve_math::simple_sieve;
+
+mod common;
+use common::run;
+
+/// 篩による区間カウントが primecount の π(b) - π(a - 1) と一致することを確認する。
+#[test]
+fn count_primes_sieve_matches_primecount() {
+    let ranges: &[(u64, u64)] = &[
+        (0, 0),
+        (0, 1),
+        (2, 2),
+        (0, 10),
+        (4, 6),
+        (7, 7),
+        (1, 1_000_000),
+        (999_000, 1_001_000),
+        (123_456_789, 160_000_000),
+        (10_000_000_000, 10_010_000_000),
+    ];
+
+    for &(a, b) in ranges {
+        let pi_b = compute_prime_pi(b).unwrap();
+        let pi_a = if a == 0 { 0 } else { compute_prime_pi(a - 1).unwrap() };
+        assert_eq!(
+            count_primes_sieve(a, b).unwrap(),
+            pi_b - pi_a,
+            "count_primes_sieve({a}, {b})"
+        );
+    }
+    assert_eq!(count_primes_sieve(10, 5).unwrap(), 0);
+}
+
+/// Last Prime Only（カウント専用経路）の個数と最後の素数が、
+/// 素数列を作る通常経路の結果と全ホイールで一致することを確認する。
+#[test]
+fn last_prime_only_counting_path_matches_collecting_path() {
+    let stop_flag = AtomicBool::new(false);
+    let ranges: &[(u64, u64)] = &[(1, 200_000), (89_690, 89_800), (24, 28), (2, 5)];
+
+    for wheel_type in [WheelType::Odd, WheelType::Mod6, WheelType::Mod30, WheelType::Mod210] {
+        for &(prime_min, prime_max) in ranges {
+            let cfg = Config {
+                prime_min,
+                prime_max,
+                segment_size: 997,
+                wheel_type,
+                ..Config::default()
+            };
+
+            let expected: Vec<u64> = simple_sieve(prime_max)
+                .unwrap()
+                .into_iter()
+                .filter(|&p| p >= prime_min)
+                .collect();
+            assert_eq!(run(&cfg), expected, "{wheel_type:?} [{prime_min}, {prime_max}]");
+
+            let mut last = LastPrimeWriter::new();
+            generate_primes_cpu(&cfg, &stop_flag, &mut last, |_| {}).unwrap();
+            assert_eq!(
+                last.total_primes_written(),
+                expected.len() as u64,
+                "{wheel_type:?} [{prime_min}, {prime_max}]"
+            );
+            assert_eq!(last.get_last_prime(), expected.last().copied());
+        }
+    }
+}
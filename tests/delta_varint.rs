@@ -0,0 +1,78 @@
+use std::io::Cursor;
+use std::sync::atomic::AtomicBool;
+
+use sosu_seisei_main2::config::{Config, OutputFormat};
+use sosu_seisei_main2::cpu_engine::generate_primes_cpu;
+use sosu_seisei_main2::delta_varint::{DeltaVarintHeader, DeltaVarintReader};
+use sosu_seisei_main2::output::{FilePrimeWriter, PrimeWriter};
+use sosu_seisei_main2::verify::verify_primes_file;
+
+/// 分割した差分 varint 出力を読み戻すと、元の素数列と一致し `verify` も通ることを確認する。
+#[test]
+fn delta_varint_files_round_trip_and_verify() {
+    let dir = std::env::temp_dir().join(format!("sosu_delta_varint_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let cfg = Config {
+        prime_min: 1,
+        prime_max: 1_000_000,
+        output_format: OutputFormat::DeltaVarint,
+        output_dir: dir.to_string_lossy().to_string(),
+        split_count: 30_000,
+        last_prime_only: false,
+        ..Config::default()
+    };
+    let mut writer = FilePrimeWriter::new(
+        &cfg.output_dir,
+        cfg.output_format,
+        cfg.split_count,
+        cfg.writer_buffer_size,
+        None,
+    )
+    .unwrap();
+    generate_primes_cpu(&cfg, &AtomicBool::new(false), &mut writer, |_| {}).unwrap();
+    writer.finish().unwrap();
+    writer.commit().unwrap();
+
+    let mut primes = Vec::new();
+    for path in writer.output_file_paths() {
+        let reader = DeltaVarintReader::open(path).unwrap();
+        let header = *reader.header();
+        let values: Vec<u64> = reader.map(|v| v.unwrap()).collect();
+        assert_eq!(values.len() as u64, header.count);
+        assert_eq!(values.first().copied().unwrap_or(0), header.base);
+        if !values.is_empty() {
+            verify_primes_file(path, 50, None).unwrap();
+        }
+        primes.extend(values);
+    }
+
+    assert_eq!(primes.len(), 78_498);
+    assert_eq!(&primes[..5], &[2, 3, 5, 7, 11]);
+    assert_eq!(primes.last(), Some(&999_983));
+    assert!(primes.windows(2).all(|w| w[0] < w[1]));
+
+    // 1 バイト varint が大半を占めるため、Binary（8 バイト/素数）より大幅に小さい
+    let total_bytes: u64 = writer
+        .output_file_paths()
+        .iter()
+        .map(|p| std::fs::metadata(p).unwrap().len())
+        .sum();
+    assert!(total_bytes < primes.len() as u64 * 2);
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+/// ヘッダの個数とデータ長が合わないファイルはエラーになることを確認する。
+#[test]
+fn delta_varint_reader_rejects_truncated_data() {
+    let mut bytes = Vec::new();
+    DeltaVarintHeader::new(3, 3).write_to(&mut bytes).unwrap();
+    bytes.push(1); // 3 -> 5 の差 / 2
+
+    let values: Vec<_> = DeltaVarintReader::new(Cursor::new(bytes)).unwrap().collect();
+    assert_eq!(values.len(), 3);
+    assert_eq!(*values[0].as_ref().unwrap(), 3);
+    assert_eq!(*values[1].as_ref().unwrap(), 5);
+    assert!(values[2].is_err());
+}
@@ -0,0 +1,53 @@
+#![cfg(not(windows))]
+
+use std::sync::atomic::AtomicBool;
+use std::sync::mpsc;
+
+use sosu_seisei_main2::config::{Config, OutputFormat};
+use sosu_seisei_main2::output::OutputMetadata;
+use sosu_seisei_main2::worker_jobs::run_generate_job;
+
+/// 生成後に書き出されたマニフェストを読み戻し、ファイルごとの件数・最初/最後の素数と
+/// 設定スナップショットが記録されていることを確認する。
+#[test]
+fn manifest_round_trips_run_details() {
+    let dir = std::env::temp_dir().join(format!("sosu_manifest_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let cfg = Config {
+        prime_min: 1,
+        prime_max: 100_000,
+        output_format: OutputFormat::Text,
+        output_dir: dir.to_string_lossy().to_string(),
+        split_count: 5_000,
+        last_prime_only: false,
+        use_timestamp_prefix: false,
+        ..Config::default()
+    };
+    let (sender, _receiver) = mpsc::channel();
+    run_generate_job(&cfg, None, &AtomicBool::new(false), &sender).unwrap();
+
+    let manifests = OutputMetadata::find_manifests(&dir).unwrap();
+    assert_eq!(manifests.len(), 1);
+    let manifest = OutputMetadata::read_manifest(&manifests[0]).unwrap();
+
+    let metadata = &manifest.metadata;
+    assert_eq!(metadata.range, (1, 100_000));
+    assert_eq!(metadata.count, 9_592);
+    assert!(metadata.pi_x_verified);
+    assert_eq!(manifest.config.split_count, 5_000);
+    assert_eq!(manifest.config.output_format, OutputFormat::Text);
+
+    // π(100000) = 9592 を 5000 件ずつ分割 → 2 ファイル
+    assert_eq!(metadata.files.len(), 2);
+    assert_eq!(metadata.files[0].count, 5_000);
+    assert_eq!(metadata.files[0].first_prime, Some(2));
+    assert_eq!(metadata.files[1].count, 4_592);
+    assert_eq!(metadata.files[1].last_prime, Some(99_991));
+    assert!(metadata.files[0].last_prime < metadata.files[1].first_prime);
+    for file in &metadata.files {
+        assert_eq!(std::fs::metadata(&file.path).unwrap().len(), file.bytes);
+    }
+
+    std::fs::remove_dir_all(&dir).ok();
+}
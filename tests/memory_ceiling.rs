@@ -0,0 +1,76 @@
+#![cfg(not(windows))]
+
+use std::sync::atomic::AtomicBool;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use sosu_seisei_main2::config::Config;
+use sosu_seisei_main2::memory::{MemoryCeiling, ProcessMemory};
+use sosu_seisei_main2::worker_jobs::start_resource_monitor;
+use sosu_seisei_main2::worker_message::WorkerMessage;
+
+mod common;
+use common::{generate, Generated};
+
+/// プロセスの RSS を取得でき、上限 0 は「上限なし」になることを確認する。
+#[test]
+fn process_rss_and_ceiling() {
+    let mut process = ProcessMemory::new();
+    let rss = process.rss().unwrap();
+    assert!(rss > 0);
+    assert!(process.system_used() >= rss);
+
+    assert!(MemoryCeiling::from_mb(0).is_none());
+    let mut ceiling = MemoryCeiling::from_mb(1).unwrap();
+    assert_eq!(ceiling.limit(), 1024 * 1024);
+    let (_, ratio) = ceiling.usage().unwrap();
+    assert!(ratio > 1.0);
+}
+
+/// 常に上限を超えている状態でも、1 セグメントずつ篩って最後まで同じ結果を出すことを確認する
+/// （パイプライン時は書き出し待ちが捌けるのを待ってから次を篩う）。
+#[test]
+fn exceeded_ceiling_shrinks_groups_without_changing_output() {
+    let base = Config {
+        prime_min: 1,
+        prime_max: 2_000_000,
+        segment_size: 50_000,
+        threads: 4,
+        ..Config::default()
+    };
+    let expected = generate(&base, &AtomicBool::new(false), None);
+
+    for pipeline_depth in [0, 2] {
+        let cfg = Config {
+            memory_ceiling_mb: 1,
+            pipeline_depth,
+            ..base.clone()
+        };
+        let Generated {
+            primes, group_ends, ..
+        } = generate(&cfg, &AtomicBool::new(false), None);
+        assert_eq!(primes, expected.primes, "depth {pipeline_depth}");
+        // グループは 1 セグメントずつになる
+        assert!(group_ends.len() > expected.group_ends.len());
+        assert!(group_ends.windows(2).all(|w| w[1] - w[0] <= 50_000));
+        assert_eq!(group_ends.last(), expected.group_ends.last());
+    }
+}
+
+/// リソースモニタがシステム全体の使用量とプロセスの RSS を別々に送ることを確認する。
+#[test]
+fn resource_monitor_reports_process_and_system_memory() {
+    let (sender, receiver) = mpsc::channel();
+    start_resource_monitor(sender);
+
+    let (mut system, mut process) = (None, None);
+    while system.is_none() || process.is_none() {
+        match receiver.recv_timeout(Duration::from_secs(5)).unwrap() {
+            WorkerMessage::MemUsage(mem) => system = Some(mem),
+            WorkerMessage::ProcessMemUsage(mem) => process = Some(mem),
+            _ => {}
+        }
+    }
+    assert!(process.unwrap() > 0);
+    assert!(system.unwrap() >= process.unwrap());
+}
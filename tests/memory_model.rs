@@ -0,0 +1,93 @@
+#![cfg(not(windows))]
+
+use sosu_seisei_main2::config::{Config, WheelType};
+use sosu_seisei_main2::memory::{
+    calculate_optimal_segment_size, estimate_prime_vector_memory, estimate_small_prime_memory,
+    get_memory_info, get_total_memory, MemoryModel,
+};
+
+fn file_output_model(num_threads: usize, pipeline_depth: usize) -> MemoryModel {
+    let cfg = Config {
+        prime_min: 1,
+        prime_max: 1_000_000_000_000,
+        wheel_type: WheelType::Mod30,
+        writer_buffer_size: 8 * 1024 * 1024,
+        pipeline_depth,
+        ..Config::default()
+    };
+    MemoryModel::from_config(&cfg, num_threads, false)
+}
+
+/// 素数ベクタの見積もりが実際の素数の個数に近いことを確認する（10^8 幅で約 40MB）。
+#[test]
+fn prime_vector_estimate_tracks_prime_count() {
+    // π(10^8) = 5,761,455
+    let bytes = estimate_prime_vector_memory(100_000_000, 0);
+    assert!(
+        (5_761_455 * 8..5_761_455 * 8 * 11 / 10).contains(&bytes),
+        "{bytes}"
+    );
+
+    // 大きい値の区間ほど素数がまばらになる
+    assert!(estimate_prime_vector_memory(100_000_000, 1 << 40) < bytes);
+
+    // √10^12 = 10^6 以下の素数は 78,498 個
+    let table = estimate_small_prime_memory(1_000_000_000_000, 100_000_000);
+    assert!(
+        (78_498 * 8..78_498 * 8 * 11 / 10).contains(&table),
+        "{table}"
+    );
+}
+
+/// 内訳の合計が推定使用量になり、書き出しモードとパイプラインの深さが反映されることを確認する。
+#[test]
+fn memory_info_breakdown_reflects_run_settings() {
+    let segment_size = 10_000_000;
+    let info = get_memory_info(segment_size, &file_output_model(8, 0));
+    assert_eq!(
+        info.estimated_total,
+        info.sieve_memory
+            + info.prime_vector_memory
+            + info.writer_buffer_memory
+            + info.small_prime_memory
+    );
+    assert_eq!(info.sieve_memory, info.segment_memory * 8);
+    assert_eq!(
+        info.prime_vector_memory,
+        estimate_prime_vector_memory(segment_size, 1) * 8
+    );
+    assert_eq!(info.writer_buffer_memory, 8 * 1024 * 1024);
+    assert!(info.format().contains("素数ベクタ"));
+
+    // パイプライン時は篩中・キュー内・書き出し中のグループ分の結果を保持する
+    let pipelined = get_memory_info(segment_size, &file_output_model(8, 2));
+    assert_eq!(pipelined.prime_vector_memory, info.prime_vector_memory * 4);
+
+    // Last Prime Only では素数ベクタも書き出しバッファも持たない
+    let counts_only = MemoryModel {
+        counts_only: true,
+        ..file_output_model(8, 0)
+    };
+    let info = get_memory_info(segment_size, &counts_only);
+    assert_eq!(info.prime_vector_memory, 0);
+    assert_eq!(info.writer_buffer_memory, 0);
+}
+
+/// 最適セグメントサイズで見積もった使用量が、指定したメモリ使用率に収まることを確認する。
+#[test]
+fn optimal_segment_size_respects_memory_budget() {
+    let allowed = get_total_memory() / 10;
+    for model in [file_output_model(64, 0), file_output_model(64, 4)] {
+        let segment_size = calculate_optimal_segment_size(10.0, &model);
+        let info = get_memory_info(segment_size, &model);
+        if segment_size > 1_000_000 {
+            assert!(info.estimated_total <= allowed, "{}", info.format());
+        }
+
+        let counts_only = MemoryModel {
+            counts_only: true,
+            ..model
+        };
+        assert!(calculate_optimal_segment_size(10.0, &counts_only) >= segment_size);
+    }
+}
@@ -0,0 +1,143 @@
+use std::sync::atomic::AtomicBool;
+
+use sosu_seisei_main2::config::{Config, WheelType};
+use sosu_seisei_main2::cpu_engine::{count_primes_sieve, generate_primes_cpu};
+use sosu_seisei_main2::output::LastPrimeWriter;
+use sosu_seisei_main2::prime_iter::primes_in;
+use sosu_seisei_main2::prime_pi_engine::compute_prime_pi;
+use sosu_seisei_main2::sieve_math::{integer_sqrt, sieving_primes, simple_sieve};
+
+mod common;
+use common::run;
+
+/// 2^64 未満の最大の素数（2^64 - 59）
+const LARGEST_U64_PRIME: u64 = 18_446_744_073_709_551_557;
+
+/// `integer_sqrt` が u64::MAX 付近まで厳密な床関数になっていることを確認する。
+#[test]
+fn integer_sqrt_is_exact_up_to_u64_max() {
+    let max_root = u32::MAX as u64;
+    assert_eq!(integer_sqrt(u64::MAX), max_root);
+    assert_eq!(integer_sqrt(max_root * max_root), max_root);
+    assert_eq!(integer_sqrt(max_root * max_root - 1), max_root - 1);
+
+    for n in [0u64, 1, 2, 3, 4, 5, 8, 9, 10, 99, 100, 101] {
+        let r = integer_sqrt(n);
+        assert!(r * r <= n && (r + 1) * (r + 1) > n, "integer_sqrt({n}) = {r}");
+    }
+    for k in [3_037_000_499u64, 3_037_000_500, 4_000_000_000, max_root - 1] {
+        assert_eq!(integer_sqrt(k * k), k);
+        assert_eq!(integer_sqrt(k * k - 1), k - 1);
+        assert_eq!(integer_sqrt(k * k + 1), k);
+    }
+}
+
+/// ビット配列版の `simple_sieve` が primecount と同じ個数の素数を返すことを確認する。
+#[test]
+fn simple_sieve_counts_match_primecount() {
+    for limit in [0u64, 1, 2, 3, 4, 9, 10, 11, 100, 65_536, 1_000_003] {
+        let primes = simple_sieve(limit).unwrap();
+        assert_eq!(primes.len() as u64, compute_prime_pi(limit).unwrap(), "limit {limit}");
+        assert!(primes.last().is_none_or(|&p| p <= limit));
+    }
+}
+
+/// `sieving_primes` が √high までの素数のうち、区間に倍数を持つものだけを返すことを確認する。
+#[test]
+fn sieving_primes_keep_only_primes_with_multiples_in_range() {
+    for high in [0u64, 1, 4, 288, 289, 290, 1_000_003, 123_456_789_012] {
+        let all = simple_sieve(integer_sqrt(high)).unwrap();
+        assert_eq!(sieving_primes(0, high).unwrap(), all, "high {high}");
+    }
+
+    // 狭い区間では、`max(low, p^2)` 以上の倍数が区間内に無い素数は捨てる
+    let (low, high) = (1_000_000_000_000 - 999, 1_000_000_000_000);
+    let expected: Vec<u64> = simple_sieve(integer_sqrt(high))
+        .unwrap()
+        .into_iter()
+        .filter(|&p| (low.max(p * p)..=high).any(|n| n % p == 0))
+        .collect();
+    assert_eq!(sieving_primes(low, high).unwrap(), expected);
+    assert!(expected.len() < 1_000);
+}
+
+/// u64 に対する決定的 Miller-Rabin 素数判定（テストの参照実装）。
+fn is_prime_u64(n: u64) -> bool {
+    if n < 2 {
+        return false;
+    }
+    const BASES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+    for p in BASES {
+        if n.is_multiple_of(p) {
+            return n == p;
+        }
+    }
+
+    let mul_mod = |a: u64, b: u64| ((a as u128 * b as u128) % n as u128) as u64;
+    let pow_mod = |mut base: u64, mut exp: u64| {
+        let mut result = 1u64;
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result = mul_mod(result, base);
+            }
+            base = mul_mod(base, base);
+            exp >>= 1;
+        }
+        result
+    };
+
+    let s = (n - 1).trailing_zeros();
+    let d = (n - 1) >> s;
+    BASES.iter().all(|&a| {
+        let mut x = pow_mod(a, d);
+        if x == 1 || x == n - 1 {
+            return true;
+        }
+        for _ in 1..s {
+            x = mul_mod(x, x);
+            if x == n - 1 {
+                return true;
+            }
+        }
+        false
+    })
+}
+
+/// `[2^64 - 10^6, 2^64 - 1]` を全ホイールで篩い、Miller-Rabin と一致することを確認する。
+///
+/// √(2^64) までの素数は流しながら選別するので、保持するのは区間に倍数を持つ約 55 万個だけです。
+#[test]
+fn window_below_2_pow_64_matches_miller_rabin() {
+    let stop_flag = AtomicBool::new(false);
+    let prime_min = u64::MAX - 999_999;
+    let prime_max = u64::MAX;
+    let expected: Vec<u64> = (prime_min..=prime_max).filter(|&n| is_prime_u64(n)).collect();
+    assert_eq!(expected.last(), Some(&LARGEST_U64_PRIME));
+
+    for wheel_type in [WheelType::Odd, WheelType::Mod6, WheelType::Mod30, WheelType::Mod210] {
+        let cfg = Config {
+            prime_min,
+            prime_max,
+            segment_size: 65_536,
+            wheel_type,
+            ..Config::default()
+        };
+        assert_eq!(run(&cfg), expected, "{wheel_type:?}");
+    }
+
+    let cfg = Config {
+        prime_min,
+        prime_max,
+        ..Config::default()
+    };
+    let mut last = LastPrimeWriter::new();
+    generate_primes_cpu(&cfg, &stop_flag, &mut last, |_| {}).unwrap();
+    assert_eq!(last.total_primes_written(), expected.len() as u64);
+    assert_eq!(last.get_last_prime(), Some(LARGEST_U64_PRIME));
+
+    assert_eq!(
+        count_primes_sieve(prime_min, prime_max).unwrap(),
+        expected.len() as u64
+    );
+    assert_eq!(primes_in(prime_min..=prime_max).collect::<Vec<_>>(), expected);
+}
@@ -0,0 +1,252 @@
+#![cfg(not(windows))]
+
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+
+use sosu_seisei_main2::checkpoint::Checkpoint;
+use sosu_seisei_main2::config::Config;
+use sosu_seisei_main2::output::{OutputMetadata, RunManifest, RunStatus};
+use sosu_seisei_main2::worker_jobs::{run_generate_job, GenerateOutcome};
+use sosu_seisei_main2::worker_message::WorkerMessage;
+
+mod common;
+use common::{test_config, test_dir};
+
+/// 出力フォルダ内の素数ファイル名（`.bin` / `.bin.partial`）を名前順に返す。
+fn prime_files(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+        .filter(|n| n.ends_with(".bin") || n.ends_with(".bin.partial"))
+        .collect();
+    names.sort();
+    names
+}
+
+fn read_manifest(dir: &Path) -> RunManifest {
+    OutputMetadata::read_manifest(dir.join("primes.manifest.json")).unwrap()
+}
+
+/// 最初の進捗が届いたところで停止する。
+fn run_and_stop(cfg: &Config) -> GenerateOutcome {
+    let stop_flag = AtomicBool::new(false);
+    let (sender, receiver) = mpsc::channel();
+    std::thread::scope(|scope| {
+        let job = scope.spawn(|| {
+            let outcome = run_generate_job(cfg, None, &stop_flag, &sender);
+            drop(sender);
+            outcome
+        });
+        for message in receiver {
+            if let WorkerMessage::Progress { .. } = message {
+                stop_flag.store(true, Ordering::SeqCst);
+            }
+        }
+        job.join().unwrap().unwrap()
+    })
+}
+
+/// 最後まで生成すると `.partial` が本来の名前に変わり、`complete` が記録されることを確認する。
+#[test]
+fn complete_run_renames_partial_files() {
+    let (sender, _receiver) = mpsc::channel();
+    let dir = test_dir("complete");
+    let cfg = test_config(&dir);
+    let outcome = run_generate_job(&cfg, None, &AtomicBool::new(false), &sender).unwrap();
+    assert_eq!(outcome.file_verified, Some(true));
+
+    assert_eq!(
+        prime_files(&dir),
+        ["primes_1.bin", "primes_2.bin", "primes_3.bin"]
+    );
+    let manifest = read_manifest(&dir);
+    assert_eq!(manifest.metadata.status, RunStatus::Complete);
+    assert!(manifest
+        .metadata
+        .files
+        .iter()
+        .all(|f| f.path.ends_with(".bin")));
+    let report = std::fs::read_to_string(dir.join("primes.meta.txt")).unwrap();
+    assert!(report.contains("Status: complete"), "{report}");
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+/// 停止すると `.partial` のまま残り、`stopped` が記録され、再開すると完成した出力になることを確認する。
+#[test]
+fn stopped_run_keeps_partial_files_for_resume() {
+    let dir = test_dir("stopped");
+    let cfg = Config {
+        prime_max: 100_000_000,
+        ..test_config(&dir)
+    };
+    run_and_stop(&cfg);
+
+    let files = prime_files(&dir);
+    assert!(!files.is_empty());
+    assert!(files.iter().all(|n| n.ends_with(".partial")), "{files:?}");
+    let manifest = read_manifest(&dir);
+    assert_eq!(manifest.metadata.status, RunStatus::Stopped);
+    let report = std::fs::read_to_string(dir.join("primes.meta.txt")).unwrap();
+    assert!(report.contains("Status: stopped"), "{report}");
+
+    // 再開は範囲を変えられないので、同じ設定のまま最後まで生成する
+    let (sender, _receiver) = mpsc::channel();
+    let resume_cfg = Config {
+        resume: true,
+        ..cfg
+    };
+    let outcome = run_generate_job(&resume_cfg, None, &AtomicBool::new(false), &sender).unwrap();
+    assert_eq!(outcome.pi_x_verified, Some(true));
+    let files = prime_files(&dir);
+    assert!(files.iter().all(|n| n.ends_with(".bin")), "{files:?}");
+    assert_eq!(read_manifest(&dir).metadata.status, RunStatus::Complete);
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+/// `delete_partials_on_stop` では、停止時に `.partial` とチェックポイントが削除されることを確認する。
+#[test]
+fn stopped_run_deletes_partial_files_when_requested() {
+    let dir = test_dir("deleted");
+    let cfg = Config {
+        prime_max: 100_000_000,
+        delete_partials_on_stop: true,
+        ..test_config(&dir)
+    };
+    run_and_stop(&cfg);
+
+    assert!(prime_files(&dir).is_empty());
+    assert!(!Checkpoint::path_for(&cfg.output_dir, None).exists());
+    let manifest = read_manifest(&dir);
+    assert_eq!(manifest.metadata.status, RunStatus::Stopped);
+    assert!(manifest.metadata.files.is_empty());
+
+    let mut parsed = Config::default();
+    parsed.set_field("delete_partials_on_stop", "true").unwrap();
+    assert!(parsed.delete_partials_on_stop);
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+/// エラーで終了すると `.partial` が残り、`failed` が記録されることを確認する。
+#[test]
+fn failed_run_records_failed_status() {
+    let (sender, _receiver) = mpsc::channel();
+    let dir = test_dir("failed");
+    let cfg = test_config(&dir);
+    // チェックポイントの一時ファイルと同名のディレクトリを置き、保存を失敗させる
+    std::fs::create_dir_all(dir.join("primes.checkpoint.json.tmp")).unwrap();
+
+    let result = run_generate_job(&cfg, None, &AtomicBool::new(false), &sender);
+    assert!(result.is_err());
+
+    let files = prime_files(&dir);
+    assert!(!files.is_empty());
+    assert!(files.iter().all(|n| n.ends_with(".partial")), "{files:?}");
+    assert_eq!(read_manifest(&dir).metadata.status, RunStatus::Failed);
+    let report = std::fs::read_to_string(dir.join("primes.meta.txt")).unwrap();
+    assert!(report.contains("Status: failed"), "{report}");
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+/// 確定（`.partial` のリネーム）に失敗した場合も `failed` が記録されることを確認する。
+#[test]
+fn failed_commit_records_failed_status() {
+    let (sender, _receiver) = mpsc::channel();
+    let dir = test_dir("commit_failed");
+    let cfg = test_config(&dir);
+    // 確定後の名前にフォルダがあるとリネームできない
+    std::fs::create_dir_all(dir.join("primes_2.bin").join("blocker")).unwrap();
+
+    let result = run_generate_job(&cfg, None, &AtomicBool::new(false), &sender);
+    assert!(result.is_err());
+    assert_eq!(read_manifest(&dir).metadata.status, RunStatus::Failed);
+    let report = std::fs::read_to_string(dir.join("primes.meta.txt")).unwrap();
+    assert!(report.contains("Status: failed"), "{report}");
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+/// 最初のグループより前に停止していても、`stopped` が記録されることを確認する。
+#[test]
+fn stop_before_first_group_records_status() {
+    let (sender, _receiver) = mpsc::channel();
+    for last_prime_only in [false, true] {
+        let dir = test_dir(&format!("stopped_early_{last_prime_only}"));
+        let cfg = Config {
+            last_prime_only,
+            delete_partials_on_stop: true,
+            ..test_config(&dir)
+        };
+        let outcome = run_generate_job(&cfg, None, &AtomicBool::new(true), &sender).unwrap();
+        assert_eq!(outcome.pi_x_verified, None);
+        assert!(prime_files(&dir).is_empty());
+        let manifest = read_manifest(&dir);
+        assert_eq!(
+            manifest.metadata.status,
+            RunStatus::Stopped,
+            "{last_prime_only}"
+        );
+        assert_eq!(manifest.metadata.count, 0);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
+
+/// 停止した実行では π(x) との照合もファイル検証も行わず、`None` になることを確認する。
+#[test]
+fn stopped_run_skips_verification() {
+    for delete_partials_on_stop in [false, true] {
+        let dir = test_dir(&format!("unverified_{delete_partials_on_stop}"));
+        let cfg = Config {
+            prime_max: 100_000_000,
+            delete_partials_on_stop,
+            ..test_config(&dir)
+        };
+        let outcome = run_and_stop(&cfg);
+        assert_eq!(outcome.pi_x_verified, None);
+        assert_eq!(outcome.file_verified, None);
+        assert!(!read_manifest(&dir).metadata.pi_x_verified);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
+
+/// 最後の素数だけのモードでも、終わり方がメタレポートとマニフェストに記録されることを確認する。
+#[test]
+fn last_prime_run_records_status() {
+    let (sender, _receiver) = mpsc::channel();
+    let dir = test_dir("last_prime");
+    let cfg = Config {
+        last_prime_only: true,
+        ..test_config(&dir)
+    };
+    let outcome = run_generate_job(&cfg, None, &AtomicBool::new(false), &sender).unwrap();
+    assert_eq!(outcome.pi_x_verified, Some(true));
+    let manifest = read_manifest(&dir);
+    assert_eq!(manifest.metadata.status, RunStatus::Complete);
+    assert_eq!(manifest.metadata.count, outcome.total_primes);
+    assert!(manifest.metadata.pi_x_verified);
+    assert!(manifest.metadata.files.is_empty());
+
+    let stopped_dir = test_dir("last_prime_stopped");
+    let stopped_cfg = Config {
+        prime_max: 100_000_000,
+        last_prime_only: true,
+        ..test_config(&stopped_dir)
+    };
+    let outcome = run_and_stop(&stopped_cfg);
+    assert_eq!(outcome.pi_x_verified, None);
+    assert_eq!(
+        read_manifest(&stopped_dir).metadata.status,
+        RunStatus::Stopped
+    );
+    let report = std::fs::read_to_string(stopped_dir.join("primes.meta.txt")).unwrap();
+    assert!(report.contains("Status: stopped"), "{report}");
+
+    std::fs::remove_dir_all(&dir).ok();
+    std::fs::remove_dir_all(&stopped_dir).ok();
+}
@@ -0,0 +1,149 @@
+#![cfg(not(windows))]
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::time::Duration;
+
+use sosu_seisei_main2::config::Config;
+use sosu_seisei_main2::cpu_engine::generate_primes_cpu_pausable;
+use sosu_seisei_main2::engine_types::{compute_eta, PauseFlag};
+use sosu_seisei_main2::worker_jobs::run_generate_job_pausable;
+use sosu_seisei_main2::worker_message::WorkerMessage;
+
+mod common;
+use common::{run, CollectWriter};
+
+fn test_config() -> Config {
+    Config {
+        prime_min: 1,
+        prime_max: 3_000_000,
+        segment_size: 10_000,
+        ..Config::default()
+    }
+}
+
+/// 一時停止していた時間は ETA の推定から除外されることを確認する。
+#[test]
+fn eta_excludes_paused_time() {
+    assert_eq!(compute_eta(50, 100, 20.0, 0.0), Some(20));
+    assert_eq!(compute_eta(50, 100, 20.0, 10.0), Some(10));
+    assert_eq!(compute_eta(0, 100, 20.0, 10.0), None);
+
+    let pause_flag = PauseFlag::new();
+    assert_eq!(pause_flag.paused_secs(), 0.0);
+    pause_flag.pause();
+    std::thread::sleep(Duration::from_millis(50));
+    pause_flag.resume();
+    let paused = pause_flag.paused_secs();
+    assert!(paused >= 0.05);
+    std::thread::sleep(Duration::from_millis(20));
+    assert_eq!(pause_flag.paused_secs(), paused);
+}
+
+/// 一時停止中は篩が進まず、再開すると続きから処理して通常どおりの結果になることを確認する。
+#[test]
+fn paused_generation_blocks_and_resumes_without_losing_state() {
+    let cfg = test_config();
+    let stop_flag = AtomicBool::new(false);
+    let expected = run(&cfg);
+
+    let pause_flag = PauseFlag::new();
+    pause_flag.pause();
+    let groups = AtomicUsize::new(0);
+    let mut collect = CollectWriter(Vec::new());
+
+    std::thread::scope(|scope| {
+        scope.spawn(|| {
+            generate_primes_cpu_pausable(
+                &cfg,
+                &stop_flag,
+                &pause_flag,
+                &mut collect,
+                |_| {},
+                None,
+                |_, _| {
+                    groups.fetch_add(1, Ordering::SeqCst);
+                    Ok(())
+                },
+            )
+            .unwrap();
+        });
+
+        std::thread::sleep(Duration::from_millis(300));
+        assert_eq!(groups.load(Ordering::SeqCst), 0);
+        pause_flag.resume();
+    });
+
+    assert!(groups.load(Ordering::SeqCst) > 0);
+    assert_eq!(collect.0, expected);
+    assert!(pause_flag.paused_secs() >= 0.3);
+}
+
+/// 一時停止中に停止すると、再開を待たずに終了することを確認する。
+#[test]
+fn stop_while_paused_ends_generation() {
+    let cfg = test_config();
+    let stop_flag = AtomicBool::new(false);
+    let pause_flag = PauseFlag::new();
+    pause_flag.pause();
+    let mut groups = 0;
+
+    std::thread::scope(|scope| {
+        scope.spawn(|| {
+            std::thread::sleep(Duration::from_millis(100));
+            stop_flag.store(true, Ordering::SeqCst);
+        });
+        generate_primes_cpu_pausable(
+            &cfg,
+            &stop_flag,
+            &pause_flag,
+            &mut CollectWriter(Vec::new()),
+            |_| {},
+            None,
+            |_, _| {
+                groups += 1;
+                Ok(())
+            },
+        )
+        .unwrap();
+    });
+
+    assert_eq!(groups, 0);
+    assert!(pause_flag.is_paused());
+}
+
+/// 生成ジョブは一時停止と再開を `WorkerMessage::Paused` で通知することを確認する。
+#[test]
+fn generate_job_reports_pause_state() {
+    let cfg = Config {
+        output_dir: std::env::temp_dir()
+            .join(format!("sosu_pause_{}", std::process::id()))
+            .to_string_lossy()
+            .to_string(),
+        last_prime_only: true,
+        ..test_config()
+    };
+    let stop_flag = AtomicBool::new(false);
+    let pause_flag = PauseFlag::new();
+    pause_flag.pause();
+    let (sender, receiver) = mpsc::channel();
+
+    let outcome = std::thread::scope(|scope| {
+        let job =
+            scope.spawn(|| run_generate_job_pausable(&cfg, None, &stop_flag, &pause_flag, &sender));
+        std::thread::sleep(Duration::from_millis(300));
+        pause_flag.resume();
+        job.join().unwrap().unwrap()
+    });
+    assert_eq!(outcome.total_primes, 216_816);
+
+    let paused: Vec<bool> = receiver
+        .try_iter()
+        .filter_map(|m| match m {
+            WorkerMessage::Paused(p) => Some(p),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(paused, vec![true, false]);
+    let _ = std::fs::remove_dir_all(&cfg.output_dir);
+}
@@ -0,0 +1,84 @@
+#![cfg(not(windows))]
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use sosu_seisei_main2::config::{Config, WheelType};
+use sosu_seisei_main2::cpu_engine::generate_primes_cpu_resumable;
+use sosu_seisei_main2::output::LastPrimeWriter;
+
+mod common;
+use common::{generate, CollectWriter};
+
+/// パイプライン有効時も、出力順・進捗・チェックポイント位置が従来の交互実行と一致することを確認する。
+#[test]
+fn pipelined_output_matches_serial_output() {
+    let stop_flag = AtomicBool::new(false);
+
+    for wheel_type in [WheelType::Odd, WheelType::Mod30, WheelType::Mod210] {
+        let serial_cfg = Config {
+            prime_min: 1_000,
+            prime_max: 3_000_000,
+            segment_size: 10_000,
+            wheel_type,
+            pipeline_depth: 0,
+            ..Config::default()
+        };
+        let expected = generate(&serial_cfg, &stop_flag, None);
+        assert!(expected.primes.windows(2).all(|w| w[0] < w[1]));
+
+        for pipeline_depth in [1, 4] {
+            let cfg = Config {
+                pipeline_depth,
+                ..serial_cfg.clone()
+            };
+            assert_eq!(
+                generate(&cfg, &stop_flag, None),
+                expected,
+                "{wheel_type:?}, depth {pipeline_depth}"
+            );
+
+            // カウント専用経路（Last Prime Only）
+            let mut last = LastPrimeWriter::new();
+            generate_primes_cpu_resumable(&cfg, &stop_flag, &mut last, |_| {}, None, |_, _| Ok(()))
+                .unwrap();
+            assert_eq!(last.total_primes_written(), expected.primes.len() as u64);
+            assert_eq!(last.get_last_prime(), expected.primes.last().copied());
+        }
+    }
+}
+
+/// 書き出し中に停止した場合、篩側のスレッドも止まり、書き出し済みの素数は順序どおりであることを確認する。
+#[test]
+fn pipelined_output_stops_with_stop_flag() {
+    let stop_flag = AtomicBool::new(false);
+    let cfg = Config {
+        prime_min: 1,
+        prime_max: 50_000_000,
+        segment_size: 10_000,
+        pipeline_depth: 2,
+        ..Config::default()
+    };
+
+    let mut collect = CollectWriter(Vec::new());
+    let mut groups = 0;
+    generate_primes_cpu_resumable(
+        &cfg,
+        &stop_flag,
+        &mut collect,
+        |_| {},
+        None,
+        |_, _| {
+            groups += 1;
+            if groups == 3 {
+                stop_flag.store(true, Ordering::SeqCst);
+            }
+            Ok(())
+        },
+    )
+    .unwrap();
+
+    assert_eq!(groups, 3);
+    assert!(!collect.0.is_empty());
+    assert!(collect.0.windows(2).all(|w| w[0] < w[1]));
+    assert!(collect.0.last().is_some_and(|&p| p < cfg.prime_max / 10));
+}
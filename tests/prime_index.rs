@@ -0,0 +1,145 @@
+#![cfg(not(windows))]
+
+use std::sync::atomic::AtomicBool;
+use std::sync::mpsc;
+
+use sosu_seisei_main2::config::{Config, OutputFormat};
+use sosu_seisei_main2::output::{FilePrimeWriter, FileSplit, PrimeWriter};
+use sosu_seisei_main2::prime_index::PrimeIndex;
+use sosu_seisei_main2::prime_iter::primes_in;
+use sosu_seisei_main2::worker_jobs::run_generate_job;
+
+mod common;
+use common::{test_config, test_dir};
+
+/// 分割された Binary / DeltaVarint 出力で、`nth`・`range`・`pi_from_file` が全件走査と一致することを確認する。
+#[test]
+fn index_answers_queries_over_split_files() {
+    let expected: Vec<u64> = primes_in(1_000..=2_000_000).collect();
+
+    for format in [OutputFormat::Binary, OutputFormat::DeltaVarint] {
+        let (sender, _receiver) = mpsc::channel();
+        let dir = test_dir(&format!("{format:?}"));
+        let cfg = Config {
+            prime_min: 1_000,
+            output_format: format,
+            split_count: 30_000,
+            index_stride: 1_000,
+            ..test_config(&dir)
+        };
+        run_generate_job(&cfg, None, &AtomicBool::new(false), &sender).unwrap();
+
+        let index = PrimeIndex::open(dir.join("primes.index")).unwrap();
+        assert_eq!(index.len(), expected.len() as u64);
+        assert_eq!(index.stride(), 1_000);
+
+        for i in [
+            0,
+            1,
+            999,
+            1_000,
+            1_001,
+            29_999,
+            30_000,
+            77_777,
+            expected.len() - 1,
+        ] {
+            assert_eq!(
+                index.nth(i as u64).unwrap(),
+                Some(expected[i]),
+                "{format:?}, nth({i})"
+            );
+        }
+        assert_eq!(index.nth(expected.len() as u64).unwrap(), None);
+
+        assert_eq!(
+            index.range(1_000_000, 1_000_100).unwrap(),
+            [1_000_003, 1_000_033, 1_000_037, 1_000_039, 1_000_081, 1_000_099]
+        );
+        let across_files: Vec<u64> = expected
+            .iter()
+            .copied()
+            .filter(|&p| (300_000..=400_000).contains(&p))
+            .collect();
+        assert_eq!(index.range(300_000, 400_000).unwrap(), across_files);
+        assert!(index.range(10, 500).unwrap().is_empty());
+        assert!(index.range(5, 4).unwrap().is_empty());
+
+        for x in [0, 1_008, 1_009, 1_000_000, 1_299_709, 2_000_000, u64::MAX] {
+            let count = expected.iter().filter(|&&p| p <= x).count() as u64;
+            assert_eq!(
+                index.pi_from_file(x).unwrap(),
+                count,
+                "{format:?}, pi_from_file({x})"
+            );
+        }
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
+
+/// 途中再開した Writer でも、一度に書き出した場合と同じインデックスになることを確認する。
+#[test]
+fn resumed_writer_rebuilds_index() {
+    let primes: Vec<u64> = primes_in(1..=300_000).collect();
+    let split = FileSplit::ByValue(100_000);
+
+    let full_dir = test_dir("full");
+    let mut writer = FilePrimeWriter::with_split(
+        &full_dir.to_string_lossy(),
+        OutputFormat::DeltaVarint,
+        split,
+        4096,
+        None,
+    )
+    .unwrap();
+    assert!(writer.enable_index(500).unwrap());
+    for &p in &primes {
+        writer.write_prime(p).unwrap();
+    }
+    writer.finish().unwrap();
+    writer.commit().unwrap();
+    let full_index = std::fs::read(writer.index_path().unwrap()).unwrap();
+
+    let resumed_dir = test_dir("resumed");
+    let output_dir = resumed_dir.to_string_lossy().to_string();
+    let mut writer =
+        FilePrimeWriter::with_split(&output_dir, OutputFormat::DeltaVarint, split, 4096, None)
+            .unwrap();
+    for &p in &primes[..12_345] {
+        writer.write_prime(p).unwrap();
+    }
+    let state = writer.sync_state().unwrap().unwrap();
+    drop(writer);
+
+    let mut writer = FilePrimeWriter::resume_with_split(
+        &output_dir,
+        OutputFormat::DeltaVarint,
+        split,
+        4096,
+        None,
+        &state,
+    )
+    .unwrap();
+    assert!(writer.enable_index(500).unwrap());
+    for &p in &primes[12_345..] {
+        writer.write_prime(p).unwrap();
+    }
+    writer.finish().unwrap();
+    writer.commit().unwrap();
+    assert_eq!(
+        std::fs::read(writer.index_path().unwrap()).unwrap(),
+        full_index
+    );
+
+    let index = PrimeIndex::open(writer.index_path().unwrap()).unwrap();
+    assert_eq!(index.nth(12_345).unwrap(), Some(primes[12_345]));
+    assert_eq!(index.pi_from_file(299_999).unwrap(), primes.len() as u64);
+
+    // テキスト形式ではインデックスを作らない
+    let mut text = FilePrimeWriter::new(&output_dir, OutputFormat::Text, 0, 4096, None).unwrap();
+    assert!(!text.enable_index(500).unwrap());
+
+    std::fs::remove_dir_all(&full_dir).ok();
+    std::fs::remove_dir_all(&resumed_dir).ok();
+}
@@ -0,0 +1,43 @@
+use std::ops::RangeInclusive;
+
+use rayon::prelude::*;
+
+use sosu_seisei_main2::prime_iter::{par_prime_chunks_with_segment_size, primes_in};
+use sosu_seisei_main2::sieve_math::simple_sieve;
+
+fn expected(a: u64, b: u64) -> Vec<u64> {
+    simple_sieve(b)
+        .unwrap()
+        .into_iter()
+        .filter(|&p| p >= a)
+        .collect()
+}
+
+/// 逐次イテレータと並列チャンクが単純な篩と同じ素数列を返すことを確認する。
+#[test]
+fn iterators_match_simple_sieve() {
+    let ranges = [(0, 1), (0, 10), (2, 2), (5, 7), (7, 7), (10, 30), (1, 100_000), (99_000, 250_003)];
+    for &(a, b) in &ranges {
+        let want = expected(a, b);
+        assert_eq!(primes_in(a..=b).collect::<Vec<_>>(), want, "primes_in({a}..={b})");
+        assert_eq!(
+            primes_in(a..=b).with_segment_size(1_000).collect::<Vec<_>>(),
+            want,
+            "primes_in({a}..={b}) with small segments"
+        );
+
+        let chunks: Vec<Vec<u64>> = par_prime_chunks_with_segment_size(a..=b, 4_096).collect();
+        assert_eq!(chunks.concat(), want, "par_prime_chunks({a}..={b})");
+    }
+
+    let empty = RangeInclusive::new(10, 5);
+    assert_eq!(primes_in(empty.clone()).count(), 0);
+    assert_eq!(par_prime_chunks_with_segment_size(empty, 100).count(), 0);
+}
+
+/// 遅延評価なので、巨大な範囲でも先頭だけを取り出せることを確認する。
+#[test]
+fn primes_in_is_lazy() {
+    let first: Vec<u64> = primes_in(1_000_000_000..=u64::MAX / 2).take(3).collect();
+    assert_eq!(first, vec![1_000_000_007, 1_000_000_009, 1_000_000_021]);
+}
@@ -0,0 +1,148 @@
+#![cfg(not(windows))]
+
+use std::sync::atomic::AtomicBool;
+
+use sosu_seisei_main2::config::{Config, SieveEngineKind};
+use sosu_seisei_main2::engine_types::PauseFlag;
+use sosu_seisei_main2::output::LastPrimeWriter;
+use sosu_seisei_main2::sieve_engine::{engine_for, ignored_settings, EngineCallbacks};
+
+mod common;
+use common::{generate, run};
+
+/// どのエンジンでも、区間の端・小さい素数・大きな値の付近で同じ素数列になることを確認する。
+#[test]
+fn all_engines_produce_the_same_primes() {
+    let ranges: &[(u64, u64)] = &[
+        (0, 1),
+        (0, 2),
+        (1, 100),
+        (2, 30),
+        (24, 28),
+        (1, 1_000_000),
+        (999_983, 1_000_003),
+        (10_000_000_000, 10_000_300_000),
+    ];
+
+    for &(prime_min, prime_max) in ranges {
+        let base = Config {
+            prime_min,
+            prime_max,
+            segment_size: 65_536,
+            ..Config::default()
+        };
+        let expected = run(&base);
+
+        for engine in [SieveEngineKind::Reference, SieveEngineKind::Atkin] {
+            let cfg = Config {
+                engine,
+                ..base.clone()
+            };
+            let generated = generate(&cfg, &AtomicBool::new(false), None);
+            assert_eq!(
+                generated.primes, expected,
+                "{engine:?} [{prime_min}, {prime_max}]"
+            );
+            assert_eq!(
+                generated.group_ends.last(),
+                Some(&prime_max.saturating_add(1))
+            );
+
+            let mut last = LastPrimeWriter::new();
+            engine_for(engine)
+                .generate(
+                    &cfg,
+                    &AtomicBool::new(false),
+                    &PauseFlag::new(),
+                    &mut last,
+                    EngineCallbacks {
+                        progress: &mut |_| {},
+                        on_group_done: &mut |_, _| Ok(()),
+                    },
+                    None,
+                )
+                .unwrap();
+            assert_eq!(last.total_primes_written(), expected.len() as u64);
+            assert_eq!(last.get_last_prime(), expected.last().copied());
+        }
+    }
+}
+
+/// 単一スレッドのエンジンも `resume_from` の位置から続きだけを出力することを確認する。
+#[test]
+fn sequential_engines_resume_from_next_start() {
+    for engine in [SieveEngineKind::Reference, SieveEngineKind::Atkin] {
+        let cfg = Config {
+            prime_min: 1,
+            prime_max: 3_000_000,
+            segment_size: 10_000,
+            engine,
+            ..Config::default()
+        };
+        let full = generate(&cfg, &AtomicBool::new(false), None);
+        // チェックポイントは区間ごとではなく 64 区間ずつのグループごとに保存する
+        assert_eq!(
+            full.group_ends,
+            [640_001, 1_280_001, 1_920_001, 2_560_001, 3_000_001],
+            "{engine:?}"
+        );
+        assert_eq!(full.progress.len(), 300);
+        let resume_at = full.group_ends[2];
+        let rest = generate(&cfg, &AtomicBool::new(false), Some(resume_at)).primes;
+
+        let head: Vec<u64> = full
+            .primes
+            .iter()
+            .copied()
+            .filter(|&p| p < resume_at)
+            .collect();
+        assert_eq!([head, rest].concat(), full.primes, "{engine:?}");
+    }
+}
+
+/// アトキンの篩は区間幅を √prime_max 以上に広げ、エラトステネスの篩は設定どおりの区間幅で篩うことを確認する。
+#[test]
+fn atkin_segments_span_at_least_sqrt_prime_max() {
+    for (engine, step) in [
+        (SieveEngineKind::Reference, 1_000),
+        (SieveEngineKind::Atkin, 10_000),
+    ] {
+        let cfg = Config {
+            prime_min: 99_900_001,
+            prime_max: 100_000_000,
+            segment_size: 1_000,
+            engine,
+            ..Config::default()
+        };
+        let progress = generate(&cfg, &AtomicBool::new(false), None).progress;
+        assert_eq!(progress.len() as u64, 100_000 / step, "{engine:?}");
+        assert!(
+            progress.iter().zip(1..).all(|(&p, i)| p == i * step),
+            "{engine:?}"
+        );
+    }
+}
+
+/// 単一スレッドのエンジンでは、効かない設定を変えていると報告されることを確認する。
+#[test]
+fn single_threaded_engines_report_ignored_settings() {
+    let cfg = Config {
+        threads: 4,
+        pipeline_depth: 2,
+        ..Config::default()
+    };
+    assert!(ignored_settings(&cfg).is_empty());
+
+    for engine in [SieveEngineKind::Reference, SieveEngineKind::Atkin] {
+        let tuned = Config {
+            engine,
+            ..cfg.clone()
+        };
+        assert_eq!(ignored_settings(&tuned), ["threads", "pipeline_depth"]);
+        let untouched = Config {
+            engine,
+            ..Config::default()
+        };
+        assert!(ignored_settings(&untouched).is_empty());
+    }
+}
@@ -0,0 +1,159 @@
+#![cfg(not(windows))]
+
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+use std::sync::mpsc;
+
+use sosu_seisei_main2::config::{Config, OutputFormat};
+use sosu_seisei_main2::output::{FilePrimeWriter, FileSplit, OutputMetadata, PrimeWriter};
+use sosu_seisei_main2::worker_jobs::run_generate_job;
+
+mod common;
+use common::{test_config, test_dir};
+
+/// 出力フォルダ内のファイル名を名前順に返す。
+fn file_names(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+        .collect();
+    names.sort();
+    names
+}
+
+const PRIMES: [u64; 8] = [2, 3, 97, 101, 199, 211, 401, 409];
+
+/// 値の範囲ごとにファイルが分かれ、素数を含まない範囲のファイルは作られないことを確認する。
+#[test]
+fn files_cover_fixed_value_ranges() {
+    let dir = test_dir("ranges");
+    let mut writer = FilePrimeWriter::with_split(
+        &dir.to_string_lossy(),
+        OutputFormat::Text,
+        FileSplit::ByValue(100),
+        1024,
+        None,
+    )
+    .unwrap();
+    assert!(file_names(&dir).is_empty());
+
+    for p in PRIMES {
+        writer.write_prime(p).unwrap();
+    }
+    writer.finish().unwrap();
+    writer.commit().unwrap();
+
+    assert_eq!(
+        file_names(&dir),
+        [
+            "primes_0-100.txt",
+            "primes_100-200.txt",
+            "primes_200-300.txt",
+            "primes_400-500.txt",
+        ]
+    );
+    assert_eq!(
+        std::fs::read_to_string(dir.join("primes_100-200.txt")).unwrap(),
+        "101\n199\n"
+    );
+
+    let states = writer.file_states();
+    let ranges: Vec<_> = states.iter().map(|f| f.value_range.unwrap()).collect();
+    assert_eq!(ranges, [(0, 100), (100, 200), (200, 300), (400, 500)]);
+    let counts: Vec<_> = states.iter().map(|f| f.count).collect();
+    assert_eq!(counts, [3, 2, 1, 2]);
+    assert_eq!(writer.total_primes_written(), PRIMES.len() as u64);
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+/// 再開するとチェックポイント後に作られた範囲のファイルが削除され、続きから同じ出力になることを確認する。
+#[test]
+fn resume_removes_ranges_written_after_checkpoint() {
+    let dir = test_dir("resume");
+    let output_dir = dir.to_string_lossy().to_string();
+    let mut writer = FilePrimeWriter::with_split(
+        &output_dir,
+        OutputFormat::Binary,
+        FileSplit::ByValue(100),
+        1024,
+        None,
+    )
+    .unwrap();
+    for p in &PRIMES[..4] {
+        writer.write_prime(*p).unwrap();
+    }
+    let state = writer.sync_state().unwrap().unwrap();
+    assert_eq!(state.files.last().unwrap().value_range, Some((100, 200)));
+    for p in &PRIMES[4..] {
+        writer.write_prime(*p).unwrap();
+    }
+    drop(writer);
+    assert!(dir.join("primes_400-500.bin.partial").exists());
+
+    let mut resumed = FilePrimeWriter::resume_with_split(
+        &output_dir,
+        OutputFormat::Binary,
+        FileSplit::ByValue(100),
+        1024,
+        None,
+        &state,
+    )
+    .unwrap();
+    assert_eq!(
+        file_names(&dir),
+        ["primes_0-100.bin.partial", "primes_100-200.bin.partial"]
+    );
+
+    for p in &PRIMES[4..] {
+        resumed.write_prime(*p).unwrap();
+    }
+    resumed.finish().unwrap();
+    resumed.commit().unwrap();
+
+    let values: Vec<u64> = file_names(&dir)
+        .iter()
+        .flat_map(|name| std::fs::read(dir.join(name)).unwrap())
+        .collect::<Vec<u8>>()
+        .chunks_exact(8)
+        .map(|b| u64::from_le_bytes(b.try_into().unwrap()))
+        .collect();
+    assert_eq!(values, PRIMES);
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+/// 生成ジョブで `split_width` を指定すると、マニフェストに各ファイルの範囲が記録されることを確認する。
+#[test]
+fn manifest_records_value_range_per_file() {
+    let (sender, _receiver) = mpsc::channel();
+    let dir = test_dir("manifest");
+    let cfg = Config {
+        prime_max: 2_500_000,
+        split_count: 1_000,
+        split_width: 1_000_000,
+        ..test_config(&dir)
+    };
+    let outcome = run_generate_job(&cfg, None, &AtomicBool::new(false), &sender).unwrap();
+    assert_eq!(outcome.total_primes, 183_072);
+
+    let manifest = OutputMetadata::read_manifest(dir.join("primes.manifest.json")).unwrap();
+    let files = &manifest.metadata.files;
+    let ranges: Vec<_> = files.iter().map(|f| f.value_range.unwrap()).collect();
+    assert_eq!(
+        ranges,
+        [
+            (0, 1_000_000),
+            (1_000_000, 2_000_000),
+            (2_000_000, 3_000_000)
+        ]
+    );
+    assert!(files[1].path.ends_with("primes_1000000-2000000.bin"));
+    assert_eq!(files[0].count, 78_498);
+    assert_eq!(files[0].last_prime, Some(999_983));
+    assert_eq!(files[1].first_prime, Some(1_000_003));
+    assert_eq!(files.iter().map(|f| f.count).sum::<u64>(), 183_072);
+    assert_eq!(manifest.config.split_width, 1_000_000);
+
+    std::fs::remove_dir_all(&dir).ok();
+}
@@ -0,0 +1,44 @@
+#![cfg(not(windows))]
+
+use sosu_seisei_main2::verify::verify_primes_file;
+
+mod common;
+use common::test_dir;
+
+/// Binary（8 バイト LE）ファイルも共通の検証を通り、順序の乱れ・途中で切れたレコードを検出することを確認する。
+#[test]
+fn binary_files_share_value_verification() {
+    let dir = test_dir("binary");
+    let path = dir.join("primes.bin");
+    let encode =
+        |values: &[u64]| -> Vec<u8> { values.iter().flat_map(|v| v.to_le_bytes()).collect() };
+
+    std::fs::write(&path, encode(&[2, 3, 5, 7, 11, 13])).unwrap();
+    let report = verify_primes_file(&path, 3, None).unwrap();
+    assert_eq!(report.line_count, 6);
+    assert_eq!(report.checked_tail, 3);
+    assert_eq!((report.min, report.max), (2, 13));
+
+    std::fs::write(&path, encode(&[2, 3, 7, 5])).unwrap();
+    let err = verify_primes_file(&path, 3, None).unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("Non-increasing sequence at record 4"),
+        "{err}"
+    );
+
+    std::fs::write(&path, encode(&[2, 3, 15])).unwrap();
+    let err = verify_primes_file(&path, 3, None).unwrap_err();
+    assert!(err.to_string().contains("Composite detected"), "{err}");
+
+    let mut truncated = encode(&[2, 3]);
+    truncated.pop();
+    std::fs::write(&path, truncated).unwrap();
+    assert!(verify_primes_file(&path, 3, None).is_err());
+
+    std::fs::write(&path, b"").unwrap();
+    let err = verify_primes_file(&path, 3, None).unwrap_err();
+    assert!(err.to_string().contains("empty"), "{err}");
+
+    std::fs::remove_dir_all(&dir).ok();
+}
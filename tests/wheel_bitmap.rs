@@ -0,0 +1,259 @@
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use sosu_seisei_main2::config::{Config, OutputFormat, SieveEngineKind, WheelType};
+use sosu_seisei_main2::cpu_engine::generate_primes_cpu;
+use sosu_seisei_main2::engine_types::PauseFlag;
+use sosu_seisei_main2::output::{FilePrimeWriter, PrimeWriter};
+use sosu_seisei_main2::prime_iter::primes_in;
+use sosu_seisei_main2::sieve_engine::{engine_for, EngineCallbacks};
+use sosu_seisei_main2::sieve_math::simple_sieve;
+use sosu_seisei_main2::verify::verify_primes_file;
+use sosu_seisei_main2::wheel_bitmap::WheelBitmap;
+
+fn write_bitmap(
+    name: &str,
+    prime_min: u64,
+    prime_max: u64,
+    wheel_type: WheelType,
+) -> (std::path::PathBuf, WheelBitmap) {
+    write_bitmap_with(
+        name,
+        Config {
+            prime_min,
+            prime_max,
+            // 30 の倍数に揃わないセグメント幅にする
+            segment_size: 7_777,
+            wheel_type,
+            ..Config::default()
+        },
+    )
+}
+
+/// `cfg.engine` のエンジンで `cfg` の範囲を WheelBitmap 形式で書き出し、検証して開く。
+fn write_bitmap_with(name: &str, cfg: Config) -> (std::path::PathBuf, WheelBitmap) {
+    let dir = std::env::temp_dir().join(format!("sosu_wheel_bitmap_{name}_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let cfg = Config {
+        output_format: OutputFormat::WheelBitmap,
+        output_dir: dir.to_string_lossy().to_string(),
+        last_prime_only: false,
+        ..cfg
+    };
+    let mut writer = FilePrimeWriter::new(
+        &cfg.output_dir,
+        cfg.output_format,
+        cfg.split_count,
+        cfg.writer_buffer_size,
+        None,
+    )
+    .unwrap();
+    writer.set_value_range(cfg.prime_min, cfg.prime_max);
+    engine_for(cfg.engine)
+        .generate(
+            &cfg,
+            &AtomicBool::new(false),
+            &PauseFlag::new(),
+            &mut writer,
+            EngineCallbacks {
+                progress: &mut |_| {},
+                on_group_done: &mut |_, _| Ok(()),
+            },
+            None,
+        )
+        .unwrap();
+    writer.finish().unwrap();
+    writer.commit().unwrap();
+
+    let path = writer.output_file_paths()[0].clone();
+    verify_primes_file(&path, 20, None).unwrap();
+    let bitmap = WheelBitmap::open(&path).unwrap();
+    (dir, bitmap)
+}
+
+/// ビットマップの素数判定・個数・列挙が単純な篩の結果と一致することを確認する。
+#[test]
+fn bitmap_lookups_match_simple_sieve() {
+    let (min, max) = (1_003, 200_017);
+    let (dir, mut bitmap) = write_bitmap("lookups", min, max, WheelType::Mod30);
+    let expected: Vec<u64> = simple_sieve(max)
+        .unwrap()
+        .into_iter()
+        .filter(|&p| p >= min)
+        .collect();
+
+    assert_eq!(bitmap.header().start, min);
+    assert_eq!(bitmap.header().end, max);
+    assert_eq!(bitmap.iter().collect::<io::Result<Vec<_>>>().unwrap(), expected);
+
+    for n in min..=max {
+        let is_prime = expected.binary_search(&n).is_ok();
+        assert_eq!(bitmap.is_prime(n).unwrap(), Some(is_prime), "n = {n}");
+    }
+    assert_eq!(bitmap.is_prime(min - 1).unwrap(), None);
+    assert_eq!(bitmap.is_prime(max + 1).unwrap(), None);
+
+    let ranges = [(min, max), (1_003, 1_009), (5_000, 5_029), (7_919, 7_919), (0, 60_000), (150_001, u64::MAX)];
+    for &(a, b) in &ranges {
+        let direct = expected.iter().filter(|&&p| a <= p && p <= b).count() as u64;
+        assert_eq!(bitmap.count(a, b).unwrap(), direct, "count({a}, {b})");
+        assert_eq!(bitmap.iter_range(a, b).count() as u64, direct);
+    }
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+/// 範囲に 2, 3, 5 を含む場合、ビットマップ外の小さい素数も扱えることを確認する。
+#[test]
+fn bitmap_includes_wheel_primes() {
+    let (dir, mut bitmap) = write_bitmap("small", 1, 100, WheelType::Mod30);
+    assert_eq!(bitmap.count(1, 100).unwrap(), 25);
+    let first: io::Result<Vec<_>> = bitmap.iter().take(4).collect();
+    assert_eq!(first.unwrap(), [2, 3, 5, 7]);
+    assert_eq!(bitmap.is_prime(2).unwrap(), Some(true));
+    assert_eq!(bitmap.is_prime(1).unwrap(), Some(false));
+    assert_eq!(bitmap.is_prime(9).unwrap(), Some(false));
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+/// フォルダ内の唯一のファイルを読み込む。
+fn read_only_file(dir: &std::path::Path) -> Vec<u8> {
+    let entry = std::fs::read_dir(dir).unwrap().next().unwrap().unwrap();
+    std::fs::read(entry.path()).unwrap()
+}
+
+/// 篩のホイールによらず、同じ mod 30 配置のファイルになることを確認する。
+#[test]
+fn layout_is_independent_of_sieve_wheel() {
+    let (min, max) = (1, 300_007);
+    let expected: Vec<u64> = primes_in(min..=max).collect();
+    let (mod30_dir, mut mod30) = write_bitmap("wheel_mod30", min, max, WheelType::Mod30);
+    let mod30_bytes = read_only_file(&mod30_dir);
+    assert_eq!(mod30.iter().collect::<io::Result<Vec<_>>>().unwrap(), expected);
+
+    for wheel in [WheelType::Odd, WheelType::Mod6, WheelType::Mod210] {
+        let (dir, mut bitmap) = write_bitmap(&format!("wheel_{wheel:?}"), min, max, wheel);
+        assert_eq!(bitmap.header(), mod30.header(), "{wheel:?}");
+        let primes: io::Result<Vec<_>> = bitmap.iter().collect();
+        assert_eq!(primes.unwrap(), expected, "{wheel:?}");
+        assert!(
+            read_only_file(&dir) == mod30_bytes,
+            "{wheel:?}: file differs from the Mod30 run"
+        );
+        std::fs::remove_dir_all(&dir).ok();
+    }
+    std::fs::remove_dir_all(&mod30_dir).ok();
+}
+
+/// 本体を読み込み単位（64 KiB）より大きくしても、シークして読んだ個数・列挙が正しく、
+/// 本体が欠けたファイルは開けないことを確認する。
+#[test]
+fn lookups_read_the_body_in_chunks() {
+    let (min, max) = (1, 5_000_000);
+    let (dir, mut bitmap) = write_bitmap("chunks", min, max, WheelType::Mod30);
+    assert!(bitmap.header().body_len() > 2 * 64 * 1024);
+    let expected: Vec<u64> = primes_in(min..=max).collect();
+    assert_eq!(bitmap.iter().collect::<io::Result<Vec<_>>>().unwrap(), expected);
+
+    // 64 KiB の境界（1,966,080 = 65,536 × 30）をまたぐ範囲と、境界ちょうどで終わる範囲
+    for (a, b) in [(1_966_000, 1_966_200), (1_000_000, 4_000_000), (2, 1_966_079)] {
+        let direct = expected.iter().filter(|&&p| a <= p && p <= b).count() as u64;
+        assert_eq!(bitmap.count(a, b).unwrap(), direct, "count({a}, {b})");
+        let listed: io::Result<Vec<_>> = bitmap.iter_range(a, b).collect();
+        assert_eq!(listed.unwrap().len() as u64, direct);
+    }
+    assert_eq!(bitmap.is_prime(4_999_999).unwrap(), Some(true));
+    assert_eq!(bitmap.is_prime(4_999_997).unwrap(), Some(false));
+
+    let path = std::fs::read_dir(&dir).unwrap().next().unwrap().unwrap().path();
+    let file = std::fs::OpenOptions::new().write(true).open(&path).unwrap();
+    file.set_len(32 + bitmap.header().body_len() - 1).unwrap();
+    assert!(WheelBitmap::open(&path).is_err());
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+/// CPU エンジンが篩のビットをまとめて書き出したファイルが、素数を 1 個ずつ受け取る
+/// Reference エンジンのファイルと、セグメント幅によらず一致することを確認する。
+#[test]
+fn wheel_bits_match_prime_by_prime_encoding() {
+    let (min, max) = (1_003, 300_007);
+    let (reference_dir, _) = write_bitmap_with(
+        "bits_reference",
+        Config {
+            prime_min: min,
+            prime_max: max,
+            engine: SieveEngineKind::Reference,
+            ..Config::default()
+        },
+    );
+    let reference_bytes = read_only_file(&reference_dir);
+
+    for wheel_type in [WheelType::Mod30, WheelType::Odd, WheelType::Mod210] {
+        for segment_size in [1, 29, 30, 31, 65_536] {
+            let (dir, _) = write_bitmap_with(
+                &format!("bits_{wheel_type:?}_{segment_size}"),
+                Config {
+                    prime_min: min,
+                    prime_max: max,
+                    segment_size,
+                    wheel_type,
+                    ..Config::default()
+                },
+            );
+            assert!(
+                read_only_file(&dir) == reference_bytes,
+                "{wheel_type:?}, segment {segment_size}: file differs from the Reference run"
+            );
+            std::fs::remove_dir_all(&dir).ok();
+        }
+    }
+    std::fs::remove_dir_all(&reference_dir).ok();
+}
+
+/// 停止した実行では、篩い終えた位置までしか書き出さず、ヘッダの終了値もそこになることを確認する。
+#[test]
+fn stopped_run_ends_at_sieved_position() {
+    let dir =
+        std::env::temp_dir().join(format!("sosu_wheel_bitmap_stopped_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let prime_max = 10_000_000_000;
+    let cfg = Config {
+        prime_min: 1,
+        prime_max,
+        segment_size: 100_000,
+        threads: 2,
+        output_format: OutputFormat::WheelBitmap,
+        output_dir: dir.to_string_lossy().to_string(),
+        last_prime_only: false,
+        ..Config::default()
+    };
+    let mut writer =
+        FilePrimeWriter::new(&cfg.output_dir, cfg.output_format, 0, 4096, None).unwrap();
+    writer.set_value_range(cfg.prime_min, prime_max);
+
+    // 最初のグループ（2 セグメント）を書き出したところで停止する
+    let stop_flag = AtomicBool::new(false);
+    generate_primes_cpu(&cfg, &stop_flag, &mut writer, |_| {
+        stop_flag.store(true, Ordering::SeqCst)
+    })
+    .unwrap();
+
+    let path = writer.output_file_paths()[0].clone();
+    let mut bitmap = WheelBitmap::open(&path).unwrap();
+    let end = bitmap.header().end;
+    assert!((100_000..1_000_000).contains(&end), "end = {end}");
+    assert_eq!(
+        std::fs::metadata(&path).unwrap().len(),
+        32 + bitmap.header().body_len()
+    );
+    assert_eq!(
+        bitmap.iter().collect::<io::Result<Vec<_>>>().unwrap(),
+        primes_in(1..=end).collect::<Vec<_>>()
+    );
+    assert_eq!(bitmap.is_prime(end + 1).unwrap(), None);
+
+    std::fs::remove_dir_all(&dir).ok();
+}
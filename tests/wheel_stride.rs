@@ -0,0 +1,40 @@
+use sosu_seisei_main2::config::WheelType;
+use sosu_seisei_main2::cpu_engine::{sieve_range_with, MarkingStrategy};
+use sosu_seisei_main2::sieve_math::simple_sieve;
+
+/// 事前篩パターン + ホイール刻みの倍数消去が、参照実装・単純な篩と同じ素数を返すことを確認する。
+#[test]
+fn wheel_stride_marking_matches_reference() {
+    let ranges: &[(u64, u64)] = &[
+        (0, 1),
+        (0, 100),
+        (2, 2),
+        (24, 28),
+        (29, 31),
+        (1, 100_000),
+        (999_983, 1_000_003),
+        (123_457, 654_321),
+        (4_294_967_000, 4_295_067_000),
+        // 事前篩パターンの周期（ホイールの周期 × 7·11·13·17·19）の境目をまたぐ区間
+        (646_000, 647_300),
+        (1_939_000, 1_941_000),
+        (19_398_880, 19_400_880),
+    ];
+
+    for wheel_type in [WheelType::Odd, WheelType::Mod6, WheelType::Mod30, WheelType::Mod210] {
+        for &(low, high) in ranges {
+            let stride = sieve_range_with(low, high, wheel_type, MarkingStrategy::WheelStride).unwrap();
+            let reference = sieve_range_with(low, high, wheel_type, MarkingStrategy::Reference).unwrap();
+            assert_eq!(stride, reference, "{wheel_type:?} [{low}, {high}]");
+
+            if high <= 1_000_003 {
+                let expected: Vec<u64> = simple_sieve(high)
+                    .unwrap()
+                    .into_iter()
+                    .filter(|&p| p >= low)
+                    .collect();
+                assert_eq!(stride, expected, "{wheel_type:?} [{low}, {high}]");
+            }
+        }
+    }
+}
@@ -0,0 +1,44 @@
+#![cfg(not(windows))]
+
+use sosu_seisei_main2::config::{Config, WheelType};
+use sosu_seisei_main2::cpu_engine::build_thread_pool;
+
+mod common;
+use common::run;
+
+/// 指定したスレッド数のプールが作られ、0 のときは全コアを使うことを確認する。
+#[test]
+fn thread_pool_uses_configured_thread_count() {
+    assert_eq!(build_thread_pool(1).unwrap().current_num_threads(), 1);
+    assert_eq!(build_thread_pool(3).unwrap().current_num_threads(), 3);
+    assert_eq!(
+        build_thread_pool(0).unwrap().current_num_threads(),
+        std::thread::available_parallelism().map_or(1, |n| n.get())
+    );
+}
+
+/// スレッド数を変えても出力される素数列が変わらないことを確認する。
+#[test]
+fn output_is_independent_of_thread_count() {
+    let base = Config {
+        prime_min: 10,
+        prime_max: 2_000_000,
+        segment_size: 10_000,
+        wheel_type: WheelType::Mod30,
+        ..Config::default()
+    };
+    let expected = run(&base);
+    assert_eq!(expected.len(), 148_929);
+
+    for threads in [1, 3] {
+        let cfg = Config {
+            threads,
+            ..base.clone()
+        };
+        assert_eq!(run(&cfg), expected, "threads = {threads}");
+    }
+
+    let mut cfg = base;
+    cfg.set_field("threads", "2").unwrap();
+    assert_eq!(cfg.threads, 2);
+}